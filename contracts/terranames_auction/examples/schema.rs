@@ -4,7 +4,9 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use terranames::auction::{
-    ExecuteMsg, InstantiateMsg, NameStateResponse, QueryMsg,
+    ExecuteMsg, InstantiateMsg, KeeperPoolResponse, ListNamesResponse, NameRolesResponse,
+    NameStateResponse, PendingTransferResponse, PermissionsResponse, QueryMsg,
+    ReferralBalanceResponse, StatsResponse, TldsResponse,
 };
 
 fn main() {
@@ -18,4 +20,12 @@ fn main() {
     export_schema(&schema_for!(QueryMsg), &out_dir);
 
     export_schema(&schema_for!(NameStateResponse), &out_dir);
+    export_schema(&schema_for!(NameRolesResponse), &out_dir);
+    export_schema(&schema_for!(PendingTransferResponse), &out_dir);
+    export_schema(&schema_for!(StatsResponse), &out_dir);
+    export_schema(&schema_for!(ReferralBalanceResponse), &out_dir);
+    export_schema(&schema_for!(ListNamesResponse), &out_dir);
+    export_schema(&schema_for!(TldsResponse), &out_dir);
+    export_schema(&schema_for!(KeeperPoolResponse), &out_dir);
+    export_schema(&schema_for!(PermissionsResponse), &out_dir);
 }
@@ -1,31 +1,77 @@
 use cosmwasm_std::{
-    attr, entry_point, to_binary, Addr, BankMsg, Coin, CosmosMsg, Deps,
-    DepsMut, Env, MessageInfo, QuerierWrapper, QueryResponse, Response,
-    StdResult, Uint128, WasmMsg,
+    entry_point, to_binary, Addr, Api, BankMsg, Coin, CosmosMsg, Decimal, Deps,
+    DepsMut, Env, MessageInfo, Order, OverflowError, QuerierWrapper, QueryResponse,
+    Response, StdResult, Storage, Uint128, Uint64,
 };
 
 use terranames::auction::{
-    deposit_from_seconds_ceil, deposit_from_seconds_floor, ConfigResponse,
-    AllNameStatesResponse, ExecuteMsg, InstantiateMsg, MigrateMsg,
-    NameStateItem, NameStateResponse, QueryMsg,
+    checked_deposit_from_seconds_ceil, checked_deposit_from_seconds_floor,
+    is_valid_name, is_valid_tld, split_name, ConfigResponse, AllNameStatesResponse,
+    ExecuteMsg, InstantiateMsg, MigrateMsg, ListNamesResponse, ListOrder,
+    KeeperPoolResponse, NameRolesResponse, NameStateItem, NameStateResponse,
+    NameStatus, PendingTransferResponse, Permission, PermissionsResponse,
+    QueryMsg, Role, Recipient, RecipientKind, RecipientMsg,
+    ReferralBalanceResponse, StatsResponse, TldConfigMsg, TldsResponse,
+    TransferKind, UnavailablePermission,
 };
-use terranames::root_collector::{
-    ExecuteMsg as RootCollectorExecuteMsg,
+use terranames::events::{
+    AdminAction, AdminEvent, KeeperAction, KeeperEvent, MigrateEvent,
+    NameAction, NameEvent, ReferralEvent,
 };
-use terranames::terra::deduct_coin_tax;
-use terranames::utils::Timestamp;
+use terranames::helpers::{ResolverContract, RootCollectorContract};
+use terranames::tax::TaxPolicy;
+use terranames::utils::{Timedelta, Timestamp};
 
 use crate::errors::{
     BidDepositTooLow, BidInvalidInterval, BidRateTooLow, ClosedForBids,
-    ContractError, InvalidConfig, Unauthorized, UnexpectedState, Unfunded,
+    ContractError, InvalidConfig, InvalidName, InvalidTld, MigrationInProgress,
+    NameExpired, TldExists, TooManyNames, TooManyRoles, TransferExpired,
+    TransferInvalid, Unauthorized, UnexpectedState, Unfunded, UnknownTld,
+    UnsupportedTaxPolicy,
 };
 use crate::state::{
-    collect_name_states, read_config, read_name_state, read_option_name_state,
-    store_config, store_name_state, Config, NameState, OwnerStatus,
+    collect_expired_name_states, collect_name_states,
+    collect_name_states_begun_since, collect_tlds,
+    continue_name_state_index_rebuild, count_expired_names,
+    is_index_rebuild_pending, is_legacy_name, list_name_states,
+    migrate_collector_addr, migrate_singletons, read_config,
+    read_keeper_pool, read_name_config, read_name_state, read_name_tld,
+    read_option_admin, read_option_name_state, read_option_resolver_contract,
+    read_option_tld_config, read_pending_transfer, read_referral_balance,
+    read_stats, remove_name_state, remove_pending_transfer,
+    start_name_state_index_rebuild, store_admin, store_config,
+    store_keeper_pool, store_name_state, store_pending_transfer,
+    store_referral_balance, store_resolver_contract, store_stats,
+    store_tax_policy, store_tld_config, Config, NameConfigs, NameRoles,
+    NameState, OwnerStatus, PendingTransfer, Stats,
 };
 
 type ContractResult<T> = Result<T, ContractError>;
 
+/// Maximum number of recipients of collected funds
+const MAX_RECIPIENTS: usize = 10;
+
+/// Maximum number of names pruned in one call
+const MAX_PRUNE_NAMES: usize = 30;
+
+/// Default number of names indexed by a migration from the bucket layout
+const DEFAULT_INDEX_LIMIT: u32 = 300;
+
+/// Maximum number of names indexed by a migration from the bucket layout
+const MAX_INDEX_LIMIT: u32 = 1000;
+
+/// Permissions reported by the GetPermissions query
+const PERMISSIONS: [Permission; 8] = [
+    Permission::Bid,
+    Permission::Fund,
+    Permission::Renew,
+    Permission::SetRate,
+    Permission::TransferNameOwner,
+    Permission::TransferBidOwner,
+    Permission::SetController,
+    Permission::Prune,
+];
+
 /// Return the funds of type denom attached in the request.
 fn get_sent_funds(info: &MessageInfo, denom: &str) -> Uint128 {
     info.funds
@@ -35,6 +81,84 @@ fn get_sent_funds(info: &MessageInfo, denom: &str) -> Uint128 {
         .unwrap_or_else(Uint128::zero)
 }
 
+/// Load config that applies to name
+///
+/// Fails if the TLD of the name does not exist.
+fn load_name_config(storage: &dyn Storage, name: &str) -> ContractResult<Config> {
+    match read_name_config(storage, name)? {
+        Some(config) => Ok(config),
+        None => UnknownTld {
+            tld: split_name(name).1.unwrap_or_default(),
+        }.fail(),
+    }
+}
+
+/// Fail unless name is valid or was stored before TLDs existed
+fn ensure_valid_name(storage: &dyn Storage, name: &str) -> ContractResult<()> {
+    if !is_valid_name(name) && !is_legacy_name(storage, name)? {
+        return InvalidName.fail();
+    }
+    Ok(())
+}
+
+/// Create event for an action with the resulting name state
+fn name_event(
+    action: NameAction,
+    name: &str,
+    name_state: &NameState,
+) -> Result<NameEvent, OverflowError> {
+    Ok(NameEvent {
+        rate: name_state.rate,
+        begin_time: name_state.begin_time,
+        expire_time: name_state.expire_time()?,
+        ..NameEvent::new(action, name)
+    })
+}
+
+/// Add the funds handled in a name event to the running stats
+///
+/// The counters of the stats are updated by update.
+fn record_stats<F>(
+    storage: &mut dyn Storage,
+    event: &NameEvent,
+    update: F,
+) -> StdResult<()>
+where
+    F: FnOnce(&mut Stats) -> StdResult<()>,
+{
+    let mut stats = read_stats(storage)?;
+    update(&mut stats)?;
+    stats.total_deposits = stats.total_deposits.checked_add(event.deposit)?;
+    stats.total_refunded = stats.total_refunded.checked_add(event.refund)?;
+    stats.total_collected = stats.total_collected.checked_add(event.collected)?;
+    stats.total_referral = stats.total_referral.checked_add(event.referral)?;
+    store_stats(storage, &stats)
+}
+
+/// Credit the referral share of funds bound for the collector
+///
+/// Returns the amount left for the collector and the amount credited to the
+/// referrer.
+fn credit_referral(
+    storage: &mut dyn Storage,
+    config: &Config,
+    referrer: Option<&Addr>,
+    amount: Uint128,
+) -> StdResult<(Uint128, Uint128)> {
+    let referrer = match referrer {
+        Some(referrer) => referrer,
+        None => return Ok((amount, Uint128::zero())),
+    };
+
+    let referral = amount * config.referral_share;
+    if !referral.is_zero() {
+        let balance = read_referral_balance(storage, referrer)?;
+        store_referral_balance(storage, referrer, balance.checked_add(referral)?)?;
+    }
+
+    Ok((amount.checked_sub(referral)?, referral))
+}
+
 /// Create message for refund deposits
 ///
 /// Idea: Store refunds in this contract instead of sending them back
@@ -52,7 +176,7 @@ fn refund_deposit_msg(
         BankMsg::Send {
             to_address: to.into(),
             amount: vec![
-                deduct_coin_tax(
+                config.tax_policy.deduct_coin_tax(
                     querier,
                     Coin {
                         denom: config.stable_denom.clone(),
@@ -64,55 +188,132 @@ fn refund_deposit_msg(
     ))
 }
 
-/// Create message for sending deposits to collector
-fn send_to_collector_msg(
+/// Split amount between recipients according to their shares
+///
+/// Each amount is rounded down and the remainder goes to the first recipient
+/// so the split is deterministic and sums to the full amount.
+fn split_by_share(recipients: &[Recipient], amount: Uint128) -> Vec<Uint128> {
+    let mut amounts: Vec<_> = recipients.iter()
+        .map(|recipient| amount * recipient.share)
+        .collect();
+    let total = amounts.iter().fold(Uint128::zero(), |total, &amount| total + amount);
+    if let Some(first) = amounts.first_mut() {
+        *first += amount.saturating_sub(total);
+    }
+    amounts
+}
+
+/// Create messages for sending deposits to the recipients
+fn send_to_recipients_msgs(
     querier: &QuerierWrapper,
     _env: &Env,
     config: &Config,
     _source_addr: &Addr,
     amount: Uint128,
-) -> StdResult<CosmosMsg> {
-    Ok(CosmosMsg::Wasm(
-        WasmMsg::Execute {
-            contract_addr: config.collector_addr.to_string(),
-            msg: to_binary(&RootCollectorExecuteMsg::Deposit {})?,
-            funds: vec![
-                deduct_coin_tax(
-                    querier,
-                    Coin {
-                        denom: config.stable_denom.clone(),
-                        amount,
-                    },
-                )?
-            ],
+) -> StdResult<Vec<CosmosMsg>> {
+    let amounts = split_by_share(&config.recipients, amount);
+    config.recipients.iter()
+        .zip(amounts)
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(recipient, amount)| {
+            let funds = config.tax_policy.deduct_coin_tax(
+                querier,
+                Coin {
+                    denom: config.stable_denom.clone(),
+                    amount,
+                },
+            )?;
+            Ok(match recipient.kind {
+                RecipientKind::Deposit => {
+                    RootCollectorContract(recipient.addr.clone()).deposit(funds)?
+                },
+                RecipientKind::Send => CosmosMsg::Bank(
+                    BankMsg::Send {
+                        to_address: recipient.addr.to_string(),
+                        amount: vec![funds],
+                    }
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Validate recipients and check that the shares sum to one
+fn validate_recipients(
+    api: &dyn Api,
+    recipients: Vec<RecipientMsg>,
+) -> ContractResult<Vec<Recipient>> {
+    if recipients.is_empty() || recipients.len() > MAX_RECIPIENTS {
+        return InvalidConfig.fail();
+    }
+
+    let mut total = Decimal::zero();
+    let mut validated = Vec::with_capacity(recipients.len());
+    for recipient in recipients {
+        if recipient.share.is_zero() || recipient.share > Decimal::one() {
+            return InvalidConfig.fail();
         }
-    ))
+        total = total + recipient.share;
+        validated.push(Recipient {
+            addr: api.addr_validate(&recipient.addr)?,
+            share: recipient.share,
+            kind: recipient.kind,
+        });
+    }
+
+    if total != Decimal::one() {
+        return InvalidConfig.fail();
+    }
+
+    Ok(validated)
+}
+
+/// Check that the tax policy is supported by this build
+fn validate_tax_policy(tax_policy: &TaxPolicy) -> ContractResult<()> {
+    if !tax_policy.is_supported() {
+        return UnsupportedTaxPolicy.fail();
+    }
+    Ok(())
+}
+
+/// Check the lease bounds, the referral share and the tax policy of config
+fn validate_config(config: &Config) -> ContractResult<()> {
+    if config.min_lease_secs > config.max_lease_secs || config.referral_share > Decimal::one() {
+        return InvalidConfig.fail();
+    }
+    validate_tax_policy(&config.tax_policy)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> ContractResult<Response> {
-    let collector_addr = deps.api.addr_validate(&msg.collector_addr)?;
-
-    if !(msg.min_lease_secs <= msg.max_lease_secs) {
-        return InvalidConfig.fail();
-    }
+    let recipients = validate_recipients(deps.api, msg.recipients)?;
 
     let state = Config {
-        collector_addr,
+        recipients,
+        legacy_collector_addr: None,
         stable_denom: msg.stable_denom,
         min_lease_secs: msg.min_lease_secs,
         max_lease_secs: msg.max_lease_secs,
         counter_delay_secs: msg.counter_delay_secs,
         transition_delay_secs: msg.transition_delay_secs,
         bid_delay_secs: msg.bid_delay_secs,
+        grace_period_secs: msg.grace_period_secs,
+        grace_fee_secs: msg.grace_fee_secs,
+        referral_share: msg.referral_share,
+        min_rate: msg.min_rate,
+        retention_secs: msg.retention_secs,
+        keeper_reward: msg.keeper_reward,
+        tax_policy: msg.tax_policy,
     };
+    validate_config(&state)?;
 
     store_config(deps.storage, &state)?;
+    store_admin(deps.storage, &info.sender)?;
 
     Ok(Response::default())
 }
@@ -124,13 +325,24 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> ContractResult<Response> {
+    // Names and stats are incomplete until all names are indexed
+    if is_index_rebuild_pending(deps.storage)? {
+        return MigrationInProgress.fail();
+    }
+
     match msg {
-        ExecuteMsg::BidName { name, rate } => {
-            execute_bid(deps, env, info, name, rate)
+        ExecuteMsg::BidName { name, rate, referrer } => {
+            let referrer = referrer
+                .map(|referrer| deps.api.addr_validate(&referrer))
+                .transpose()?;
+            execute_bid(deps, env, info, name, rate, referrer)
         },
-        ExecuteMsg::FundName { name, owner } => {
+        ExecuteMsg::FundName { name, owner, referrer } => {
             let owner = deps.api.addr_validate(&owner)?;
-            execute_fund(deps, env, info, name, owner)
+            let referrer = referrer
+                .map(|referrer| deps.api.addr_validate(&referrer))
+                .transpose()?;
+            execute_fund(deps, env, info, name, owner, referrer)
         },
         ExecuteMsg::SetNameRate { name, rate } => {
             execute_set_rate(deps, env, info, name, rate)
@@ -140,9 +352,51 @@ pub fn execute(
             execute_transfer_owner(deps, env, info, name, to)
         },
         ExecuteMsg::SetNameController { name, controller } => {
-            let controller = deps.api.addr_validate(&controller)?;
+            let controller = controller
+                .map(|controller| deps.api.addr_validate(&controller))
+                .transpose()?;
             execute_set_controller(deps, env, info, name, controller)
         },
+        ExecuteMsg::GrantRole { name, role, address } => {
+            let address = deps.api.addr_validate(&address)?;
+            execute_grant_role(deps, env, info, name, role, address)
+        },
+        ExecuteMsg::RevokeRole { name, role, address } => {
+            let address = deps.api.addr_validate(&address)?;
+            execute_revoke_role(deps, env, info, name, role, address)
+        },
+        ExecuteMsg::ProposeTransfer { name, to, expires } => {
+            let to = deps.api.addr_validate(&to)?;
+            execute_propose_transfer(deps, env, info, name, to, expires)
+        },
+        ExecuteMsg::AcceptTransfer { name } => {
+            execute_accept_transfer(deps, env, info, name)
+        },
+        ExecuteMsg::CancelTransfer { name } => {
+            execute_cancel_transfer(deps, env, info, name)
+        },
+        ExecuteMsg::WithdrawReferralBalance { to } => {
+            let to = to.map(|to| deps.api.addr_validate(&to)).transpose()?;
+            execute_withdraw_referral_balance(deps, env, info, to)
+        },
+        ExecuteMsg::CreateTld { tld, config } => {
+            execute_create_tld(deps, env, info, tld, config)
+        },
+        ExecuteMsg::SetResolverContract { resolver_contract } => {
+            let resolver_contract = resolver_contract
+                .map(|resolver_contract| deps.api.addr_validate(&resolver_contract))
+                .transpose()?;
+            execute_set_resolver_contract(deps, env, info, resolver_contract)
+        },
+        ExecuteMsg::FundKeeperPool {} => {
+            execute_fund_keeper_pool(deps, env, info)
+        },
+        ExecuteMsg::PruneExpired { names } => {
+            execute_prune_expired(deps, env, info, names)
+        },
+        ExecuteMsg::PruneExpiredBatch { start_after, limit } => {
+            execute_prune_expired_batch(deps, env, info, start_after, limit)
+        },
     }
 }
 
@@ -152,44 +406,62 @@ fn execute_bid(
     info: MessageInfo,
     name: String,
     rate: Uint128,
+    referrer: Option<Addr>,
 ) -> ContractResult<Response> {
+    ensure_valid_name(deps.storage, &name)?;
+
+    let config = load_name_config(deps.storage, &name)?;
+    if rate < config.min_rate {
+        return BidRateTooLow {
+            rate: config.min_rate,
+        }.fail();
+    }
+
     if let Some(name_state) = read_option_name_state(deps.storage, &name)? {
-        let config = read_config(deps.storage)?;
-        let owner_status = name_state.owner_status(&config, env.block.time.into());
+        let owner_status = name_state.owner_status(&config, env.block.time.into())?;
+        let roles = name_state.name_roles(&owner_status);
         match owner_status {
             OwnerStatus::Valid { owner, transition_reference_time } |
             OwnerStatus::TransitionDelay { owner, transition_reference_time } => {
                 execute_bid_existing(
-                    deps, env, info, name, rate, config, name_state, Some(owner),
-                    transition_reference_time,
+                    deps, env, info, name, rate, referrer, config, name_state,
+                    Some(owner), roles, transition_reference_time,
                 )
             },
             OwnerStatus::CounterDelay { name_owner: owner, transition_reference_time, .. } => {
                 execute_bid_existing(
-                    deps, env, info, name, rate, config, name_state, owner,
-                    transition_reference_time,
+                    deps, env, info, name, rate, referrer, config, name_state,
+                    owner, roles, transition_reference_time,
                 )
             },
+            OwnerStatus::Grace { .. } => {
+                ClosedForBids.fail()
+            },
             OwnerStatus::Expired { expire_time, .. } => {
                 execute_bid_new(
-                    deps, env, info, name, rate, expire_time,
+                    deps, env, info, name, rate, referrer, config, expire_time,
                 )
             },
         }
     } else {
-        execute_bid_new(deps, env, info, name, rate, Timestamp::zero())
+        execute_bid_new(
+            deps, env, info, name, rate, referrer, config, Timestamp::zero(),
+        )
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_bid_existing(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     name: String,
     rate: Uint128,
+    referrer: Option<Addr>,
     config: Config,
     mut name_state: NameState,
     owner: Option<Addr>,
+    roles: NameRoles,
     transition_reference_time: Timestamp,
 ) -> ContractResult<Response> {
     if info.sender == name_state.owner {
@@ -198,11 +470,12 @@ fn execute_bid_existing(
 
     let seconds_spent_since_bid = match name_state.seconds_spent_since_bid(env.block.time.into()) {
         Some(seconds_spent) => seconds_spent,
-        None => panic!("Invalid block time"),
+        None => return UnexpectedState.fail(),
     };
 
+    let bid_delay_end = config.counter_delay_secs.checked_add(config.bid_delay_secs)?;
     if seconds_spent_since_bid >= config.counter_delay_secs &&
-            seconds_spent_since_bid < config.counter_delay_secs + config.bid_delay_secs &&
+            seconds_spent_since_bid < bid_delay_end &&
             !name_state.rate.is_zero() {
         return ClosedForBids.fail();
     }
@@ -214,7 +487,7 @@ fn execute_bid_existing(
     }
 
     let msg_deposit = get_sent_funds(&info, &config.stable_denom);
-    let deposit_spent = deposit_from_seconds_ceil(seconds_spent_since_bid, name_state.rate);
+    let deposit_spent = checked_deposit_from_seconds_ceil(seconds_spent_since_bid, name_state.rate)?;
     let deposit_left = name_state.begin_deposit.saturating_sub(deposit_spent);
 
     // TODO Consider adding a small delta that could be given back to the previous
@@ -228,15 +501,17 @@ fn execute_bid_existing(
     // TODO Consider allowing the existing owner a slightly higher max deposit
     // at the same rate. The increase in deposit could be equal to the rate
     // increase times the length of the previous lease plus a small penalty.
-    let min_deposit = deposit_from_seconds_ceil(config.min_lease_secs, rate);
-    let max_deposit = deposit_from_seconds_floor(config.max_lease_secs, rate);
+    let min_deposit = checked_deposit_from_seconds_ceil(config.min_lease_secs, rate)?;
+    let max_deposit = checked_deposit_from_seconds_floor(config.max_lease_secs, rate)?;
     if msg_deposit < min_deposit || msg_deposit > max_deposit {
         return BidInvalidInterval.fail();
     }
 
     let previous_bidder = name_state.owner;
+    let counter_bid = seconds_spent_since_bid < config.counter_delay_secs;
 
     name_state.previous_owner = owner.clone();
+    name_state.previous_roles = roles.clone();
     name_state.previous_transition_reference_time = transition_reference_time;
     name_state.owner = info.sender.clone();
     name_state.rate = rate;
@@ -244,14 +519,17 @@ fn execute_bid_existing(
     name_state.begin_deposit = msg_deposit;
 
     // Only update transition reference time if ownership is assigned to a new
-    // owner.
+    // owner. The delegated roles are reset for a new owner.
     if Some(name_state.owner.clone()) != owner {
         name_state.transition_reference_time = env.block.time.into();
+        name_state.roles = NameRoles::default();
     } else {
         name_state.transition_reference_time = name_state.previous_transition_reference_time;
+        name_state.roles = roles;
     }
 
     store_name_state(deps.storage, &name, &name_state)?;
+    remove_pending_transfer(deps.storage, &name);
 
     let mut messages = vec![];
 
@@ -273,57 +551,71 @@ fn execute_bid_existing(
 
     // Send excess deposit to collector
     let excess_deposit = msg_deposit.checked_sub(deposit_left)?;
-    messages.push(
-        send_to_collector_msg(
-            &deps.querier,
-            &env,
-            &config,
-            &info.sender,
-            excess_deposit,
-        )?,
-    );
-
-    let mut attributes = vec![
-        attr("action", "bid"),
-        attr("owner", info.sender),
-        attr("rate", rate),
-        attr("deposit", msg_deposit),
-        attr("refund", deposit_left),
-    ];
-
-    if let Some(previous_owner) = name_state.previous_owner {
-        attributes.push(
-            attr("previous_owner", previous_owner),
+    let (collected, referral) = credit_referral(
+        deps.storage, &config, referrer.as_ref(), excess_deposit,
+    )?;
+    if !collected.is_zero() {
+        messages.extend(
+            send_to_recipients_msgs(
+                &deps.querier,
+                &env,
+                &config,
+                &info.sender,
+                collected,
+            )?
         );
     }
 
+    let event = NameEvent {
+        old_owner: Some(previous_bidder),
+        new_owner: Some(info.sender),
+        deposit: msg_deposit,
+        refund: deposit_left,
+        collected,
+        referrer,
+        referral,
+        ..name_event(NameAction::Bid, &name, &name_state)?
+    };
+    record_stats(deps.storage, &event, |stats| {
+        stats.total_bids = stats.total_bids.checked_add(Uint64::new(1))?;
+        if counter_bid {
+            stats.total_counter_bids = stats.total_counter_bids.checked_add(Uint64::new(1))?;
+        }
+        Ok(())
+    })?;
+
     Ok(Response::new()
         .add_messages(messages)
-        .add_attributes(attributes)
+        .add_event(event.into())
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_bid_new(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     name: String,
     rate: Uint128,
+    referrer: Option<Addr>,
+    config: Config,
     transition_reference_time: Timestamp,
 ) -> ContractResult<Response> {
-    let config = read_config(deps.storage)?;
     let msg_deposit = get_sent_funds(&info, &config.stable_denom);
     let begin_time = env.block.time.into();
 
-    let min_deposit = deposit_from_seconds_ceil(config.min_lease_secs, rate);
-    let max_deposit = deposit_from_seconds_floor(config.max_lease_secs, rate);
+    let min_deposit = checked_deposit_from_seconds_ceil(config.min_lease_secs, rate)?;
+    let max_deposit = checked_deposit_from_seconds_floor(config.max_lease_secs, rate)?;
     if msg_deposit < min_deposit || msg_deposit > max_deposit {
         return BidInvalidInterval.fail();
     }
 
+    // Names that expired are bid on again without being pruned first
+    let new_name = read_option_name_state(deps.storage, &name)?.is_none();
     let name_state = NameState {
         owner: info.sender.clone(),
-        controller: None,
+        roles: NameRoles::default(),
+        legacy_controller: None,
         transition_reference_time,
 
         begin_time,
@@ -331,31 +623,49 @@ fn execute_bid_new(
         rate,
 
         previous_owner: None,
+        previous_roles: NameRoles::default(),
         previous_transition_reference_time: Timestamp::zero(),
     };
     store_name_state(deps.storage, &name, &name_state)?;
+    remove_pending_transfer(deps.storage, &name);
 
     let mut messages = vec![];
 
     // Send deposit to fund
-    if !msg_deposit.is_zero() {
-        messages.push(
-            send_to_collector_msg(
+    let (collected, referral) = credit_referral(
+        deps.storage, &config, referrer.as_ref(), msg_deposit,
+    )?;
+    if !collected.is_zero() {
+        messages.extend(
+            send_to_recipients_msgs(
                 &deps.querier,
                 &env,
                 &config,
                 &info.sender,
-                msg_deposit,
-            )?,
+                collected,
+            )?
         );
     }
 
+    let event = NameEvent {
+        new_owner: Some(info.sender),
+        deposit: msg_deposit,
+        collected,
+        referrer,
+        referral,
+        ..name_event(NameAction::Bid, &name, &name_state)?
+    };
+    record_stats(deps.storage, &event, |stats| {
+        stats.total_bids = stats.total_bids.checked_add(Uint64::new(1))?;
+        if new_name {
+            stats.names = stats.names.checked_add(Uint64::new(1))?;
+        }
+        Ok(())
+    })?;
+
     Ok(Response::new()
         .add_messages(messages)
-        .add_attribute("action", "bid")
-        .add_attribute("owner", info.sender)
-        .add_attribute("rate", rate)
-        .add_attribute("deposit", msg_deposit)
+        .add_event(event.into())
     )
 }
 
@@ -365,8 +675,9 @@ fn execute_fund(
     info: MessageInfo,
     name: String,
     owner: Addr,
+    referrer: Option<Addr>,
 ) -> ContractResult<Response> {
-    let config = read_config(deps.storage)?;
+    let config = load_name_config(deps.storage, &name)?;
     let msg_deposit = get_sent_funds(&info, &config.stable_denom);
     let mut name_state = read_name_state(deps.storage, &name)?;
 
@@ -379,8 +690,37 @@ fn execute_fund(
         return UnexpectedState.fail();
     }
 
-    let combined_deposit = msg_deposit + name_state.begin_deposit;
-    let max_deposit = name_state.max_allowed_deposit(&config, env.block.time.into());
+    let current_time = Timestamp::from(env.block.time);
+    let owner_status = name_state.owner_status(&config, current_time)?;
+    let fee = match owner_status {
+        OwnerStatus::Grace { .. } => {
+            // Only the owner can renew in the grace period. The deposit has
+            // to cover the lapsed time as well as the renewal fee.
+            let roles = name_state.name_roles(&owner_status);
+            if !owner_status.can_renew(&info.sender, &roles) {
+                return Unauthorized.fail();
+            }
+
+            let seconds_spent = current_time.checked_sub(name_state.begin_time)?;
+            let fee = checked_deposit_from_seconds_ceil(config.grace_fee_secs, name_state.rate)?;
+            let min_deposit = checked_deposit_from_seconds_ceil(
+                seconds_spent.checked_add(Timedelta::from_seconds(1))?,
+                name_state.rate,
+            )?.saturating_sub(name_state.begin_deposit).checked_add(fee)?;
+            if msg_deposit < min_deposit {
+                return BidDepositTooLow {
+                    deposit: min_deposit,
+                }.fail();
+            }
+
+            fee
+        },
+        OwnerStatus::Expired { .. } => return NameExpired.fail(),
+        _ => Uint128::zero(),
+    };
+
+    let combined_deposit = msg_deposit.checked_sub(fee)?.checked_add(name_state.begin_deposit)?;
+    let max_deposit = name_state.max_allowed_deposit(&config, current_time)?;
     if combined_deposit > max_deposit {
         return BidInvalidInterval.fail();
     }
@@ -388,23 +728,41 @@ fn execute_fund(
     name_state.begin_deposit = combined_deposit;
     store_name_state(deps.storage, &name, &name_state)?;
 
+    // Send deposit to fund
+    let (collected, referral) = credit_referral(
+        deps.storage, &config, referrer.as_ref(), msg_deposit,
+    )?;
     let mut messages = vec![];
+    if !collected.is_zero() {
+        messages.extend(
+            send_to_recipients_msgs(
+                &deps.querier,
+                &env,
+                &config,
+                &info.sender,
+                collected,
+            )?
+        );
+    }
 
-    // Send deposit to fund
-    messages.push(
-        send_to_collector_msg(
-            &deps.querier,
-            &env,
-            &config,
-            &info.sender,
-            msg_deposit,
-        )?,
-    );
+    let event = NameEvent {
+        old_owner: Some(name_state.owner.clone()),
+        new_owner: Some(name_state.owner.clone()),
+        deposit: msg_deposit,
+        collected,
+        referrer,
+        referral,
+        fee,
+        ..name_event(NameAction::Fund, &name, &name_state)?
+    };
+    record_stats(deps.storage, &event, |stats| {
+        stats.total_fundings = stats.total_fundings.checked_add(Uint64::new(1))?;
+        Ok(())
+    })?;
 
     Ok(Response::new()
         .add_messages(messages)
-        .add_attribute("action", "fund")
-        .add_attribute("deposit", combined_deposit)
+        .add_event(event.into())
     )
 }
 
@@ -415,22 +773,30 @@ fn execute_set_rate(
     name: String,
     rate: Uint128,
 ) -> ContractResult<Response> {
-    let config = read_config(deps.storage)?;
+    let config = load_name_config(deps.storage, &name)?;
     let mut name_state = read_name_state(deps.storage, &name)?;
     let sender_canonical = info.sender;
-    let owner_status = name_state.owner_status(&config, env.block.time.into());
+    let owner_status = name_state.owner_status(&config, env.block.time.into())?;
+
+    let roles = name_state.name_roles(&owner_status);
 
-    if !owner_status.can_set_rate(&sender_canonical) {
+    if !owner_status.can_set_rate(&sender_canonical, &roles) {
         return Unauthorized.fail();
     }
 
+    if rate < config.min_rate {
+        return BidRateTooLow {
+            rate: config.min_rate,
+        }.fail();
+    }
+
     // Always round up spent deposit to avoid charging too little.
     let seconds_spent = Timestamp::from(env.block.time).checked_sub(name_state.begin_time)?;
-    let spent_deposit = deposit_from_seconds_ceil(seconds_spent, name_state.rate);
+    let spent_deposit = checked_deposit_from_seconds_ceil(seconds_spent, name_state.rate)?;
     let new_deposit = name_state.begin_deposit.saturating_sub(spent_deposit); // TODO <-- add test for this: last block spends slightly more than total deposit
 
-    let min_deposit = deposit_from_seconds_ceil(config.min_lease_secs, rate);
-    let max_deposit = deposit_from_seconds_floor(config.max_lease_secs, rate);
+    let min_deposit = checked_deposit_from_seconds_ceil(config.min_lease_secs, rate)?;
+    let max_deposit = checked_deposit_from_seconds_floor(config.max_lease_secs, rate)?;
 
     if new_deposit < min_deposit || new_deposit > max_deposit {
         return BidInvalidInterval.fail();
@@ -440,13 +806,22 @@ fn execute_set_rate(
     name_state.begin_time = env.block.time.into();
     name_state.begin_deposit = new_deposit;
     name_state.previous_owner = Some(name_state.owner.clone());
+    name_state.previous_roles = name_state.roles.clone();
     name_state.previous_transition_reference_time = name_state.transition_reference_time;
     store_name_state(deps.storage, &name, &name_state)?;
 
+    let event = NameEvent {
+        old_owner: Some(name_state.owner.clone()),
+        new_owner: Some(name_state.owner.clone()),
+        ..name_event(NameAction::SetRate, &name, &name_state)?
+    };
+    record_stats(deps.storage, &event, |stats| {
+        stats.total_rate_changes = stats.total_rate_changes.checked_add(Uint64::new(1))?;
+        Ok(())
+    })?;
+
     Ok(Response::new()
-        .add_attribute("action", "set_rate")
-        .add_attribute("rate", rate)
-        .add_attribute("deposit", new_deposit)
+        .add_event(event.into())
     )
 }
 
@@ -457,125 +832,766 @@ fn execute_transfer_owner(
     name: String,
     to: Addr,
 ) -> ContractResult<Response> {
-    let config = read_config(deps.storage)?;
+    let config = load_name_config(deps.storage, &name)?;
     let mut name_state = read_name_state(deps.storage, &name)?;
     let sender_canonical = info.sender;
-    let owner_status = name_state.owner_status(&config, env.block.time.into());
+    let owner_status = name_state.owner_status(&config, env.block.time.into())?;
 
     let new_owner = to;
 
-    if owner_status.can_transfer_name_owner(&sender_canonical) {
+    let (kind, from) = match transfer_authorization(&name_state, &owner_status, &sender_canonical) {
+        Some(authorization) => authorization,
+        None => return Unauthorized.fail(),
+    };
+
+    apply_transfer(&mut name_state, &owner_status, kind, new_owner.clone());
+    store_name_state(deps.storage, &name, &name_state)?;
+
+    // Any proposed transfer is superseded by the direct transfer
+    remove_pending_transfer(deps.storage, &name);
+
+    let event = NameEvent {
+        old_owner: Some(from),
+        new_owner: Some(new_owner),
+        ..name_event(NameAction::Transfer, &name, &name_state)?
+    };
+
+    Ok(Response::new()
+        .add_event(event.into())
+    )
+}
+
+/// Return the ownership that sender is allowed to transfer
+///
+/// Returns the kind of transfer and the current holder of the ownership that
+/// is transferred.
+fn transfer_authorization(
+    name_state: &NameState,
+    owner_status: &OwnerStatus,
+    sender: &Addr,
+) -> Option<(TransferKind, Addr)> {
+    let roles = name_state.name_roles(owner_status);
+    if owner_status.can_transfer_name_owner(sender, &roles) {
+        owner_status.name_owner()
+            .map(|owner| (TransferKind::NameOwner, owner.clone()))
+    } else if owner_status.can_transfer_bid_owner(sender, &name_state.roles) {
+        // This lets the current highest bid holder transfer their bid.
         match owner_status {
-            // In the counter-delay state, the current owner is determined by
-            // previous_owner since owner is the current highest bid holder.
-            OwnerStatus::CounterDelay { .. } => {
-                name_state.previous_owner = Some(new_owner.clone());
+            OwnerStatus::CounterDelay { bid_owner, .. } => {
+                Some((TransferKind::BidOwner, bid_owner.clone()))
             },
-            _ => {
-                name_state.owner = new_owner.clone();
-            }
+            _ => None,
         }
-    } else if owner_status.can_transfer_bid_owner(&sender_canonical) {
-        // This lets the current highest bid holder transfer their bid.
-        name_state.owner = new_owner.clone();
     } else {
-        return Unauthorized.fail();
+        None
+    }
+}
+
+/// Move ownership of kind to the new owner
+fn apply_transfer(
+    name_state: &mut NameState,
+    owner_status: &OwnerStatus,
+    kind: TransferKind,
+    new_owner: Addr,
+) {
+    // The delegated roles belonged to the previous owner so they are reset
+    // on transfer.
+    match (kind, owner_status) {
+        // In the counter-delay state, the current owner is determined by
+        // previous_owner since owner is the current highest bid holder.
+        (TransferKind::NameOwner, OwnerStatus::CounterDelay { .. }) => {
+            name_state.previous_owner = Some(new_owner);
+            name_state.previous_roles = NameRoles::default();
+        },
+        _ => {
+            name_state.owner = new_owner;
+            name_state.roles = NameRoles::default();
+        },
     }
+}
 
-    store_name_state(deps.storage, &name, &name_state)?;
+fn execute_propose_transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+    to: Addr,
+    expires: Option<Timestamp>,
+) -> ContractResult<Response> {
+    let config = load_name_config(deps.storage, &name)?;
+    let name_state = read_name_state(deps.storage, &name)?;
+    let sender_canonical = info.sender;
+    let current_time = env.block.time.into();
+    let owner_status = name_state.owner_status(&config, current_time)?;
+
+    let (kind, from) = match transfer_authorization(&name_state, &owner_status, &sender_canonical) {
+        Some(authorization) => authorization,
+        None => return Unauthorized.fail(),
+    };
+
+    let pending_transfer = PendingTransfer {
+        from,
+        to,
+        kind,
+        expires,
+    };
+    if pending_transfer.is_expired(current_time) {
+        return TransferExpired.fail();
+    }
+
+    store_pending_transfer(deps.storage, &name, &pending_transfer)?;
+
+    let event = NameEvent {
+        old_owner: Some(pending_transfer.from.clone()),
+        new_owner: Some(pending_transfer.from),
+        address: Some(pending_transfer.to),
+        ..name_event(NameAction::ProposeTransfer, &name, &name_state)?
+    };
 
     Ok(Response::new()
-        .add_attribute("action", "transfer_owner")
-        .add_attribute("owner", new_owner)
+        .add_event(event.into())
     )
 }
 
-fn execute_set_controller(
+fn execute_accept_transfer(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     name: String,
-    controller: Addr,
 ) -> ContractResult<Response> {
-    let config = read_config(deps.storage)?;
+    let config = load_name_config(deps.storage, &name)?;
     let mut name_state = read_name_state(deps.storage, &name)?;
-    let sender_canonical = info.sender;
-    let owner_status = name_state.owner_status(&config, env.block.time.into());
+    let pending_transfer = read_pending_transfer(deps.storage, &name)?;
+    let current_time = env.block.time.into();
+    let owner_status = name_state.owner_status(&config, current_time)?;
 
-    if !owner_status.can_set_controller(&sender_canonical) {
+    if info.sender != pending_transfer.to {
         return Unauthorized.fail();
     }
 
-    name_state.controller = Some(controller.clone());
+    if pending_transfer.is_expired(current_time) {
+        return TransferExpired.fail();
+    }
+
+    // The proposing owner must still hold the same ownership, otherwise the
+    // name has changed hands since the proposal.
+    let authorization = transfer_authorization(&name_state, &owner_status, &pending_transfer.from);
+    if authorization != Some((pending_transfer.kind, pending_transfer.from.clone())) {
+        return TransferInvalid.fail();
+    }
+
+    apply_transfer(&mut name_state, &owner_status, pending_transfer.kind, pending_transfer.to.clone());
     store_name_state(deps.storage, &name, &name_state)?;
+    remove_pending_transfer(deps.storage, &name);
+
+    let event = NameEvent {
+        old_owner: Some(pending_transfer.from),
+        new_owner: Some(pending_transfer.to),
+        ..name_event(NameAction::Transfer, &name, &name_state)?
+    };
 
     Ok(Response::new()
-        .add_attribute("action", "set_controller")
-        .add_attribute("controller", controller)
+        .add_event(event.into())
     )
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(
-    deps: Deps,
+fn execute_cancel_transfer(
+    deps: DepsMut,
     env: Env,
-    msg: QueryMsg,
-) -> ContractResult<QueryResponse> {
-    match msg {
-        QueryMsg::Config {} => {
-            Ok(to_binary(&query_config(deps)?)?)
+    info: MessageInfo,
+    name: String,
+) -> ContractResult<Response> {
+    let config = load_name_config(deps.storage, &name)?;
+    let name_state = read_name_state(deps.storage, &name)?;
+    let pending_transfer = read_pending_transfer(deps.storage, &name)?;
+    let sender_canonical = info.sender;
+    let owner_status = name_state.owner_status(&config, env.block.time.into())?;
+
+    // The proposal can be cancelled by the proposer or by the current holder
+    // of the ownership being transferred.
+    let current_holder = match pending_transfer.kind {
+        TransferKind::NameOwner => owner_status.name_owner(),
+        TransferKind::BidOwner => match &owner_status {
+            OwnerStatus::CounterDelay { bid_owner, .. } => Some(bid_owner),
+            _ => None,
         },
-        QueryMsg::GetNameState { name } => {
-            Ok(to_binary(&query_name_state(deps, env, name)?)?)
+    };
+    if sender_canonical != pending_transfer.from &&
+        current_holder != Some(&sender_canonical) {
+        return Unauthorized.fail();
+    }
+
+    remove_pending_transfer(deps.storage, &name);
+
+    let event = NameEvent {
+        old_owner: Some(pending_transfer.from.clone()),
+        new_owner: Some(pending_transfer.from),
+        address: Some(pending_transfer.to),
+        ..name_event(NameAction::CancelTransfer, &name, &name_state)?
+    };
+
+    Ok(Response::new()
+        .add_event(event.into())
+    )
+}
+
+/// Update the roles delegated by the current name owner
+fn update_name_roles<F>(
+    name_state: &mut NameState,
+    owner_status: &OwnerStatus,
+    update: F,
+) -> ContractResult<()>
+where
+    F: Fn(&mut NameRoles) -> ContractResult<()>,
+{
+    match owner_status {
+        // In the counter-delay state, the name is controlled by the previous
+        // owner. The roles also apply to the highest bid if it is held by the
+        // same owner (e.g. following a rate change).
+        OwnerStatus::CounterDelay { name_owner, bid_owner, .. } => {
+            update(&mut name_state.previous_roles)?;
+            if name_owner.as_ref() == Some(bid_owner) {
+                update(&mut name_state.roles)?;
+            }
         },
-        QueryMsg::GetAllNameStates { start_after, limit } => {
-            Ok(to_binary(&query_all_name_states(deps, env, start_after, limit)?)?)
+        _ => {
+            update(&mut name_state.roles)?;
         },
     }
+    Ok(())
 }
 
-fn query_config(
-    deps: Deps,
-) -> ContractResult<ConfigResponse> {
-    let config = read_config(deps.storage)?;
+fn execute_set_controller(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+    controller: Option<Addr>,
+) -> ContractResult<Response> {
+    let config = load_name_config(deps.storage, &name)?;
+    let mut name_state = read_name_state(deps.storage, &name)?;
+    let sender_canonical = info.sender;
+    let owner_status = name_state.owner_status(&config, env.block.time.into())?;
 
-    Ok(ConfigResponse {
-        collector_addr: config.collector_addr,
-        stable_denom: config.stable_denom,
-        min_lease_secs: config.min_lease_secs,
-        max_lease_secs: config.max_lease_secs,
-        counter_delay_secs: config.counter_delay_secs,
-        transition_delay_secs: config.transition_delay_secs,
-        bid_delay_secs: config.bid_delay_secs,
-    })
-}
+    if !owner_status.can_set_controller(&sender_canonical) {
+        return Unauthorized.fail();
+    }
 
-fn create_name_state_response(
-    config: &Config,
-    current_time: Timestamp,
-    name_state: &NameState,
-) -> NameStateResponse {
-    let counter_delay_end = name_state.counter_delay_end(config);
-    let transition_delay_end = name_state.transition_delay_end(config);
-    let bid_delay_end = name_state.bid_delay_end(config);
-    let expire_time = name_state.expire_time();
+    update_name_roles(&mut name_state, &owner_status, |roles| {
+        roles.revoke_all(Role::Controller);
+        if let Some(controller) = &controller {
+            if !roles.grant(Role::Controller, controller.clone()) {
+                return TooManyRoles.fail();
+            }
+        }
+        Ok(())
+    })?;
+    store_name_state(deps.storage, &name, &name_state)?;
 
-    let owner_status = name_state.owner_status(&config, current_time);
-    let current_deposit = name_state.current_deposit(current_time);
+    let owner = owner_status.name_owner().cloned();
+    let event = NameEvent {
+        old_owner: owner.clone(),
+        new_owner: owner,
+        role: Some(Role::Controller),
+        address: controller,
+        ..name_event(NameAction::SetController, &name, &name_state)?
+    };
 
-    let (name_owner, bid_owner) = match owner_status {
-        OwnerStatus::Expired { .. } =>
+    Ok(Response::new()
+        .add_event(event.into())
+    )
+}
+
+fn execute_grant_role(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+    role: Role,
+    address: Addr,
+) -> ContractResult<Response> {
+    let config = load_name_config(deps.storage, &name)?;
+    let mut name_state = read_name_state(deps.storage, &name)?;
+    let sender_canonical = info.sender;
+    let owner_status = name_state.owner_status(&config, env.block.time.into())?;
+
+    if !owner_status.can_set_controller(&sender_canonical) {
+        return Unauthorized.fail();
+    }
+
+    update_name_roles(&mut name_state, &owner_status, |roles| {
+        if !roles.grant(role, address.clone()) {
+            return TooManyRoles.fail();
+        }
+        Ok(())
+    })?;
+    store_name_state(deps.storage, &name, &name_state)?;
+
+    let owner = owner_status.name_owner().cloned();
+    let event = NameEvent {
+        old_owner: owner.clone(),
+        new_owner: owner,
+        role: Some(role),
+        address: Some(address),
+        ..name_event(NameAction::GrantRole, &name, &name_state)?
+    };
+
+    Ok(Response::new()
+        .add_event(event.into())
+    )
+}
+
+fn execute_revoke_role(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+    role: Role,
+    address: Addr,
+) -> ContractResult<Response> {
+    let config = load_name_config(deps.storage, &name)?;
+    let mut name_state = read_name_state(deps.storage, &name)?;
+    let sender_canonical = info.sender;
+    let owner_status = name_state.owner_status(&config, env.block.time.into())?;
+
+    if !owner_status.can_set_controller(&sender_canonical) {
+        return Unauthorized.fail();
+    }
+
+    update_name_roles(&mut name_state, &owner_status, |roles| {
+        roles.revoke(role, &address);
+        Ok(())
+    })?;
+    store_name_state(deps.storage, &name, &name_state)?;
+
+    let owner = owner_status.name_owner().cloned();
+    let event = NameEvent {
+        old_owner: owner.clone(),
+        new_owner: owner,
+        role: Some(role),
+        address: Some(address),
+        ..name_event(NameAction::RevokeRole, &name, &name_state)?
+    };
+
+    Ok(Response::new()
+        .add_event(event.into())
+    )
+}
+
+fn execute_withdraw_referral_balance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    to: Option<Addr>,
+) -> ContractResult<Response> {
+    let config = read_config(deps.storage)?;
+    let balance = read_referral_balance(deps.storage, &info.sender)?;
+    if balance.is_zero() {
+        return Unfunded.fail();
+    }
+
+    store_referral_balance(deps.storage, &info.sender, Uint128::zero())?;
+
+    let recipient = to.unwrap_or_else(|| info.sender.clone());
+    let messages = vec![
+        refund_deposit_msg(
+            &deps.querier,
+            &env,
+            &config,
+            &recipient,
+            balance,
+        )?,
+    ];
+
+    let event = ReferralEvent {
+        referrer: info.sender,
+        recipient,
+        amount: balance,
+    };
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_event(event.into())
+    )
+}
+
+fn execute_create_tld(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    tld: String,
+    msg: TldConfigMsg,
+) -> ContractResult<Response> {
+    if read_option_admin(deps.storage)?.as_ref() != Some(&info.sender) {
+        return Unauthorized.fail();
+    }
+
+    if !is_valid_tld(&tld) {
+        return InvalidTld.fail();
+    }
+
+    if read_option_tld_config(deps.storage, &tld)?.is_some() {
+        return TldExists.fail();
+    }
+
+    // The stablecoin and the tax policy are shared between all TLDs
+    let default_config = read_config(deps.storage)?;
+    let config = Config {
+        recipients: validate_recipients(deps.api, msg.recipients)?,
+        legacy_collector_addr: None,
+        stable_denom: default_config.stable_denom,
+        min_lease_secs: msg.min_lease_secs,
+        max_lease_secs: msg.max_lease_secs,
+        counter_delay_secs: msg.counter_delay_secs,
+        transition_delay_secs: msg.transition_delay_secs,
+        bid_delay_secs: msg.bid_delay_secs,
+        grace_period_secs: msg.grace_period_secs,
+        grace_fee_secs: msg.grace_fee_secs,
+        referral_share: msg.referral_share,
+        min_rate: msg.min_rate,
+        retention_secs: msg.retention_secs,
+        keeper_reward: msg.keeper_reward,
+        tax_policy: default_config.tax_policy,
+    };
+    validate_config(&config)?;
+
+    store_tld_config(deps.storage, &tld, &config)?;
+
+    let event = AdminEvent {
+        action: AdminAction::CreateTld,
+        admin: info.sender,
+        tld: Some(tld),
+        resolver_contract: None,
+    };
+
+    Ok(Response::new()
+        .add_event(event.into())
+    )
+}
+
+fn execute_set_resolver_contract(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    resolver_contract: Option<Addr>,
+) -> ContractResult<Response> {
+    if read_option_admin(deps.storage)?.as_ref() != Some(&info.sender) {
+        return Unauthorized.fail();
+    }
+
+    store_resolver_contract(deps.storage, resolver_contract.as_ref())?;
+
+    let event = AdminEvent {
+        action: AdminAction::SetResolverContract,
+        admin: info.sender,
+        tld: None,
+        resolver_contract,
+    };
+
+    Ok(Response::new()
+        .add_event(event.into())
+    )
+}
+
+fn execute_fund_keeper_pool(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> ContractResult<Response> {
+    let config = read_config(deps.storage)?;
+    let msg_deposit = get_sent_funds(&info, &config.stable_denom);
+    if msg_deposit.is_zero() {
+        return Unfunded.fail();
+    }
+
+    let balance = read_keeper_pool(deps.storage)?.checked_add(msg_deposit)?;
+    store_keeper_pool(deps.storage, balance)?;
+
+    let event = KeeperEvent {
+        action: KeeperAction::FundKeeperPool,
+        address: info.sender,
+        amount: msg_deposit,
+        balance,
+        pruned: 0,
+        last_scanned: None,
+    };
+
+    Ok(Response::new()
+        .add_event(event.into())
+    )
+}
+
+fn execute_prune_expired(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    names: Vec<String>,
+) -> ContractResult<Response> {
+    if names.len() > MAX_PRUNE_NAMES {
+        return TooManyNames.fail();
+    }
+
+    let mut name_states = Vec::with_capacity(names.len());
+    for name in names {
+        if let Some(name_state) = read_option_name_state(deps.storage, &name)? {
+            name_states.push((name, name_state));
+        }
+    }
+
+    prune_name_states(deps, env, info, name_states, None)
+}
+
+fn execute_prune_expired_batch(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> ContractResult<Response> {
+    let name_states = collect_expired_name_states(
+        deps.storage,
+        env.block.time.into(),
+        start_after.as_deref(),
+        limit,
+    )?;
+    let last_scanned = name_states.last().map(|(name, _)| name.clone());
+
+    prune_name_states(deps, env, info, name_states, last_scanned)
+}
+
+/// Remove the name states that are past the retention period
+///
+/// The keeper is rewarded from the keeper pool for each pruned name as long
+/// as the pool lasts, and the resolver is told to drop the values of the
+/// pruned names. The last scanned name is reported in the keeper event.
+fn prune_name_states(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name_states: Vec<(String, NameState)>,
+    last_scanned: Option<String>,
+) -> ContractResult<Response> {
+    let configs = NameConfigs::load(deps.storage)?;
+    let current_time = env.block.time.into();
+    let mut pool = read_keeper_pool(deps.storage)?;
+    let mut reward = Uint128::zero();
+
+    let mut pruned = vec![];
+    let mut events = vec![];
+    for (name, name_state) in name_states {
+        let config = match configs.get(read_name_tld(deps.storage, &name)?) {
+            Some(config) => config,
+            None => continue,
+        };
+        if !name_state.can_prune(config, current_time)? {
+            continue;
+        }
+
+        remove_name_state(deps.storage, &name)?;
+        remove_pending_transfer(deps.storage, &name);
+
+        let name_reward = config.keeper_reward.min(pool);
+        pool = pool.checked_sub(name_reward)?;
+        reward = reward.checked_add(name_reward)?;
+
+        let event = NameEvent {
+            old_owner: Some(name_state.owner.clone()),
+            address: Some(info.sender.clone()),
+            ..name_event(NameAction::Prune, &name, &name_state)?
+        };
+        events.push(event.into());
+        pruned.push(name);
+    }
+    store_keeper_pool(deps.storage, pool)?;
+
+    let pruned_count = pruned.len();
+    let mut stats = read_stats(deps.storage)?;
+    stats.names = stats.names.checked_sub(Uint64::new(pruned_count as u64))?;
+    store_stats(deps.storage, &stats)?;
+
+    let mut messages = vec![];
+    if !reward.is_zero() {
+        messages.push(
+            refund_deposit_msg(
+                &deps.querier,
+                &env,
+                configs.default_config(),
+                &info.sender,
+                reward,
+            )?
+        );
+    }
+
+    if !pruned.is_empty() {
+        if let Some(resolver_contract) = read_option_resolver_contract(deps.storage)? {
+            messages.push(ResolverContract(resolver_contract).drop_name_values(pruned)?);
+        }
+    }
+
+    let event = KeeperEvent {
+        action: KeeperAction::PruneExpired,
+        address: info.sender,
+        amount: reward,
+        balance: pool,
+        pruned: pruned_count as u64,
+        last_scanned,
+    };
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_events(events)
+        .add_event(event.into())
+    )
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(
+    deps: Deps,
+    env: Env,
+    msg: QueryMsg,
+) -> ContractResult<QueryResponse> {
+    match msg {
+        QueryMsg::Config {} => {
+            Ok(to_binary(&query_config(deps)?)?)
+        },
+        QueryMsg::GetNameState { name } => {
+            Ok(to_binary(&query_name_state(deps, env, name)?)?)
+        },
+        QueryMsg::GetAllNameStates { start_after, limit } => {
+            Ok(to_binary(&query_all_name_states(deps, env, start_after, limit)?)?)
+        },
+        QueryMsg::GetNameRoles { name } => {
+            Ok(to_binary(&query_name_roles(deps, env, name)?)?)
+        },
+        QueryMsg::GetPendingTransfer { name } => {
+            Ok(to_binary(&query_pending_transfer(deps, env, name)?)?)
+        },
+        QueryMsg::ListNames {
+            start_after, limit, order, status, prefix, min_rate, owner,
+        } => {
+            let owner = owner.map(|owner| deps.api.addr_validate(&owner)).transpose()?;
+            let filter = ListNamesFilter { status, min_rate, owner };
+            Ok(to_binary(&query_list_names(
+                deps, env, start_after, limit, order, prefix, filter,
+            )?)?)
+        },
+        QueryMsg::Stats {} => {
+            Ok(to_binary(&query_stats(deps, env)?)?)
+        },
+        QueryMsg::GetReferralBalance { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            Ok(to_binary(&query_referral_balance(deps, env, address)?)?)
+        },
+        QueryMsg::GetTldConfig { tld } => {
+            Ok(to_binary(&query_tld_config(deps, env, tld)?)?)
+        },
+        QueryMsg::ListTlds { start_after, limit } => {
+            Ok(to_binary(&query_list_tlds(deps, env, start_after, limit)?)?)
+        },
+        QueryMsg::GetKeeperPool {} => {
+            Ok(to_binary(&query_keeper_pool(deps, env)?)?)
+        },
+        QueryMsg::GetPermissions { name, address } => {
+            let address = deps.api.addr_validate(&address)?;
+            Ok(to_binary(&query_permissions(deps, env, name, address)?)?)
+        },
+    }
+}
+
+fn create_config_response(
+    storage: &dyn Storage,
+    config: Config,
+) -> StdResult<ConfigResponse> {
+    Ok(ConfigResponse {
+        admin: read_option_admin(storage)?,
+        resolver_contract: read_option_resolver_contract(storage)?,
+        recipients: config.recipients,
+        stable_denom: config.stable_denom,
+        min_lease_secs: config.min_lease_secs,
+        max_lease_secs: config.max_lease_secs,
+        counter_delay_secs: config.counter_delay_secs,
+        transition_delay_secs: config.transition_delay_secs,
+        bid_delay_secs: config.bid_delay_secs,
+        grace_period_secs: config.grace_period_secs,
+        grace_fee_secs: config.grace_fee_secs,
+        referral_share: config.referral_share,
+        min_rate: config.min_rate,
+        retention_secs: config.retention_secs,
+        keeper_reward: config.keeper_reward,
+        tax_policy: config.tax_policy,
+    })
+}
+
+fn query_config(
+    deps: Deps,
+) -> ContractResult<ConfigResponse> {
+    let config = read_config(deps.storage)?;
+
+    Ok(create_config_response(deps.storage, config)?)
+}
+
+fn query_tld_config(
+    deps: Deps,
+    _env: Env,
+    tld: String,
+) -> ContractResult<ConfigResponse> {
+    let config = match read_option_tld_config(deps.storage, &tld)? {
+        Some(config) => config,
+        None => return UnknownTld { tld }.fail(),
+    };
+
+    Ok(create_config_response(deps.storage, config)?)
+}
+
+fn query_list_tlds(
+    deps: Deps,
+    _env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> ContractResult<TldsResponse> {
+    let tlds = collect_tlds(deps.storage, start_after.as_deref(), limit)?;
+
+    Ok(TldsResponse {
+        tlds,
+    })
+}
+
+fn create_name_state_response(
+    config: &Config,
+    tld: Option<&str>,
+    current_time: Timestamp,
+    name_state: &NameState,
+) -> Result<NameStateResponse, OverflowError> {
+    let counter_delay_end = name_state.counter_delay_end(config)?;
+    let transition_delay_end = name_state.transition_delay_end(config)?;
+    let bid_delay_end = name_state.bid_delay_end(config)?;
+    let expire_time = name_state.expire_time()?;
+    let grace_period_end = name_state.grace_period_end(config)?;
+
+    let owner_status = name_state.owner_status(config, current_time)?;
+    let current_deposit = name_state.current_deposit(current_time)?;
+    let roles = name_state.name_roles(&owner_status);
+    let transition_reference_time = owner_status.transition_reference_time();
+
+    let (name_owner, bid_owner) = match owner_status {
+        OwnerStatus::Expired { .. } =>
             (None, None),
         OwnerStatus::CounterDelay { name_owner, bid_owner, .. } =>
             (name_owner, Some(bid_owner)),
         OwnerStatus::Valid { owner, .. } | OwnerStatus::TransitionDelay { owner, ..} =>
             (Some(owner.clone()), Some(owner)),
+        // The owner still holds the name in the grace period but resolution
+        // is marked as lapsed based on expire time.
+        OwnerStatus::Grace { owner, .. } =>
+            (Some(owner.clone()), Some(owner)),
     };
 
-    NameStateResponse {
+    Ok(NameStateResponse {
         name_owner,
         bid_owner,
-        controller: name_state.controller.clone(),
+        controller: roles.controller().cloned(),
+        roles: roles.grants().to_vec(),
+        tld: tld.map(String::from),
         rate: name_state.rate,
         begin_time: name_state.begin_time,
         begin_deposit: name_state.begin_deposit,
@@ -584,7 +1600,9 @@ fn create_name_state_response(
         transition_delay_end,
         bid_delay_end,
         expire_time,
-    }
+        grace_period_end,
+        transition_reference_time,
+    })
 }
 
 fn query_name_state(
@@ -592,10 +1610,11 @@ fn query_name_state(
     env: Env,
     name: String,
 ) -> ContractResult<NameStateResponse> {
-    let config = read_config(deps.storage)?;
+    let config = load_name_config(deps.storage, &name)?;
     let name_state = read_name_state(deps.storage, &name)?;
+    let tld = read_name_tld(deps.storage, &name)?;
 
-    Ok(create_name_state_response(&config, env.block.time.into(), &name_state))
+    Ok(create_name_state_response(&config, tld, env.block.time.into(), &name_state)?)
 }
 
 fn query_all_name_states(
@@ -604,34 +1623,346 @@ fn query_all_name_states(
     start_after: Option<String>,
     limit: Option<u32>,
 ) -> ContractResult<AllNameStatesResponse> {
-    let config = read_config(deps.storage)?;
+    let configs = NameConfigs::load(deps.storage)?;
     let name_states = collect_name_states(
         deps.storage,
         start_after.as_deref(),
         limit,
     )?;
 
-    let names: Vec<NameStateItem> = name_states.into_iter().map(|(name, name_state)| {
+    let mut names = Vec::with_capacity(name_states.len());
+    for (name, name_state) in name_states {
+        let tld = read_name_tld(deps.storage, &name)?;
+        let config = match configs.get(tld) {
+            Some(config) => config,
+            None => continue,
+        };
         let state = create_name_state_response(
-            &config, env.block.time.into(), &name_state,
-        );
+            config, tld, env.block.time.into(), &name_state,
+        )?;
 
-        Ok(NameStateItem {
+        names.push(NameStateItem {
             name,
             state,
-        })
-    }).collect::<StdResult<Vec<_>>>()?;
+        });
+    }
 
     Ok(AllNameStatesResponse {
         names,
     })
 }
 
+/// Filter for listing names
+struct ListNamesFilter {
+    status: Option<NameStatus>,
+    min_rate: Option<Uint128>,
+    owner: Option<Addr>,
+}
+
+impl ListNamesFilter {
+    fn matches(&self, name_state: &NameState, owner_status: &OwnerStatus) -> bool {
+        if let Some(status) = self.status {
+            if owner_status.name_status() != status {
+                return false;
+            }
+        }
+        if let Some(min_rate) = self.min_rate {
+            if name_state.rate < min_rate {
+                return false;
+            }
+        }
+        if let Some(owner) = &self.owner {
+            if owner_status.name_owner() != Some(owner) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn query_list_names(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    order: Option<ListOrder>,
+    prefix: Option<String>,
+    filter: ListNamesFilter,
+) -> ContractResult<ListNamesResponse> {
+    let configs = NameConfigs::load(deps.storage)?;
+    let current_time = env.block.time.into();
+    let order = match order {
+        Some(ListOrder::Descending) => Order::Descending,
+        _ => Order::Ascending,
+    };
+
+    let (name_states, next_start_after) = list_name_states(
+        deps.storage,
+        start_after.as_deref(),
+        limit,
+        prefix.as_deref(),
+        order,
+        |name, name_state| {
+            match configs.get(read_name_tld(deps.storage, name)?) {
+                Some(config) => {
+                    let owner_status = name_state.owner_status(config, current_time)?;
+                    Ok(filter.matches(name_state, &owner_status))
+                },
+                None => Ok(false),
+            }
+        },
+    )?;
+
+    let mut names = Vec::with_capacity(name_states.len());
+    for (name, name_state) in name_states {
+        let tld = read_name_tld(deps.storage, &name)?;
+        let config = match configs.get(tld) {
+            Some(config) => config,
+            None => continue,
+        };
+        names.push(NameStateItem {
+            state: create_name_state_response(config, tld, current_time, &name_state)?,
+            name,
+        });
+    }
+
+    Ok(ListNamesResponse {
+        names,
+        next_start_after,
+    })
+}
+
+fn query_name_roles(
+    deps: Deps,
+    env: Env,
+    name: String,
+) -> ContractResult<NameRolesResponse> {
+    let config = load_name_config(deps.storage, &name)?;
+    let name_state = read_name_state(deps.storage, &name)?;
+    let owner_status = name_state.owner_status(&config, env.block.time.into())?;
+    let roles = name_state.name_roles(&owner_status);
+
+    Ok(NameRolesResponse {
+        name_owner: owner_status.name_owner().cloned(),
+        roles: roles.grants().to_vec(),
+    })
+}
+
+/// Return whether address is allowed to take the action at the given time
+///
+/// This mirrors the authorization and timing checks of the execute handlers
+/// but not the checks that depend on the funds or rate in the message.
+fn has_permission(
+    name_state: &NameState,
+    config: &Config,
+    address: &Addr,
+    permission: Permission,
+    time: Timestamp,
+) -> Result<bool, OverflowError> {
+    let owner_status = name_state.owner_status(config, time)?;
+    let roles = name_state.name_roles(&owner_status);
+    Ok(match permission {
+        Permission::Bid => match owner_status {
+            OwnerStatus::Grace { .. } => false,
+            OwnerStatus::Expired { .. } => true,
+            _ => {
+                let bid_delay = time >= name_state.counter_delay_end(config)? &&
+                    time < name_state.bid_delay_end(config)?;
+                address != &name_state.owner && !bid_delay
+            },
+        },
+        Permission::Fund => !matches!(
+            owner_status, OwnerStatus::Grace { .. } | OwnerStatus::Expired { .. }
+        ),
+        Permission::Renew => owner_status.can_renew(address, &roles),
+        Permission::SetRate => owner_status.can_set_rate(address, &roles),
+        Permission::TransferNameOwner => {
+            owner_status.can_transfer_name_owner(address, &roles)
+        },
+        Permission::TransferBidOwner => {
+            owner_status.can_transfer_bid_owner(address, &name_state.roles)
+        },
+        Permission::SetController => owner_status.can_set_controller(address),
+        Permission::Prune => name_state.can_prune(config, time)?,
+    })
+}
+
+fn query_permissions(
+    deps: Deps,
+    env: Env,
+    name: String,
+    address: Addr,
+) -> ContractResult<PermissionsResponse> {
+    ensure_valid_name(deps.storage, &name)?;
+
+    let config = load_name_config(deps.storage, &name)?;
+    let name_state = match read_option_name_state(deps.storage, &name)? {
+        Some(name_state) => name_state,
+        None => {
+            // Unclaimed names are only open for bids
+            return Ok(PermissionsResponse {
+                status: None,
+                allowed: vec![Permission::Bid],
+                unavailable: PERMISSIONS.iter()
+                    .filter(|permission| **permission != Permission::Bid)
+                    .map(|permission| UnavailablePermission {
+                        permission: *permission,
+                        available_time: None,
+                    })
+                    .collect(),
+            });
+        },
+    };
+
+    let current_time = Timestamp::from(env.block.time);
+    let owner_status = name_state.owner_status(&config, current_time)?;
+
+    // Permissions only change at these times unless the name state is
+    // changed. Times that overflow are never reached.
+    let mut transition_times: Vec<Timestamp> = vec![
+        name_state.counter_delay_end(&config).ok(),
+        name_state.transition_delay_end(&config).ok(),
+        name_state.bid_delay_end(&config).ok(),
+        name_state.expire_time().ok().flatten(),
+        name_state.grace_period_end(&config).ok().flatten(),
+        name_state.prune_time(&config).ok().flatten(),
+    ].into_iter().flatten().filter(|time| *time > current_time).collect();
+    transition_times.sort();
+    transition_times.dedup();
+
+    let mut allowed = vec![];
+    let mut unavailable = vec![];
+    for permission in PERMISSIONS.iter().copied() {
+        if has_permission(&name_state, &config, &address, permission, current_time)? {
+            allowed.push(permission);
+            continue;
+        }
+
+        let mut available_time = None;
+        for time in transition_times.iter().copied() {
+            if has_permission(&name_state, &config, &address, permission, time)? {
+                available_time = Some(time);
+                break;
+            }
+        }
+        unavailable.push(UnavailablePermission { permission, available_time });
+    }
+
+    Ok(PermissionsResponse {
+        status: Some(owner_status.name_status()),
+        allowed,
+        unavailable,
+    })
+}
+
+fn query_pending_transfer(
+    deps: Deps,
+    _env: Env,
+    name: String,
+) -> ContractResult<PendingTransferResponse> {
+    let pending_transfer = read_pending_transfer(deps.storage, &name)?;
+
+    Ok(PendingTransferResponse {
+        from: pending_transfer.from,
+        to: pending_transfer.to,
+        kind: pending_transfer.kind,
+        expires: pending_transfer.expires,
+    })
+}
+
+fn query_stats(
+    deps: Deps,
+    env: Env,
+) -> ContractResult<StatsResponse> {
+    let stats = read_stats(deps.storage)?;
+    let current_time: Timestamp = env.block.time.into();
+
+    let expired_names = count_expired_names(deps.storage, current_time);
+    let owned_names = stats.names.u64().saturating_sub(expired_names);
+
+    // Only bids that began within the longest counter delay can be in the
+    // counter delay of their config
+    let configs = NameConfigs::load(deps.storage)?;
+    let start_time = Timestamp::from_seconds(
+        current_time.value().saturating_sub(configs.max_counter_delay().value()),
+    );
+    let mut counter_delay_names = 0u64;
+    for (name, name_state) in collect_name_states_begun_since(deps.storage, start_time)? {
+        let config = match configs.get(read_name_tld(deps.storage, &name)?) {
+            Some(config) => config,
+            None => continue,
+        };
+        let owner_status = name_state.owner_status(config, current_time)?;
+        if let OwnerStatus::CounterDelay { .. } = owner_status {
+            counter_delay_names += 1;
+        }
+    }
+
+    Ok(StatsResponse {
+        names: stats.names,
+        owned_names: Uint64::new(owned_names),
+        counter_delay_names: Uint64::new(counter_delay_names),
+        total_bids: stats.total_bids,
+        total_counter_bids: stats.total_counter_bids,
+        total_fundings: stats.total_fundings,
+        total_rate_changes: stats.total_rate_changes,
+        total_deposits: stats.total_deposits,
+        total_refunded: stats.total_refunded,
+        total_collected: stats.total_collected,
+        total_referral: stats.total_referral,
+    })
+}
+
+fn query_keeper_pool(
+    deps: Deps,
+    _env: Env,
+) -> ContractResult<KeeperPoolResponse> {
+    Ok(KeeperPoolResponse {
+        balance: read_keeper_pool(deps.storage)?,
+    })
+}
+
+fn query_referral_balance(
+    deps: Deps,
+    _env: Env,
+    address: Addr,
+) -> ContractResult<ReferralBalanceResponse> {
+    Ok(ReferralBalanceResponse {
+        balance: read_referral_balance(deps.storage, &address)?,
+    })
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
-    _msg: MigrateMsg,
+    msg: MigrateMsg,
 ) -> ContractResult<Response> {
-    Ok(Response::default())
+    if migrate_singletons(deps.storage)? {
+        // Name states stored with the bucket layout are missing the indexes
+        start_name_state_index_rebuild(deps.storage)?;
+    }
+    migrate_collector_addr(deps.storage)?;
+
+    if let Some(admin) = msg.admin {
+        let admin = deps.api.addr_validate(&admin)?;
+        store_admin(deps.storage, &admin)?;
+    }
+
+    if let Some(tax_policy) = msg.tax_policy {
+        validate_tax_policy(&tax_policy)?;
+        store_tax_policy(deps.storage, &tax_policy)?;
+    }
+
+    let index_limit = msg.index_limit.unwrap_or(DEFAULT_INDEX_LIMIT).min(MAX_INDEX_LIMIT);
+    let (count, remaining) = continue_name_state_index_rebuild(deps.storage, index_limit)?;
+
+    let event = MigrateEvent {
+        migrated: count as u64,
+        complete: !remaining,
+    };
+
+    Ok(Response::new()
+        .add_event(event.into())
+    )
 }
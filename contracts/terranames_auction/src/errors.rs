@@ -14,6 +14,8 @@ pub enum ContractError {
     Unfunded { backtrace: Option<snafu::Backtrace> },
     #[snafu(display("Invalid Config"))]
     InvalidConfig { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Tax policy is not supported"))]
+    UnsupportedTaxPolicy { backtrace: Option<snafu::Backtrace> },
     #[snafu(display("Closed For Bids"))]
     ClosedForBids { backtrace: Option<snafu::Backtrace> },
     #[snafu(display("Bid rate too low (min {})", rate))]
@@ -22,6 +24,26 @@ pub enum ContractError {
     BidDepositTooLow { deposit: Uint128, backtrace: Option<snafu::Backtrace> },
     #[snafu(display("Bid has invalid interval"))]
     BidInvalidInterval { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Name expired"))]
+    NameExpired { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Too many roles granted"))]
+    TooManyRoles { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Transfer expired"))]
+    TransferExpired { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Pending transfer is no longer valid"))]
+    TransferInvalid { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Invalid name"))]
+    InvalidName { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Invalid TLD"))]
+    InvalidTld { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Unknown TLD: {}", tld))]
+    UnknownTld { tld: String, backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("TLD already exists"))]
+    TldExists { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Too many names"))]
+    TooManyNames { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Migration in progress"))]
+    MigrationInProgress { backtrace: Option<snafu::Backtrace> },
     #[snafu(display("Unexpected state"))]
     UnexpectedState { backtrace: Option<snafu::Backtrace> },
 }
@@ -6,4 +6,4 @@ pub mod state;
 mod tests;
 
 #[cfg(test)]
-mod mock_querier;
+mod proptests;
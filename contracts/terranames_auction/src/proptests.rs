@@ -0,0 +1,368 @@
+//! Property-based tests of invariants across random sequences of actions
+//!
+//! Random interleavings of bids, funding, rate changes, transfers and
+//! controller changes are executed at random block times. After every step
+//! the invariants below are checked for every name:
+//!
+//! - All funds sent to the contract are forwarded as refunds or deposits in
+//!   the same transaction (taxes are disabled so the amounts match exactly).
+//! - The owner status only moves forward with time, except that bids and
+//!   rate changes restart the counter delay and renewals end the grace period.
+//! - The current deposit never increases unless the name is bid on or funded.
+//! - Handlers never panic and failed handlers leave no trace.
+
+use cosmwasm_std::{
+    coins, BankMsg, CosmosMsg, Decimal, OwnedDeps, Order, Response, Storage,
+    Uint128, WasmMsg,
+};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage};
+use proptest::prelude::*;
+
+use terranames::auction::{
+    ExecuteMsg, InstantiateMsg, NameStatus, RecipientKind, RecipientMsg,
+};
+use terranames::testing::helpers::EnvBuilder;
+use terranames::tax::TaxPolicy;
+use terranames::testing::{mock_dependencies, MockQuerier};
+use terranames::utils::{Timedelta, Timestamp};
+
+use crate::contract::{execute, instantiate};
+use crate::errors::ContractError;
+use crate::state::{read_config, read_option_name_state};
+
+const ABC_COIN: &str = "uabc";
+
+const NAMES: [&str; 2] = ["example", "other"];
+const ADDRESSES: [&str; 3] = ["alice", "bob", "carol"];
+
+type Deps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+#[derive(Clone, Debug)]
+enum Action {
+    Bid { name: usize, sender: usize, rate: u128, deposit: u128 },
+    Fund { name: usize, sender: usize, deposit: u128 },
+    SetRate { name: usize, sender: usize, rate: u128 },
+    Transfer { name: usize, sender: usize, to: usize },
+    SetController { name: usize, sender: usize, controller: Option<usize> },
+}
+
+impl Action {
+    fn name(&self) -> usize {
+        match self {
+            Action::Bid { name, .. } |
+            Action::Fund { name, .. } |
+            Action::SetRate { name, .. } |
+            Action::Transfer { name, .. } |
+            Action::SetController { name, .. } => *name,
+        }
+    }
+
+    fn funds(&self) -> u128 {
+        match self {
+            Action::Bid { deposit, .. } |
+            Action::Fund { deposit, .. } => *deposit,
+            _ => 0,
+        }
+    }
+}
+
+/// Time to wait before an action
+#[derive(Clone, Debug)]
+enum Wait {
+    Seconds(u64),
+    /// Wait until the offset into the grace period of the name (no wait if
+    /// the name does not expire in the future)
+    GracePeriod { name: usize, offset: u64 },
+}
+
+#[derive(Clone, Debug)]
+struct Step {
+    wait: Wait,
+    action: Action,
+}
+
+fn action_strategy() -> impl Strategy<Value = Action> {
+    let name = 0..NAMES.len();
+    let address = 0..ADDRESSES.len();
+    prop_oneof![
+        3 => (name.clone(), address.clone(), 0..500u128, 0..1_000_000u128)
+            .prop_map(|(name, sender, rate, deposit)| {
+                Action::Bid { name, sender, rate, deposit }
+            }),
+        2 => (name.clone(), address.clone(), 0..200_000u128)
+            .prop_map(|(name, sender, deposit)| {
+                Action::Fund { name, sender, deposit }
+            }),
+        1 => (name.clone(), address.clone(), 0..500u128)
+            .prop_map(|(name, sender, rate)| {
+                Action::SetRate { name, sender, rate }
+            }),
+        1 => (name.clone(), address.clone(), address.clone())
+            .prop_map(|(name, sender, to)| {
+                Action::Transfer { name, sender, to }
+            }),
+        1 => (name, address.clone(), proptest::option::of(address))
+            .prop_map(|(name, sender, controller)| {
+                Action::SetController { name, sender, controller }
+            }),
+    ]
+}
+
+fn step_strategy() -> impl Strategy<Value = Step> {
+    // Mix of short and long intervals to reach all owner states
+    let wait = prop_oneof![
+        (0..3_600u64).prop_map(Wait::Seconds),
+        (0..2_000_000u64).prop_map(Wait::Seconds),
+        (0..40_000_000u64).prop_map(Wait::Seconds),
+        (0..NAMES.len(), 0..1_209_600u64)
+            .prop_map(|(name, offset)| Wait::GracePeriod { name, offset }),
+    ];
+    (wait, action_strategy())
+        .prop_map(|(wait, action)| Step { wait, action })
+}
+
+fn init() -> Deps {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        recipients: vec![
+            RecipientMsg {
+                addr: "collector".into(),
+                share: Decimal::one(),
+                kind: RecipientKind::Deposit,
+            },
+        ],
+        stable_denom: ABC_COIN.to_string(),
+        min_lease_secs: Timedelta::from_seconds(15_778_476), // 6 months
+        max_lease_secs: Timedelta::from_seconds(157_784_760), // 5 years
+        counter_delay_secs: Timedelta::from_seconds(604_800), // 1 week
+        transition_delay_secs: Timedelta::from_seconds(1_814_400), // 3 weeks
+        bid_delay_secs: Timedelta::from_seconds(15_778_476), // 6 months
+        grace_period_secs: Timedelta::from_seconds(1_209_600), // 2 weeks
+        grace_fee_secs: Timedelta::from_seconds(604_800), // 1 week
+        referral_share: Decimal::zero(),
+        min_rate: Uint128::zero(),
+        retention_secs: Timedelta::zero(),
+        keeper_reward: Uint128::zero(),
+        tax_policy: TaxPolicy::None {},
+    };
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    deps
+}
+
+fn wait_until(storage: &dyn Storage, time: u64, wait: &Wait) -> u64 {
+    match wait {
+        Wait::Seconds(seconds) => time + seconds,
+        Wait::GracePeriod { name, offset } => {
+            read_option_name_state(storage, NAMES[*name]).unwrap()
+                .and_then(|name_state| name_state.expire_time().unwrap())
+                .map(|expire_time| expire_time.value() + offset)
+                .filter(|target| *target > time)
+                .unwrap_or(time)
+        },
+    }
+}
+
+/// Copy of all storage entries for comparing after a failed handler
+fn snapshot(storage: &dyn Storage) -> Vec<(Vec<u8>, Vec<u8>)> {
+    storage.range(None, None, Order::Ascending).collect()
+}
+
+fn execute_action(
+    deps: &mut Deps,
+    time: u64,
+    action: &Action,
+) -> Result<Response, ContractError> {
+    let env = mock_env().at_time(time);
+    match action {
+        Action::Bid { name, sender, rate, deposit } => {
+            let info = mock_info(ADDRESSES[*sender], &coins(*deposit, ABC_COIN));
+            execute(deps.as_mut(), env, info, ExecuteMsg::BidName {
+                name: NAMES[*name].into(),
+                rate: Uint128::new(*rate),
+                referrer: None,
+            })
+        },
+        Action::Fund { name, sender, deposit } => {
+            // Fund the current bid owner if the name exists
+            let owner = read_option_name_state(&deps.storage, NAMES[*name]).unwrap()
+                .map_or_else(|| ADDRESSES[*sender].to_string(), |state| state.owner.into());
+            let info = mock_info(ADDRESSES[*sender], &coins(*deposit, ABC_COIN));
+            execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
+                name: NAMES[*name].into(),
+                owner,
+                referrer: None,
+            })
+        },
+        Action::SetRate { name, sender, rate } => {
+            let info = mock_info(ADDRESSES[*sender], &[]);
+            execute(deps.as_mut(), env, info, ExecuteMsg::SetNameRate {
+                name: NAMES[*name].into(),
+                rate: Uint128::new(*rate),
+            })
+        },
+        Action::Transfer { name, sender, to } => {
+            let info = mock_info(ADDRESSES[*sender], &[]);
+            execute(deps.as_mut(), env, info, ExecuteMsg::TransferNameOwner {
+                name: NAMES[*name].into(),
+                to: ADDRESSES[*to].into(),
+            })
+        },
+        Action::SetController { name, sender, controller } => {
+            let info = mock_info(ADDRESSES[*sender], &[]);
+            execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
+                name: NAMES[*name].into(),
+                controller: controller.map(|controller| ADDRESSES[controller].into()),
+            })
+        },
+    }
+}
+
+/// Sum of funds sent out by the response
+fn sent_funds(response: &Response) -> u128 {
+    response.messages.iter()
+        .flat_map(|sub_msg| match &sub_msg.msg {
+            CosmosMsg::Bank(BankMsg::Send { amount, .. }) => amount.clone(),
+            CosmosMsg::Wasm(WasmMsg::Execute { funds, .. }) => funds.clone(),
+            _ => vec![],
+        })
+        .filter(|coin| coin.denom == ABC_COIN)
+        .map(|coin| coin.amount.u128())
+        .sum()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Observation {
+    status: Option<NameStatus>,
+    current_deposit: Uint128,
+}
+
+fn observe(storage: &dyn Storage, name: &str, time: u64) -> Observation {
+    let config = read_config(storage).unwrap();
+    let time = Timestamp::from_seconds(time);
+    match read_option_name_state(storage, name).unwrap() {
+        Some(name_state) => Observation {
+            status: Some(name_state.owner_status(&config, time).unwrap().name_status()),
+            current_deposit: name_state.current_deposit(time).unwrap(),
+        },
+        None => Observation {
+            status: None,
+            current_deposit: Uint128::zero(),
+        },
+    }
+}
+
+fn status_rank(status: NameStatus) -> u8 {
+    match status {
+        NameStatus::CounterDelay => 0,
+        NameStatus::TransitionDelay => 1,
+        NameStatus::Valid => 2,
+        NameStatus::Grace => 3,
+        NameStatus::Expired => 4,
+    }
+}
+
+/// Check invariants for the passing of time without any actions
+fn check_elapsed(before: &Observation, after: &Observation) -> Result<(), TestCaseError> {
+    match (before.status, after.status) {
+        (None, None) => {},
+        (Some(before_status), Some(after_status)) => {
+            prop_assert!(
+                status_rank(before_status) <= status_rank(after_status),
+                "Status moved backwards from {:?} to {:?}", before_status, after_status,
+            );
+        },
+        _ => prop_assert!(false, "Name state changed without action: {:?} to {:?}", before, after),
+    }
+    prop_assert!(after.current_deposit <= before.current_deposit);
+    Ok(())
+}
+
+/// Check invariants for a successful action on the name
+fn check_action(
+    action: &Action,
+    before: &Observation,
+    after: &Observation,
+) -> Result<(), TestCaseError> {
+    match action {
+        Action::Bid { .. } => {
+            prop_assert_eq!(after.status, Some(NameStatus::CounterDelay));
+        },
+        Action::SetRate { .. } => {
+            prop_assert_eq!(after.status, Some(NameStatus::CounterDelay));
+            prop_assert!(after.current_deposit <= before.current_deposit);
+        },
+        Action::Fund { .. } => {
+            if before.status == Some(NameStatus::Grace) {
+                prop_assert!(matches!(
+                    after.status,
+                    Some(NameStatus::TransitionDelay) | Some(NameStatus::Valid)
+                ), "Renewal resulted in {:?}", after.status);
+            } else {
+                prop_assert_eq!(after.status, before.status);
+            }
+            prop_assert!(after.current_deposit >= before.current_deposit);
+        },
+        Action::Transfer { .. } | Action::SetController { .. } => {
+            prop_assert_eq!(after.status, before.status);
+            prop_assert!(after.current_deposit <= before.current_deposit);
+        },
+    }
+    Ok(())
+}
+
+fn run_steps(steps: &[Step]) -> Result<(), TestCaseError> {
+    let mut deps = init();
+    let mut time = mock_env().block.time.seconds();
+    let mut observations: Vec<_> = NAMES.iter()
+        .map(|name| observe(&deps.storage, name, time))
+        .collect();
+
+    for step in steps {
+        time = wait_until(&deps.storage, time, &step.wait);
+        let before: Vec<_> = NAMES.iter()
+            .map(|name| observe(&deps.storage, name, time))
+            .collect();
+        for (previous, before) in observations.iter().zip(&before) {
+            check_elapsed(previous, before)?;
+        }
+
+        let entries = snapshot(&deps.storage);
+        let res = execute_action(&mut deps, time, &step.action);
+        match &res {
+            Ok(response) => {
+                prop_assert_eq!(sent_funds(response), step.action.funds());
+            },
+            Err(_) => {
+                prop_assert!(snapshot(&deps.storage) == entries, "failed handler wrote to storage");
+            },
+        }
+
+        observations = NAMES.iter()
+            .map(|name| observe(&deps.storage, name, time))
+            .collect();
+        for (index, (before, after)) in before.iter().zip(&observations).enumerate() {
+            if res.is_ok() && index == step.action.name() {
+                check_action(&step.action, before, after)?;
+            } else {
+                prop_assert_eq!(before, after);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn invariants_hold_for_random_actions(
+        steps in proptest::collection::vec(step_strategy(), 1..40),
+    ) {
+        run_steps(&steps)?;
+    }
+}
@@ -1,35 +1,83 @@
+use std::collections::BTreeMap;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Order, StdError, StdResult, Storage, Uint128};
-use cosmwasm_storage::{
-    bucket, bucket_read, singleton, singleton_read,
+use cosmwasm_std::{
+    Addr, Decimal, Empty, Order, OverflowError, StdError, StdResult, Storage,
+    Uint128, Uint64,
+};
+use cw_storage_plus::{
+    Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex, U64Key,
 };
 
 use terranames::auction::{
-    seconds_from_deposit, deposit_from_seconds_ceil,
-    deposit_from_seconds_floor,
+    checked_deposit_from_seconds_ceil, checked_deposit_from_seconds_floor,
+    checked_seconds_from_deposit, split_name, NameStatus, Recipient,
+    RecipientKind, Role, RoleGrant, TransferKind, TLD_SEPARATOR,
 };
+use terranames::storage::migrate_singleton;
+use terranames::tax::TaxPolicy;
 use terranames::utils::{Timedelta, Timestamp};
 
-pub static CONFIG_KEY: &[u8] = b"config";
-pub static NAME_STATE_PREFIX: &[u8] = b"name";
+const CONFIG_KEY: &str = "config";
+pub const CONFIG: Item<Config> = Item::new(CONFIG_KEY);
+pub const NAME_STATE_NAMESPACE: &str = "name";
+pub const PENDING_TRANSFERS: Map<&str, PendingTransfer> = Map::new("pending_transfer");
+pub const STATS: Item<Stats> = Item::new("stats");
+pub const REFERRAL_BALANCES: Map<&Addr, Uint128> = Map::new("referral_balance");
+pub const ADMIN: Item<Addr> = Item::new("admin");
+pub const TLD_CONFIGS: Map<&str, Config> = Map::new("tld_config");
+/// Progress of rebuilding the name state indexes after moving from buckets
+pub const INDEX_MIGRATION: Item<IndexMigration> = Item::new("index_migration");
+/// Names containing the separator that were stored before TLDs existed
+pub const LEGACY_NAMES: Map<&str, Empty> = Map::new("legacy_name");
+pub const RESOLVER_CONTRACT: Item<Addr> = Item::new("resolver_contract");
+pub const KEEPER_POOL: Item<Uint128> = Item::new("keeper_pool");
 
 const DEFAULT_LIMIT: u32 = 10;
 const MAX_LIMIT: u32 = 30;
 
-fn calc_range_start_str(start_after: Option<&str>) -> Option<Vec<u8>> {
-    start_after.map(|s| {
-        let mut v: Vec<u8> = s.into();
-        v.push(0);
-        v
-    })
+/// Maximum number of names scanned when listing names with filters
+const MAX_SCAN_LIMIT: u32 = 300;
+
+/// Maximum number of roles that can be granted for a name
+pub const MAX_ROLE_GRANTS: usize = 10;
+
+fn calc_range_start_str(start_after: Option<&str>) -> Option<Bound> {
+    start_after.map(|s| Bound::exclusive(s.as_bytes()))
+}
+
+/// Return the first key after all keys starting with prefix
+fn calc_prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// Move singletons stored by earlier versions
+///
+/// Returns true if the singletons were moved which means that the storage
+/// was written with the cosmwasm_storage layout.
+pub fn migrate_singletons(storage: &mut dyn Storage) -> StdResult<bool> {
+    migrate_singleton(storage, CONFIG_KEY, &CONFIG)
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
-    /// Collector of funds
-    pub collector_addr: Addr,
+    /// Recipients of collected funds
+    #[serde(default)]
+    pub recipients: Vec<Recipient>,
+    /// Single collector of funds stored by earlier versions
+    ///
+    /// This is moved to the recipients by the migration.
+    #[serde(default, rename = "collector_addr", skip_serializing_if = "Option::is_none")]
+    pub legacy_collector_addr: Option<Addr>,
     /// Stablecoin denomination
     pub stable_denom: String,
     /// Minimum number of seconds to allow bidding for
@@ -42,26 +90,283 @@ pub struct Config {
     pub transition_delay_secs: Timedelta,
     /// Number of seconds until a new bid can start
     pub bid_delay_secs: Timedelta,
+    /// Number of seconds after expiry where only the owner can renew
+    #[serde(default)]
+    pub grace_period_secs: Timedelta,
+    /// Number of seconds of rate charged as fee for renewing in grace period
+    #[serde(default)]
+    pub grace_fee_secs: Timedelta,
+    /// Share of collector-bound funds credited to the referrer
+    #[serde(default)]
+    pub referral_share: Decimal,
+    /// Minimum rate allowed for bids
+    #[serde(default)]
+    pub min_rate: Uint128,
+    /// Number of seconds after the grace period until a name can be pruned
+    #[serde(default)]
+    pub retention_secs: Timedelta,
+    /// Reward paid from the keeper pool for each pruned name
+    #[serde(default)]
+    pub keeper_reward: Uint128,
+    /// Tax deducted from funds sent by the contract
+    ///
+    /// Configs stored by earlier versions use the default policy.
+    #[serde(default)]
+    pub tax_policy: TaxPolicy,
 }
 
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
-    singleton_read(storage, CONFIG_KEY).load()
+    CONFIG.load(storage)
+}
+
+pub fn read_option_tld_config(
+    storage: &dyn Storage,
+    tld: &str,
+) -> StdResult<Option<Config>> {
+    TLD_CONFIGS.may_load(storage, tld)
+}
+
+pub fn store_tld_config(
+    storage: &mut dyn Storage,
+    tld: &str,
+    config: &Config,
+) -> StdResult<()> {
+    TLD_CONFIGS.save(storage, tld, config)
+}
+
+pub fn collect_tlds(
+    storage: &dyn Storage,
+    start_after: Option<&str>,
+    limit: Option<u32>,
+) -> StdResult<Vec<String>> {
+    let start = calc_range_start_str(start_after);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    TLD_CONFIGS.keys(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|key| String::from_utf8(key).map_err(|_| StdError::generic_err("Invalid utf-8")))
+        .collect()
+}
+
+/// Return whether name was stored before TLDs existed
+///
+/// These names belong to the default namespace even if they contain the
+/// separator, until they are pruned.
+pub fn is_legacy_name(storage: &dyn Storage, name: &str) -> StdResult<bool> {
+    Ok(LEGACY_NAMES.may_load(storage, name)?.is_some())
+}
+
+/// Return the TLD of name or None for names in the default namespace
+pub fn read_name_tld<'a>(
+    storage: &dyn Storage,
+    name: &'a str,
+) -> StdResult<Option<&'a str>> {
+    if is_legacy_name(storage, name)? {
+        return Ok(None);
+    }
+    Ok(split_name(name).1)
+}
+
+/// Read config that applies to name based on its TLD
+///
+/// Returns None if the TLD of the name does not exist.
+pub fn read_name_config(
+    storage: &dyn Storage,
+    name: &str,
+) -> StdResult<Option<Config>> {
+    match read_name_tld(storage, name)? {
+        Some(tld) => read_option_tld_config(storage, tld),
+        None => read_config(storage).map(Some),
+    }
+}
+
+/// Configs of the default namespace and all TLDs
+///
+/// Used when handling many names at once to avoid reading the config of
+/// each name separately.
+pub struct NameConfigs {
+    default_config: Config,
+    tld_configs: BTreeMap<String, Config>,
+}
+
+impl NameConfigs {
+    pub fn load(storage: &dyn Storage) -> StdResult<Self> {
+        let tld_configs = TLD_CONFIGS.range(storage, None, None, Order::Ascending)
+            .map(|item| {
+                let (key, config) = item?;
+                let key = String::from_utf8(key).map_err(|_| StdError::generic_err("Invalid utf-8"))?;
+                Ok((key, config))
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(NameConfigs {
+            default_config: read_config(storage)?,
+            tld_configs,
+        })
+    }
+
+    /// Return config of names in the default namespace
+    pub fn default_config(&self) -> &Config {
+        &self.default_config
+    }
+
+    /// Return the longest counter delay of all configs
+    pub fn max_counter_delay(&self) -> Timedelta {
+        self.tld_configs.values()
+            .map(|config| config.counter_delay_secs)
+            .fold(self.default_config.counter_delay_secs, Timedelta::max)
+    }
+
+    /// Return config that applies to names of TLD or None if the TLD does
+    /// not exist
+    pub fn get(&self, tld: Option<&str>) -> Option<&Config> {
+        match tld {
+            Some(tld) => self.tld_configs.get(tld),
+            None => Some(&self.default_config),
+        }
+    }
+}
+
+pub fn read_option_admin(storage: &dyn Storage) -> StdResult<Option<Addr>> {
+    ADMIN.may_load(storage)
+}
+
+pub fn store_admin(storage: &mut dyn Storage, admin: &Addr) -> StdResult<()> {
+    ADMIN.save(storage, admin)
+}
+
+pub fn read_option_resolver_contract(storage: &dyn Storage) -> StdResult<Option<Addr>> {
+    RESOLVER_CONTRACT.may_load(storage)
+}
+
+pub fn store_resolver_contract(
+    storage: &mut dyn Storage,
+    resolver_contract: Option<&Addr>,
+) -> StdResult<()> {
+    match resolver_contract {
+        Some(resolver_contract) => RESOLVER_CONTRACT.save(storage, resolver_contract),
+        None => {
+            RESOLVER_CONTRACT.remove(storage);
+            Ok(())
+        },
+    }
+}
+
+pub fn read_keeper_pool(storage: &dyn Storage) -> StdResult<Uint128> {
+    Ok(KEEPER_POOL.may_load(storage)?.unwrap_or_default())
+}
+
+pub fn store_keeper_pool(
+    storage: &mut dyn Storage,
+    balance: Uint128,
+) -> StdResult<()> {
+    KEEPER_POOL.save(storage, &balance)
+}
+
+/// Replace the single collector of earlier versions with a recipient
+pub fn migrate_collector_addr(storage: &mut dyn Storage) -> StdResult<()> {
+    let mut config = read_config(storage)?;
+    if let Some(collector_addr) = config.legacy_collector_addr.take() {
+        if config.recipients.is_empty() {
+            config.recipients = vec![
+                Recipient {
+                    addr: collector_addr,
+                    share: Decimal::one(),
+                    kind: RecipientKind::Deposit,
+                },
+            ];
+        }
+        store_config(storage, &config)?;
+    }
+    Ok(())
 }
 
-#[must_use]
 pub fn store_config(
     storage: &mut dyn Storage,
     config: &Config,
 ) -> StdResult<()> {
-    singleton(storage, CONFIG_KEY).save(config)
+    CONFIG.save(storage, config)
+}
+
+/// Store tax policy in the default config and all TLD configs
+pub fn store_tax_policy(
+    storage: &mut dyn Storage,
+    tax_policy: &TaxPolicy,
+) -> StdResult<()> {
+    let mut config = read_config(storage)?;
+    config.tax_policy = tax_policy.clone();
+    store_config(storage, &config)?;
+
+    let tld_configs = TLD_CONFIGS.range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (tld, mut config) in tld_configs {
+        config.tax_policy = tax_policy.clone();
+        let tld = String::from_utf8(tld)?;
+        store_tld_config(storage, &tld, &config)?;
+    }
+    Ok(())
+}
+
+/// Roles delegated by an owner
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct NameRoles(Vec<RoleGrant>);
+
+impl NameRoles {
+    /// Return whether address holds role
+    pub fn has_role(&self, role: Role, address: &Addr) -> bool {
+        self.0.iter().any(|grant| grant.role == role && &grant.address == address)
+    }
+
+    /// Grant role to address
+    ///
+    /// Returns false if the role could not be granted because of too many
+    /// existing grants.
+    pub fn grant(&mut self, role: Role, address: Addr) -> bool {
+        if self.has_role(role, &address) {
+            return true;
+        }
+        if self.0.len() >= MAX_ROLE_GRANTS {
+            return false;
+        }
+        self.0.push(RoleGrant { role, address });
+        self.0.sort_by(|a, b| (a.role, &a.address).cmp(&(b.role, &b.address)));
+        true
+    }
+
+    /// Revoke role from address
+    pub fn revoke(&mut self, role: Role, address: &Addr) {
+        self.0.retain(|grant| !(grant.role == role && &grant.address == address));
+    }
+
+    /// Revoke role from all addresses
+    pub fn revoke_all(&mut self, role: Role) {
+        self.0.retain(|grant| grant.role != role);
+    }
+
+    /// Return all role grants
+    pub fn grants(&self) -> &[RoleGrant] {
+        &self.0
+    }
+
+    /// Return the first address granted the controller role
+    pub fn controller(&self) -> Option<&Addr> {
+        self.0.iter()
+            .find(|grant| grant.role == Role::Controller)
+            .map(|grant| &grant.address)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct NameState {
     /// Owner of the name
     pub owner: Addr,
-    /// Controller of the name
-    pub controller: Option<Addr>,
+    /// Roles delegated by the owner
+    #[serde(default)]
+    pub roles: NameRoles,
+    /// Controller stored by earlier versions
+    ///
+    /// This is moved to the roles by the migration.
+    #[serde(default, rename = "controller", skip_serializing_if = "Option::is_none")]
+    pub legacy_controller: Option<Addr>,
     /// Timestamp from where transition delay is calculated from
     pub transition_reference_time: Timestamp,
 
@@ -74,6 +379,9 @@ pub struct NameState {
 
     /// Previous owner
     pub previous_owner: Option<Addr>,
+    /// Roles delegated by the previous owner
+    #[serde(default)]
+    pub previous_roles: NameRoles,
     /// Previous transition reference timestamp
     pub previous_transition_reference_time: Timestamp,
 }
@@ -90,105 +398,155 @@ impl NameState {
     }
 
     /// Return timestamp when counter delay ends
-    pub fn counter_delay_end(&self, config: &Config) -> Timestamp {
-        self.begin_time + config.counter_delay_secs
+    pub fn counter_delay_end(&self, config: &Config) -> Result<Timestamp, OverflowError> {
+        self.begin_time.checked_add(config.counter_delay_secs)
     }
 
     /// Return timestamp when transition delay ends
-    pub fn transition_delay_end(&self, config: &Config) -> Timestamp {
+    pub fn transition_delay_end(&self, config: &Config) -> Result<Timestamp, OverflowError> {
         if self.transition_reference_time.is_zero() {
             // Special case for new bids
-            self.begin_time
+            Ok(self.begin_time)
         } else {
-            self.transition_reference_time + config.counter_delay_secs +
-                config.transition_delay_secs
+            self.transition_reference_time
+                .checked_add(config.counter_delay_secs)?
+                .checked_add(config.transition_delay_secs)
         }
     }
 
     /// Return timestamp when bid delay ends
     ///
     /// Note: There is no effective bid delay when the rate is zero.
-    pub fn bid_delay_end(&self, config: &Config) -> Timestamp {
+    pub fn bid_delay_end(&self, config: &Config) -> Result<Timestamp, OverflowError> {
         let delay = if !self.rate.is_zero() {
-            config.counter_delay_secs + config.bid_delay_secs
+            config.counter_delay_secs.checked_add(config.bid_delay_secs)?
         } else {
             Timedelta::zero()
         };
-        self.begin_time + delay
+        self.begin_time.checked_add(delay)
     }
 
     /// Return number of seconds since beginning that the deposit allows for
-    pub fn max_seconds(&self) -> Option<Timedelta> {
-        seconds_from_deposit(self.begin_deposit, self.rate)
+    pub fn max_seconds(&self) -> Result<Option<Timedelta>, OverflowError> {
+        checked_seconds_from_deposit(self.begin_deposit, self.rate)
     }
 
     /// Return timestamp when ownership expires
-    pub fn expire_time(&self) -> Option<Timestamp> {
-        self.max_seconds().map(|max_seconds| self.begin_time + max_seconds)
+    pub fn expire_time(&self) -> Result<Option<Timestamp>, OverflowError> {
+        self.max_seconds()?
+            .map(|max_seconds| self.begin_time.checked_add(max_seconds))
+            .transpose()
+    }
+
+    /// Return timestamp when grace period after expiration ends
+    pub fn grace_period_end(&self, config: &Config) -> Result<Option<Timestamp>, OverflowError> {
+        self.expire_time()?
+            .map(|expire_time| expire_time.checked_add(config.grace_period_secs))
+            .transpose()
+    }
+
+    /// Return timestamp when the name can be pruned
+    ///
+    /// Names that never expire cannot be pruned.
+    pub fn prune_time(&self, config: &Config) -> Result<Option<Timestamp>, OverflowError> {
+        self.grace_period_end(config)?
+            .map(|grace_period_end| grace_period_end.checked_add(config.retention_secs))
+            .transpose()
+    }
+
+    /// Return whether the name can be pruned at current time
+    pub fn can_prune(&self, config: &Config, current_time: Timestamp) -> Result<bool, OverflowError> {
+        match self.prune_time(config)? {
+            Some(prune_time) => Ok(current_time >= prune_time),
+            None => Ok(false),
+        }
     }
 
     /// Return current remaining deposit
-    pub fn current_deposit(&self, current_time: Timestamp) -> Uint128 {
+    pub fn current_deposit(&self, current_time: Timestamp) -> Result<Uint128, OverflowError> {
         let seconds_spent = match self.seconds_spent_since_bid(current_time) {
             Some(seconds_spent) => seconds_spent,
-            None => return Uint128::zero(),
+            None => return Ok(Uint128::zero()),
         };
-        let deposit_spent = deposit_from_seconds_ceil(seconds_spent, self.rate);
-        self.begin_deposit - deposit_spent
+        let deposit_spent = checked_deposit_from_seconds_ceil(seconds_spent, self.rate)?;
+        Ok(self.begin_deposit.saturating_sub(deposit_spent))
+    }
+
+    /// Return roles delegated by the current name owner
+    pub fn name_roles(&self, owner_status: &OwnerStatus) -> NameRoles {
+        match owner_status {
+            // In the counter-delay state, the name is still controlled by the
+            // previous owner.
+            OwnerStatus::CounterDelay { name_owner: Some(_), .. } => {
+                self.previous_roles.clone()
+            },
+            OwnerStatus::CounterDelay { name_owner: None, .. } |
+            OwnerStatus::Expired { .. } => NameRoles::default(),
+            _ => self.roles.clone(),
+        }
     }
 
     /// Return max allowed deposit for the name
-    pub fn max_allowed_deposit(&self, config: &Config, current_time: Timestamp) -> Uint128 {
+    pub fn max_allowed_deposit(&self, config: &Config, current_time: Timestamp) -> Result<Uint128, OverflowError> {
         let seconds_spent = match self.seconds_spent_since_bid(current_time) {
             Some(seconds_spent) => seconds_spent,
-            None => return Uint128::zero(),
+            None => return Ok(Uint128::zero()),
         };
-        let max_seconds_from_beginning = config.max_lease_secs + seconds_spent;
-        deposit_from_seconds_floor(max_seconds_from_beginning, self.rate)
+        let max_seconds_from_beginning = config.max_lease_secs.checked_add(seconds_spent)?;
+        checked_deposit_from_seconds_floor(max_seconds_from_beginning, self.rate)
     }
 
     /// Return owner status
-    pub fn owner_status(&self, config: &Config, current_time: Timestamp) -> OwnerStatus {
+    pub fn owner_status(&self, config: &Config, current_time: Timestamp) -> Result<OwnerStatus, OverflowError> {
         let seconds_spent_since_bid = match self.seconds_spent_since_bid(current_time) {
             Some(seconds_spent) => seconds_spent,
-            None => return OwnerStatus::Expired {
+            None => return Ok(OwnerStatus::Expired {
                 expire_time: Timestamp::zero(),
                 transition_reference_time: self.transition_reference_time,
-            },
+            }),
         };
 
-        if let Some(max_seconds) = self.max_seconds() {
+        if let Some(max_seconds) = self.max_seconds()? {
             if seconds_spent_since_bid >= max_seconds {
-                return OwnerStatus::Expired {
-                    expire_time: self.begin_time + max_seconds,
+                let expire_time = self.begin_time.checked_add(max_seconds)?;
+                if seconds_spent_since_bid < max_seconds.checked_add(config.grace_period_secs)? {
+                    return Ok(OwnerStatus::Grace {
+                        owner: self.owner.clone(),
+                        expire_time,
+                        transition_reference_time: self.transition_reference_time,
+                    });
+                }
+                return Ok(OwnerStatus::Expired {
+                    expire_time,
                     transition_reference_time: self.transition_reference_time,
-                };
+                });
             }
         }
 
         let seconds_spent_since_transition = match self.seconds_spent_since_transition(current_time) {
             Some(seconds_spent) => seconds_spent,
-            None => return OwnerStatus::Expired {
+            None => return Ok(OwnerStatus::Expired {
                 expire_time: Timestamp::zero(),
                 transition_reference_time: self.transition_reference_time,
-            },
+            }),
         };
+        let transition_delay = config.counter_delay_secs.checked_add(config.transition_delay_secs)?;
         if seconds_spent_since_bid < config.counter_delay_secs {
-            OwnerStatus::CounterDelay {
+            Ok(OwnerStatus::CounterDelay {
                 name_owner: self.previous_owner.clone(),
                 bid_owner: self.owner.clone(),
                 transition_reference_time: self.previous_transition_reference_time,
-            }
-        } else if seconds_spent_since_transition < config.counter_delay_secs + config.transition_delay_secs {
-            OwnerStatus::TransitionDelay {
+            })
+        } else if seconds_spent_since_transition < transition_delay {
+            Ok(OwnerStatus::TransitionDelay {
                 owner: self.owner.clone(),
                 transition_reference_time: self.transition_reference_time,
-            }
+            })
         } else {
-            OwnerStatus::Valid {
+            Ok(OwnerStatus::Valid {
                 owner: self.owner.clone(),
                 transition_reference_time: self.transition_reference_time,
-            }
+            })
         }
     }
 }
@@ -208,6 +566,11 @@ pub enum OwnerStatus {
         owner: Addr,
         transition_reference_time: Timestamp,
     },
+    Grace {
+        owner: Addr,
+        expire_time: Timestamp,
+        transition_reference_time: Timestamp,
+    },
     Expired {
         expire_time: Timestamp,
         transition_reference_time: Timestamp,
@@ -215,26 +578,82 @@ pub enum OwnerStatus {
 }
 
 impl OwnerStatus {
-    pub fn can_set_rate(&self, sender: &Addr) -> bool {
+    /// Return the current owner of the name
+    pub fn name_owner(&self) -> Option<&Addr> {
         match self {
+            OwnerStatus::CounterDelay { name_owner, .. } => name_owner.as_ref(),
             OwnerStatus::Valid { owner, .. } |
-            OwnerStatus::TransitionDelay { owner, .. } => sender == owner,
+            OwnerStatus::TransitionDelay { owner, .. } |
+            OwnerStatus::Grace { owner, .. } => Some(owner),
+            OwnerStatus::Expired { .. } => None,
+        }
+    }
+
+    /// Return the transition reference time of the current owner
+    pub fn transition_reference_time(&self) -> Timestamp {
+        match self {
+            OwnerStatus::CounterDelay { transition_reference_time, .. } |
+            OwnerStatus::TransitionDelay { transition_reference_time, .. } |
+            OwnerStatus::Valid { transition_reference_time, .. } |
+            OwnerStatus::Grace { transition_reference_time, .. } |
+            OwnerStatus::Expired { transition_reference_time, .. } => {
+                *transition_reference_time
+            },
+        }
+    }
+
+    pub fn name_status(&self) -> NameStatus {
+        match self {
+            OwnerStatus::CounterDelay { .. } => NameStatus::CounterDelay,
+            OwnerStatus::TransitionDelay { .. } => NameStatus::TransitionDelay,
+            OwnerStatus::Valid { .. } => NameStatus::Valid,
+            OwnerStatus::Grace { .. } => NameStatus::Grace,
+            OwnerStatus::Expired { .. } => NameStatus::Expired,
+        }
+    }
+}
+
+/// Permission checks for the current owner status
+///
+/// The roles passed in are the roles delegated by the current name owner (see
+/// NameState::name_roles) except for can_transfer_bid_owner where the roles
+/// delegated by the bid owner are used.
+impl OwnerStatus {
+    pub fn can_set_rate(&self, sender: &Addr, roles: &NameRoles) -> bool {
+        match self {
+            OwnerStatus::Valid { owner, .. } |
+            OwnerStatus::TransitionDelay { owner, .. } => {
+                sender == owner || roles.has_role(Role::RateManager, sender)
+            },
             _ => false,
         }
     }
 
-    pub fn can_transfer_name_owner(&self, sender: &Addr) -> bool {
+    pub fn can_transfer_name_owner(&self, sender: &Addr, roles: &NameRoles) -> bool {
         match self {
             OwnerStatus::Valid { owner, .. } |
             OwnerStatus::CounterDelay { name_owner: Some(owner), .. } |
-            OwnerStatus::TransitionDelay { owner, .. } => sender == owner,
+            OwnerStatus::TransitionDelay { owner, .. } => {
+                sender == owner || roles.has_role(Role::TransferAgent, sender)
+            },
+            _ => false,
+        }
+    }
+
+    pub fn can_transfer_bid_owner(&self, sender: &Addr, bid_roles: &NameRoles) -> bool {
+        match self {
+            OwnerStatus::CounterDelay { bid_owner, .. } => {
+                sender == bid_owner || bid_roles.has_role(Role::TransferAgent, sender)
+            },
             _ => false,
         }
     }
 
-    pub fn can_transfer_bid_owner(&self, sender: &Addr) -> bool {
+    pub fn can_renew(&self, sender: &Addr, roles: &NameRoles) -> bool {
         match self {
-            OwnerStatus::CounterDelay { bid_owner, .. } => sender == bid_owner,
+            OwnerStatus::Grace { owner, .. } => {
+                sender == owner || roles.has_role(Role::Funder, sender)
+            },
             _ => false,
         }
     }
@@ -249,18 +668,61 @@ impl OwnerStatus {
     }
 }
 
+/// Secondary indexes of name states
+pub struct NameStateIndexes<'a> {
+    /// Names by expire time (u64::MAX if never expiring)
+    pub expire_time: MultiIndex<'a, (U64Key, Vec<u8>), NameState>,
+    /// Names by begin time of the current bid
+    pub begin_time: MultiIndex<'a, (U64Key, Vec<u8>), NameState>,
+}
+
+impl<'a> IndexList<NameState> for NameStateIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<NameState>> + '_> {
+        let v: Vec<&dyn Index<NameState>> = vec![&self.expire_time, &self.begin_time];
+        Box::new(v.into_iter())
+    }
+}
+
+fn expire_time_index(name_state: &NameState, pk: Vec<u8>) -> (U64Key, Vec<u8>) {
+    // Expire times that overflow are indexed as never expiring
+    let expire_time = name_state.expire_time().ok().flatten().map_or(u64::MAX, |t| t.value());
+    (U64Key::new(expire_time), pk)
+}
+
+pub fn name_states<'a>() -> IndexedMap<'a, &'a str, NameState, NameStateIndexes<'a>> {
+    let indexes = NameStateIndexes {
+        expire_time: MultiIndex::new(
+            expire_time_index,
+            NAME_STATE_NAMESPACE,
+            "name__expire_time",
+        ),
+        begin_time: MultiIndex::new(
+            |name_state, pk| (U64Key::new(name_state.begin_time.value()), pk),
+            NAME_STATE_NAMESPACE,
+            "name__begin_time",
+        ),
+    };
+    IndexedMap::new(NAME_STATE_NAMESPACE, indexes)
+}
+
 pub fn read_name_state(
     storage: &dyn Storage,
     name: &str,
 ) -> StdResult<NameState> {
-    bucket_read(storage, NAME_STATE_PREFIX).load(name.as_bytes())
+    name_states().load(storage, name)
 }
 
 pub fn read_option_name_state(
     storage: &dyn Storage,
     name: &str,
 ) -> StdResult<Option<NameState>> {
-    bucket_read(storage, NAME_STATE_PREFIX).may_load(name.as_bytes())
+    name_states().may_load(storage, name)
+}
+
+fn parse_name_state_item(item: StdResult<(Vec<u8>, NameState)>) -> StdResult<(String, NameState)> {
+    let (key, value) = item?;
+    let key = String::from_utf8(key).map_err(|_| StdError::generic_err("Invalid utf-8"))?;
+    Ok((key, value))
 }
 
 pub fn collect_name_states(
@@ -268,25 +730,342 @@ pub fn collect_name_states(
     start_after: Option<&str>,
     limit: Option<u32>,
 ) -> StdResult<Vec<(String, NameState)>> {
-    let bucket = bucket_read(storage, NAME_STATE_PREFIX);
     let start = calc_range_start_str(start_after);
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    bucket.range(start.as_deref(), None, Order::Ascending)
+    name_states().range(storage, start, None, Order::Ascending)
         .take(limit)
-        .map(|item| {
-            let (key, value) = item?;
-            let key = String::from_utf8(key)
-                .or_else(|_| Err(StdError::generic_err("Invalid utf-8")))?;
-            Ok((key, value))
-        })
+        .map(parse_name_state_item)
+        .collect()
+}
+
+/// Collect name states that expired at or before current time
+///
+/// Names are collected in order of expire time, continuing after the name
+/// start_after. If start_after is no longer stored, e.g. because it was
+/// pruned, collection starts over which only revisits names that were not
+/// pruned.
+pub fn collect_expired_name_states(
+    storage: &dyn Storage,
+    current_time: Timestamp,
+    start_after: Option<&str>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(String, NameState)>> {
+    let name_states = name_states();
+    let expire_time = &name_states.idx.expire_time;
+    let start = match start_after {
+        Some(start_after) => name_states.may_load(storage, start_after)?
+            .map(|name_state| {
+                let key = expire_time_index(&name_state, start_after.as_bytes().to_vec());
+                Bound::Exclusive(expire_time.index_key(key))
+            }),
+        None => None,
+    };
+    let end = current_time.value().checked_add(1).map(|end| {
+        Bound::Exclusive(expire_time.index_key((U64Key::new(end), vec![])))
+    });
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    expire_time.range(storage, start, end, Order::Ascending)
+        .take(limit)
+        .map(parse_name_state_item)
+        .collect()
+}
+
+/// Count names that expired at or before current time
+pub fn count_expired_names(
+    storage: &dyn Storage,
+    current_time: Timestamp,
+) -> u64 {
+    let name_states = name_states();
+    let expire_time = &name_states.idx.expire_time;
+    let end = current_time.value().checked_add(1).map(|end| {
+        Bound::Exclusive(expire_time.index_key((U64Key::new(end), vec![])))
+    });
+    expire_time.keys(storage, None, end, Order::Ascending).count() as u64
+}
+
+/// Collect name states with a bid that began at or after start time
+pub fn collect_name_states_begun_since(
+    storage: &dyn Storage,
+    start_time: Timestamp,
+) -> StdResult<Vec<(String, NameState)>> {
+    let name_states = name_states();
+    let begin_time = &name_states.idx.begin_time;
+    let start = Bound::Inclusive(begin_time.index_key((U64Key::new(start_time.value()), vec![])));
+    begin_time.range(storage, Some(start), None, Order::Ascending)
+        .map(parse_name_state_item)
         .collect()
 }
 
-#[must_use]
+/// Page of name states along with the name to continue listing after
+pub type NameStatePage = (Vec<(String, NameState)>, Option<String>);
+
+/// Collect name states matching filter
+///
+/// Only names starting with prefix are scanned, and at most MAX_SCAN_LIMIT
+/// names are scanned in total. Returns the matching names along with the name
+/// to continue after if the scan stopped before reaching the end.
+pub fn list_name_states<F>(
+    storage: &dyn Storage,
+    start_after: Option<&str>,
+    limit: Option<u32>,
+    prefix: Option<&str>,
+    order: Order,
+    mut filter: F,
+) -> StdResult<NameStatePage>
+where
+    F: FnMut(&str, &NameState) -> StdResult<bool>,
+{
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let prefix_start = prefix.map(|prefix| prefix.as_bytes().to_vec());
+    let prefix_end = prefix.and_then(|prefix| calc_prefix_end(prefix.as_bytes()));
+    let (start, end) = match order {
+        Order::Ascending => {
+            let start = match (start_after, prefix_start) {
+                (Some(start_after), Some(prefix_start)) if prefix_start.as_slice() > start_after.as_bytes() => {
+                    Some(Bound::Inclusive(prefix_start))
+                },
+                (Some(start_after), _) => Some(Bound::exclusive(start_after.as_bytes())),
+                (None, prefix_start) => prefix_start.map(Bound::Inclusive),
+            };
+            (start, prefix_end.map(Bound::Exclusive))
+        },
+        Order::Descending => {
+            let end = match (start_after, prefix_end) {
+                (Some(start_after), Some(prefix_end)) => {
+                    Some(start_after.as_bytes().to_vec().min(prefix_end))
+                },
+                (Some(start_after), None) => Some(start_after.as_bytes().to_vec()),
+                (None, prefix_end) => prefix_end,
+            };
+            (prefix_start.map(Bound::Inclusive), end.map(Bound::Exclusive))
+        },
+    };
+
+    let mut iter = name_states().range(storage, start, end, order);
+    let mut names = vec![];
+    let mut last_scanned = None;
+    for _ in 0..MAX_SCAN_LIMIT {
+        let (key, value) = match iter.next() {
+            Some(item) => parse_name_state_item(item)?,
+            None => return Ok((names, None)),
+        };
+
+        if filter(&key, &value)? {
+            names.push((key.clone(), value));
+        }
+        last_scanned = Some(key);
+
+        if names.len() >= limit {
+            break;
+        }
+    }
+
+    // Either the limit or the scan limit was reached so there may be more
+    // names after the last scanned name.
+    Ok((names, last_scanned))
+}
+
 pub fn store_name_state(
     storage: &mut dyn Storage,
     name: &str,
     name_info: &NameState,
 ) -> StdResult<()> {
-    bucket(storage, NAME_STATE_PREFIX).save(name.as_bytes(), name_info)
+    name_states().save(storage, name, name_info)
+}
+
+pub fn remove_name_state(
+    storage: &mut dyn Storage,
+    name: &str,
+) -> StdResult<()> {
+    LEGACY_NAMES.remove(storage, name);
+    name_states().remove(storage, name)
+}
+
+/// Progress of rebuilding the name state indexes
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct IndexMigration {
+    /// Last name that was indexed (None if no names were indexed yet)
+    pub last_name: Option<String>,
+}
+
+/// Start rebuilding the secondary indexes of all name states
+///
+/// Name states stored with the cosmwasm_storage layout are missing the index
+/// entries. The names are indexed in batches by
+/// continue_name_state_index_rebuild.
+pub fn start_name_state_index_rebuild(storage: &mut dyn Storage) -> StdResult<()> {
+    INDEX_MIGRATION.save(storage, &IndexMigration::default())
+}
+
+/// Whether name states remain to be indexed after a migration
+pub fn is_index_rebuild_pending(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(INDEX_MIGRATION.may_load(storage)?.is_some())
+}
+
+/// Rebuild the indexes of at most limit name states
+///
+/// Name states after the last indexed name are loaded and saved again which
+/// creates the missing index entries. A controller stored by earlier
+/// versions is granted the controller role, and names containing the
+/// separator are kept in the default namespace. The names are also added to
+/// the name count of the stats. Returns the number of names indexed and
+/// whether names remain to be indexed.
+pub fn continue_name_state_index_rebuild(
+    storage: &mut dyn Storage,
+    limit: u32,
+) -> StdResult<(usize, bool)> {
+    let mut migration = match INDEX_MIGRATION.may_load(storage)? {
+        Some(migration) => migration,
+        None => return Ok((0, false)),
+    };
+
+    let raw_name_states: Map<&str, NameState> = Map::new(NAME_STATE_NAMESPACE);
+    let start = calc_range_start_str(migration.last_name.as_deref());
+    let items: Vec<(Vec<u8>, NameState)> = raw_name_states
+        .range(storage, start, None, Order::Ascending)
+        .take(limit as usize)
+        .collect::<StdResult<_>>()?;
+
+    let name_states = name_states();
+    for (key, name_state) in &items {
+        let name = std::str::from_utf8(key).map_err(|_| StdError::generic_err("Invalid utf-8"))?;
+        let mut name_state = name_state.clone();
+        if let Some(controller) = name_state.legacy_controller.take() {
+            name_state.roles.grant(Role::Controller, controller);
+        }
+        // Old data is passed as None since the index entries do not exist yet
+        name_states.replace(storage, name, Some(&name_state), None)?;
+        if name.contains(TLD_SEPARATOR) {
+            LEGACY_NAMES.save(storage, name, &Empty {})?;
+        }
+        migration.last_name = Some(name.to_string());
+    }
+
+    // Names stored by earlier versions were not counted in the stats
+    let mut stats = read_stats(storage)?;
+    stats.names = stats.names.checked_add(Uint64::new(items.len() as u64))?;
+    store_stats(storage, &stats)?;
+
+    let remaining = items.len() == limit as usize;
+    if remaining {
+        INDEX_MIGRATION.save(storage, &migration)?;
+    } else {
+        INDEX_MIGRATION.remove(storage);
+    }
+
+    Ok((items.len(), remaining))
+}
+
+/// Running counters of names and totals of funds handled by the auction
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Stats {
+    /// Number of names that have been bid on and not pruned (including
+    /// expired names)
+    #[serde(default)]
+    pub names: Uint64,
+    /// Total number of bids
+    #[serde(default)]
+    pub total_bids: Uint64,
+    /// Total number of bids that countered a bid in the counter delay
+    #[serde(default)]
+    pub total_counter_bids: Uint64,
+    /// Total number of times names were funded
+    #[serde(default)]
+    pub total_fundings: Uint64,
+    /// Total number of rate changes
+    #[serde(default)]
+    pub total_rate_changes: Uint64,
+    /// Total deposits received
+    pub total_deposits: Uint128,
+    /// Total deposits refunded to previous bidders
+    pub total_refunded: Uint128,
+    /// Total deposits sent to the recipients
+    pub total_collected: Uint128,
+    /// Total deposits credited to referrers
+    #[serde(default)]
+    pub total_referral: Uint128,
+}
+
+/// Read stats (zero if never stored)
+pub fn read_stats(storage: &dyn Storage) -> StdResult<Stats> {
+    Ok(STATS.may_load(storage)?.unwrap_or_default())
+}
+
+pub fn store_stats(
+    storage: &mut dyn Storage,
+    stats: &Stats,
+) -> StdResult<()> {
+    STATS.save(storage, stats)
+}
+
+pub fn read_referral_balance(
+    storage: &dyn Storage,
+    referrer: &Addr,
+) -> StdResult<Uint128> {
+    Ok(REFERRAL_BALANCES.may_load(storage, referrer)?.unwrap_or_default())
+}
+
+pub fn store_referral_balance(
+    storage: &mut dyn Storage,
+    referrer: &Addr,
+    balance: Uint128,
+) -> StdResult<()> {
+    if balance.is_zero() {
+        REFERRAL_BALANCES.remove(storage, referrer);
+        Ok(())
+    } else {
+        REFERRAL_BALANCES.save(storage, referrer, &balance)
+    }
+}
+
+/// Transfer proposed by an owner that is waiting for acceptance
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingTransfer {
+    /// Owner that proposed the transfer
+    pub from: Addr,
+    /// Destination that must accept the transfer
+    pub to: Addr,
+    /// Ownership that is transferred
+    pub kind: TransferKind,
+    /// Time when the proposal expires
+    pub expires: Option<Timestamp>,
+}
+
+impl PendingTransfer {
+    pub fn is_expired(&self, current_time: Timestamp) -> bool {
+        match self.expires {
+            Some(expires) => current_time >= expires,
+            None => false,
+        }
+    }
+}
+
+pub fn read_pending_transfer(
+    storage: &dyn Storage,
+    name: &str,
+) -> StdResult<PendingTransfer> {
+    PENDING_TRANSFERS.load(storage, name)
+}
+
+pub fn read_option_pending_transfer(
+    storage: &dyn Storage,
+    name: &str,
+) -> StdResult<Option<PendingTransfer>> {
+    PENDING_TRANSFERS.may_load(storage, name)
+}
+
+pub fn store_pending_transfer(
+    storage: &mut dyn Storage,
+    name: &str,
+    pending_transfer: &PendingTransfer,
+) -> StdResult<()> {
+    PENDING_TRANSFERS.save(storage, name, pending_transfer)
+}
+
+pub fn remove_pending_transfer(
+    storage: &mut dyn Storage,
+    name: &str,
+) {
+    PENDING_TRANSFERS.remove(storage, name)
 }
@@ -1,33 +1,76 @@
 use cosmwasm_std::{
-    coins, from_binary, Addr, CosmosMsg, Deps, DepsMut, Response, SubMsg,
-    Uint128, WasmMsg,
+    coins, from_binary, to_binary, Addr, BankMsg, CosmosMsg, Decimal, Deps,
+    DepsMut, Order, Response, Storage, SubMsg, Uint128, Uint64, WasmMsg,
 };
 use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_storage::{bucket_read, to_length_prefixed};
+use proptest::prelude::*;
 
 use terranames::auction::{
     AllNameStatesResponse, ConfigResponse, ExecuteMsg, InstantiateMsg,
-    NameStateResponse, QueryMsg,
+    ListNamesResponse, MigrateMsg, ListOrder, NameRolesResponse, NameStateResponse,
+    NameStatus, PendingTransferResponse, Permission, PermissionsResponse,
+    QueryMsg, Recipient, RecipientKind, KeeperPoolResponse, RecipientMsg, ReferralBalanceResponse, Role,
+    RoleGrant, StatsResponse, TldConfigMsg, TldsResponse, TransferKind,
 };
+use terranames::events::{
+    AdminAction, AdminEvent, KeeperAction, KeeperEvent, MigrateEvent,
+    NameAction, NameEvent, ReferralEvent,
+};
+use terranames::resolver::ExecuteMsg as ResolverExecuteMsg;
 use terranames::root_collector::ExecuteMsg as RootCollectorExecuteMsg;
+use terranames::tax::TaxPolicy;
 use terranames::testing::helpers::EnvBuilder;
-use terranames::utils::Timedelta;
+use terranames::testing::mock_dependencies;
+use terranames::utils::{Timedelta, Timestamp};
 
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
 use crate::errors::ContractError;
-use crate::mock_querier::mock_dependencies;
+use crate::state::{
+    name_states, read_config, read_name_state, read_option_name_state, NameState,
+};
 
 static ABC_COIN: &str = "uabc";
 static NOT_ABC_COIN: &str = "uNOT";
 
 fn default_init() -> InstantiateMsg {
     InstantiateMsg {
-        collector_addr: "collector".into(),
+        recipients: vec![
+            RecipientMsg {
+                addr: "collector".into(),
+                share: Decimal::one(),
+                kind: RecipientKind::Deposit,
+            },
+        ],
         stable_denom: ABC_COIN.to_string(),
         min_lease_secs: Timedelta::from_seconds(15_778_476), // 6 months
         max_lease_secs: Timedelta::from_seconds(157_784_760), // 5 years
         counter_delay_secs: Timedelta::from_seconds(604_800), // 1 week
         transition_delay_secs: Timedelta::from_seconds(1_814_400), // 3 weeks
         bid_delay_secs: Timedelta::from_seconds(15_778_476), // 6 months
+        grace_period_secs: Timedelta::zero(),
+        grace_fee_secs: Timedelta::zero(),
+        referral_share: Decimal::zero(),
+        min_rate: Uint128::zero(),
+        retention_secs: Timedelta::zero(),
+        keeper_reward: Uint128::zero(),
+        tax_policy: mock_tax_policy(),
+    }
+}
+
+/// Tax policy matching the default mock Terra tax
+fn mock_tax_policy() -> TaxPolicy {
+    TaxPolicy::Fixed {
+        rate: Decimal::from_ratio(405u128, 100_000u128),
+        cap: Some(Uint128::new(1_500_000)),
+    }
+}
+
+fn grace_period_init() -> InstantiateMsg {
+    InstantiateMsg {
+        grace_period_secs: Timedelta::from_seconds(1_209_600), // 2 weeks
+        grace_fee_secs: Timedelta::from_seconds(604_800), // 1 week
+        ..default_init()
     }
 }
 
@@ -38,6 +81,7 @@ struct Bid<'a> {
     timestamp: u64,
     rate: u128,
     deposit: u128,
+    referrer: Option<&'a str>,
 }
 
 impl<'a> Bid<'a> {
@@ -48,6 +92,7 @@ impl<'a> Bid<'a> {
             timestamp,
             rate: 0,
             deposit: 0,
+            referrer: None,
         }
     }
 
@@ -65,6 +110,13 @@ impl<'a> Bid<'a> {
         }
     }
 
+    fn referrer(self, referrer: &'a str) -> Bid<'a> {
+        Self {
+            referrer: Some(referrer),
+            ..self
+        }
+    }
+
     fn execute(self, deps: DepsMut) -> Result<Response, ContractError> {
         let info = mock_info(self.bidder, &coins(self.deposit, ABC_COIN));
         let env = mock_env().at_time(self.timestamp);
@@ -72,6 +124,7 @@ impl<'a> Bid<'a> {
         execute(deps, env, info, ExecuteMsg::BidName {
             name: self.name.to_string(),
             rate: Uint128::from(self.rate),
+            referrer: self.referrer.map(String::from),
         })
     }
 }
@@ -94,6 +147,8 @@ struct NameStateAsserter<'a> {
     transition_delay_end: Option<u64>,
     bid_delay_end: Option<u64>,
     expire_time: Option<Option<u64>>,
+    grace_period_end: Option<Option<u64>>,
+    transition_reference_time: Option<u64>,
 }
 
 impl<'a> NameStateAsserter<'a> {
@@ -112,6 +167,8 @@ impl<'a> NameStateAsserter<'a> {
             transition_delay_end: None,
             bid_delay_end: None,
             expire_time: None,
+            grace_period_end: None,
+            transition_reference_time: None,
         }
     }
 
@@ -203,6 +260,22 @@ impl<'a> NameStateAsserter<'a> {
         }
     }
 
+    /// Set grace period end to assert
+    fn grace_period_end(self, grace_period_end: Option<u64>) -> Self {
+        Self {
+            grace_period_end: Some(grace_period_end),
+            ..self
+        }
+    }
+
+    /// Set transition reference time to assert
+    fn transition_reference_time(self, transition_reference_time: u64) -> Self {
+        Self {
+            transition_reference_time: Some(transition_reference_time),
+            ..self
+        }
+    }
+
     /// Assert name state properties
     fn assert(self, deps: Deps, block_time: u64) {
         let env = mock_env().at_time(block_time);
@@ -218,7 +291,12 @@ impl<'a> NameStateAsserter<'a> {
             assert_eq!(name_state.bid_owner, bid_owner.map(Addr::unchecked), "bid_owner does not match");
         }
         if let Some(controller) = self.controller {
-            assert_eq!(name_state.controller, controller.map(Addr::unchecked), "controller does not match");
+            let controllers: Vec<_> = name_state.roles.iter()
+                .filter(|grant| grant.role == Role::Controller)
+                .map(|grant| grant.address.clone())
+                .collect();
+            let expected: Vec<_> = controller.map(Addr::unchecked).into_iter().collect();
+            assert_eq!(controllers, expected, "controller does not match");
         }
         if let Some(rate) = self.rate {
             assert_eq!(name_state.rate.u128(), rate, "rate does not match");
@@ -244,6 +322,12 @@ impl<'a> NameStateAsserter<'a> {
         if let Some(expire_time) = self.expire_time {
             assert_eq!(name_state.expire_time.map(|t| t.value()), expire_time, "expire_time does not match");
         }
+        if let Some(grace_period_end) = self.grace_period_end {
+            assert_eq!(name_state.grace_period_end.map(|t| t.value()), grace_period_end, "grace_period_end does not match");
+        }
+        if let Some(transition_reference_time) = self.transition_reference_time {
+            assert_eq!(name_state.transition_reference_time.value(), transition_reference_time, "transition_reference_time does not match");
+        }
     }
 }
 
@@ -263,13 +347,21 @@ fn proper_initialization() {
     let env = mock_env();
     let res = query(deps.as_ref(), env, QueryMsg::Config {}).unwrap();
     let config: ConfigResponse = from_binary(&res).unwrap();
-    assert_eq!(config.collector_addr.as_str(), "collector");
+    assert_eq!(config.recipients, vec![
+        Recipient {
+            addr: Addr::unchecked("collector"),
+            share: Decimal::one(),
+            kind: RecipientKind::Deposit,
+        },
+    ]);
     assert_eq!(config.stable_denom.as_str(), ABC_COIN);
     assert_eq!(config.min_lease_secs, Timedelta::from_seconds(15_778_476));
     assert_eq!(config.max_lease_secs, Timedelta::from_seconds(157_784_760));
     assert_eq!(config.counter_delay_secs, Timedelta::from_seconds(604_800));
     assert_eq!(config.transition_delay_secs, Timedelta::from_seconds(1_814_400));
     assert_eq!(config.bid_delay_secs, Timedelta::from_seconds(15_778_476));
+    assert_eq!(config.grace_period_secs, Timedelta::zero());
+    assert_eq!(config.grace_fee_secs, Timedelta::zero());
 }
 
 #[test]
@@ -336,7 +428,7 @@ fn initial_non_zero_bid() {
             assert_eq!(contract_addr.as_str(), "collector");
             assert_eq!(funds, &coins(deposit_amount - tax_amount, ABC_COIN));
 
-            let msg: RootCollectorExecuteMsg = from_binary(&msg).unwrap();
+            let msg: RootCollectorExecuteMsg = from_binary(msg).unwrap();
             assert!(matches!(msg, RootCollectorExecuteMsg::Deposit { }));
         },
         _ => panic!("Unexpected message type: {:?}", send_to_collector_msg),
@@ -574,7 +666,14 @@ fn bid_three_bidders() {
         .transition_delay_end(bid_3_time + 604800 + 1814400)
         .bid_delay_end(bid_3_time + 604800 + 15778476)
         .expire_time(Some(bid_3_time + 20736000))
+        .transition_reference_time(0)
         .assert(deps.as_ref(), bid_3_time);
+
+    // The transition reference time changes when the new owner takes over
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_3"))
+        .transition_reference_time(bid_3_time)
+        .assert(deps.as_ref(), bid_3_time + 604800);
 }
 
 // Bid by A, starts ownership. Then bid by B, then by C, then counter bid by
@@ -640,6 +739,7 @@ fn bid_is_counter_bid_then_countered() {
         .transition_delay_end(bid_4_time)
         .bid_delay_end(bid_4_time + 604800 + 15778476)
         .expire_time(Some(bid_4_time + 19938461))
+        .transition_reference_time(0)
         .assert(deps.as_ref(), bid_4_time);
 }
 
@@ -687,6 +787,11 @@ fn bid_on_expired_name() {
         .bid_delay_end(bid_2_time + 604800 + 15778476)
         .expire_time(Some(bid_2_time + 23563636))
         .assert(deps.as_ref(), bid_2_time);
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_2"))
+        .transition_reference_time(bid_1_time + 21073170)
+        .assert(deps.as_ref(), bid_2_time + 604800);
 }
 
 // Bid on name that expired during a transition
@@ -759,6 +864,64 @@ fn bid_on_expired_name_in_transition() {
         .assert(deps.as_ref(), bid_3_time);
 }
 
+// Bid on name in grace period after expiration.
+#[test]
+fn bid_on_name_in_grace_period_fails() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = grace_period_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    // Initial bid
+    let bid_1_time = 1234;
+    let deposit_amount = 30_000;
+    let res = Bid::on("example", "bidder_1", bid_1_time)
+        .deposit(deposit_amount)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    // The name is still owned in the grace period
+    let expire_time = bid_1_time + 21073170;
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_1"))
+        .bid_owner(Some("bidder_1"))
+        .expire_time(Some(expire_time))
+        .grace_period_end(Some(expire_time + 1_209_600))
+        .assert(deps.as_ref(), expire_time);
+
+    // Bid after expiration but in grace period fails
+    let bid_2_time = expire_time + 1_209_600 - 1;
+    let res = Bid::on("example", "bidder_2", bid_2_time)
+        .deposit(deposit_amount)
+        .rate(110)
+        .execute(deps.as_mut());
+    assert!(matches!(res, Err(ContractError::ClosedForBids { .. })));
+
+    // Bid after grace period succeeds
+    let bid_2_time = expire_time + 1_209_600;
+    let res = Bid::on("example", "bidder_2", bid_2_time)
+        .deposit(deposit_amount)
+        .rate(110)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    // Transition delay is still based on when the name expired.
+    NameStateAsserter::new("example")
+        .name_owner(None)
+        .bid_owner(Some("bidder_2"))
+        .rate(110)
+        .begin_time(bid_2_time)
+        .transition_delay_end(expire_time + 604800 + 1814400)
+        .assert(deps.as_ref(), bid_2_time);
+}
+
 // TODO More bidding test cases needed here
 
 #[test]
@@ -777,6 +940,7 @@ fn fund_unclaimed_name_fails() {
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
         name: "example".into(),
         owner: "owner".into(),
+        referrer: None,
     });
     assert!(matches!(res, Err(ContractError::Std { .. })));
 }
@@ -805,6 +969,7 @@ fn fund_zero_rate_name_fails() {
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
         name: "example".into(),
         owner: "bidder".into(),
+        referrer: None,
     });
     assert!(matches!(res, Err(ContractError::BidInvalidInterval { .. })));
 }
@@ -839,6 +1004,7 @@ fn fund_name() {
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
         name: "example".into(),
         owner: "bidder".into(),
+        referrer: None,
     }).unwrap();
     assert_eq!(res.messages.len(), 1);
 
@@ -849,7 +1015,7 @@ fn fund_name() {
             assert_eq!(contract_addr.as_str(), "collector");
             assert_eq!(funds, &coins(deposit_amount - tax_amount, ABC_COIN));
 
-            let msg: RootCollectorExecuteMsg = from_binary(&msg).unwrap();
+            let msg: RootCollectorExecuteMsg = from_binary(msg).unwrap();
             assert!(matches!(msg, RootCollectorExecuteMsg::Deposit { }));
         },
         _ => panic!("Unexpected message type"),
@@ -907,6 +1073,7 @@ fn fund_name_fails_due_to_other_bid() {
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
         name: "example".into(),
         owner: "bidder_1".into(),
+        referrer: None,
     });
     assert!(matches!(res, Err(ContractError::UnexpectedState { .. })));
 }
@@ -940,6 +1107,7 @@ fn fund_name_fails_with_zero_funds() {
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
         name: "example".into(),
         owner: "bidder_1".into(),
+        referrer: None,
     });
     assert!(matches!(res, Err(ContractError::Unfunded { .. })));
 }
@@ -973,6 +1141,7 @@ fn fund_name_fails_with_too_much_funding() {
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
         name: "example".into(),
         owner: "bidder_1".into(),
+        referrer: None,
     });
     assert!(matches!(res, Err(ContractError::BidInvalidInterval { .. })));
 
@@ -983,7 +1152,107 @@ fn fund_name_fails_with_too_much_funding() {
     execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
         name: "example".into(),
         owner: "bidder_1".into(),
+        referrer: None,
+    }).unwrap();
+}
+
+#[test]
+fn renew_name_in_grace_period() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = grace_period_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    let deposit_amount: u128 = 30_000;
+    let res = Bid::on("example", "bidder_1", bid_time)
+        .deposit(deposit_amount)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    // Other funders cannot renew in the grace period
+    let fund_time = bid_time + 21073170 + 1000;
+    let env = mock_env().at_time(fund_time);
+    let info = mock_info("funder", &coins(10_000, ABC_COIN));
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
+        name: "example".into(),
+        owner: "bidder_1".into(),
+        referrer: None,
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    // The deposit must cover the lapsed time plus the renewal fee of one
+    // week of rate (861).
+    let env = mock_env().at_time(fund_time);
+    let info = mock_info("bidder_1", &coins(862, ABC_COIN));
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
+        name: "example".into(),
+        owner: "bidder_1".into(),
+        referrer: None,
+    });
+    match res {
+        Err(ContractError::BidDepositTooLow { deposit, .. }) => {
+            assert_eq!(deposit.u128(), 863);
+        },
+        _ => panic!("Unexpected result: {:?}", res),
+    }
+
+    // Owner renews
+    let env = mock_env().at_time(fund_time);
+    let info = mock_info("bidder_1", &coins(10_000, ABC_COIN));
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
+        name: "example".into(),
+        owner: "bidder_1".into(),
+        referrer: None,
     }).unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_1"))
+        .bid_owner(Some("bidder_1"))
+        .rate(123)
+        .begin_time(bid_time)
+        .begin_deposit(39_139)
+        .current_deposit(9_137)
+        .expire_time(Some(bid_time + 27492760))
+        .assert(deps.as_ref(), fund_time);
+}
+
+#[test]
+fn fund_name_after_grace_period_fails() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = grace_period_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    let deposit_amount: u128 = 30_000;
+    let res = Bid::on("example", "bidder_1", bid_time)
+        .deposit(deposit_amount)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    let fund_time = bid_time + 21073170 + 1_209_600;
+    let env = mock_env().at_time(fund_time);
+    let info = mock_info("bidder_1", &coins(10_000, ABC_COIN));
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
+        name: "example".into(),
+        owner: "bidder_1".into(),
+        referrer: None,
+    });
+    assert!(matches!(res, Err(ContractError::NameExpired { .. })));
 }
 
 #[test]
@@ -1518,6 +1787,19 @@ fn transfer_owner_during_counter_bid() {
         .unwrap();
     assert_eq!(res.messages.len(), 2);
 
+    assert_eq!(res.events.len(), 1);
+    assert_eq!(NameEvent::parse(&res.events[0]), Ok(NameEvent {
+        old_owner: Some(Addr::unchecked("bidder_1")),
+        new_owner: Some(Addr::unchecked("bidder_2")),
+        rate: Uint128::from(124u64),
+        begin_time: Timestamp::from_seconds(bid_2_time),
+        expire_time: Some(Timestamp::from_seconds(bid_2_time + 20903225)),
+        deposit: Uint128::from(30_000u64),
+        refund: Uint128::from(6_676u64),
+        collected: Uint128::from(23_324u64),
+        ..NameEvent::new(NameAction::Bid, "example")
+    }));
+
     // Original owner can transfer their expiring ownership
     let transfer_time = bid_2_time + 100;
     let env = mock_env().at_time(transfer_time);
@@ -1528,6 +1810,11 @@ fn transfer_owner_during_counter_bid() {
     }).unwrap();
     assert_eq!(res.messages.len(), 0);
 
+    let event = NameEvent::parse(&res.events[0]).unwrap();
+    assert_eq!(event.action, NameAction::Transfer);
+    assert_eq!(event.old_owner, Some(Addr::unchecked("bidder_1")));
+    assert_eq!(event.new_owner, Some(Addr::unchecked("receiver_1")));
+
     NameStateAsserter::new("example")
         .name_owner(Some("receiver_1"))
         .bid_owner(Some("bidder_2"))
@@ -1614,7 +1901,7 @@ fn set_controller_new_bid() {
     let info = mock_info("bidder", &[]);
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
         name: "example".into(),
-        controller: "controller".into(),
+        controller: Some("controller".into()),
     });
     assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
 
@@ -1624,7 +1911,7 @@ fn set_controller_new_bid() {
     let info = mock_info("bidder", &[]);
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
         name: "example".into(),
-        controller: "controller".into(),
+        controller: Some("controller".into()),
     }).unwrap();
     assert_eq!(res.messages.len(), 0);
 
@@ -1671,7 +1958,7 @@ fn set_controller_during_counter_delay() {
     let info = mock_info("bidder_1", &[]);
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
         name: "example".into(),
-        controller: "controller_1".into(),
+        controller: Some("controller_1".into()),
     }).unwrap();
     assert_eq!(res.messages.len(), 0);
 
@@ -1687,7 +1974,7 @@ fn set_controller_during_counter_delay() {
     let info = mock_info("bidder_2", &[]);
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
         name: "example".into(),
-        controller: "controller_2".into(),
+        controller: Some("controller_2".into()),
     });
     assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
 
@@ -1697,7 +1984,7 @@ fn set_controller_during_counter_delay() {
     let info = mock_info("bidder_2", &[]);
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
         name: "example".into(),
-        controller: "controller_2".into(),
+        controller: Some("controller_2".into()),
     }).unwrap();
     assert_eq!(res.messages.len(), 0);
 
@@ -1774,3 +2061,2334 @@ fn query_all_name_states() {
     assert_eq!(state.names[0].name, "other");
     assert_eq!(state.names[0].state.rate.u128(), 4);
 }
+
+/// Helper for querying names with ListNames
+fn list_names(deps: Deps, block_time: u64, msg: QueryMsg) -> (Vec<String>, Option<String>) {
+    let env = mock_env().at_time(block_time);
+    let res = query(deps, env, msg).unwrap();
+    let response: ListNamesResponse = from_binary(&res).unwrap();
+    let names = response.names.into_iter().map(|item| item.name).collect();
+    (names, response.next_start_after)
+}
+
+#[test]
+fn list_names_with_filters() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_1_time = 1234;
+    for (name, bidder) in [("example", "bidder_1"), ("other", "bidder_2")] {
+        Bid::on(name, bidder, bid_1_time)
+            .deposit(5_670)
+            .rate(30)
+            .execute(deps.as_mut())
+            .unwrap();
+    }
+
+    let bid_2_time = 2_000_000;
+    Bid::on("exam", "bidder_2", bid_2_time)
+        .deposit(5_670)
+        .rate(30)
+        .execute(deps.as_mut())
+        .unwrap();
+    Bid::on("abc-def", "bidder_1", bid_2_time)
+        .deposit(1_200_000)
+        .rate(1_400)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let query_time = bid_2_time + 100;
+    let list_msg = |status, prefix: Option<&str>, min_rate: Option<u64>, owner: Option<&str>| {
+        QueryMsg::ListNames {
+            start_after: None,
+            limit: None,
+            order: None,
+            status,
+            prefix: prefix.map(String::from),
+            min_rate: min_rate.map(Uint128::from),
+            owner: owner.map(String::from),
+        }
+    };
+
+    // Only names in an active auction
+    let (names, next) = list_names(
+        deps.as_ref(), query_time, list_msg(Some(NameStatus::CounterDelay), None, None, None),
+    );
+    assert_eq!(names, vec!["abc-def", "exam"]);
+    assert_eq!(next, None);
+
+    let (names, _) = list_names(
+        deps.as_ref(), query_time, list_msg(Some(NameStatus::TransitionDelay), None, None, None),
+    );
+    assert_eq!(names, vec!["example", "other"]);
+
+    // Prefix search
+    let (names, _) = list_names(
+        deps.as_ref(), query_time, list_msg(None, Some("exam"), None, None),
+    );
+    assert_eq!(names, vec!["exam", "example"]);
+
+    // Minimum rate
+    let (names, _) = list_names(
+        deps.as_ref(), query_time, list_msg(None, None, Some(100), None),
+    );
+    assert_eq!(names, vec!["abc-def"]);
+
+    // Owner of the name (the highest bid in the counter-delay of a new name
+    // does not own the name yet)
+    let (names, _) = list_names(
+        deps.as_ref(), query_time, list_msg(None, None, None, Some("bidder_2")),
+    );
+    assert_eq!(names, vec!["other"]);
+}
+
+#[test]
+fn list_names_descending_pagination() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    for name in ["abc-def", "exam", "example", "other"] {
+        Bid::on(name, "bidder", bid_time)
+            .deposit(5_670)
+            .rate(30)
+            .execute(deps.as_mut())
+            .unwrap();
+    }
+
+    let (names, next) = list_names(deps.as_ref(), bid_time, QueryMsg::ListNames {
+        start_after: None,
+        limit: Some(2),
+        order: Some(ListOrder::Descending),
+        status: None,
+        prefix: None,
+        min_rate: None,
+        owner: None,
+    });
+    assert_eq!(names, vec!["other", "example"]);
+    assert_eq!(next, Some("example".into()));
+
+    let (names, next) = list_names(deps.as_ref(), bid_time, QueryMsg::ListNames {
+        start_after: next,
+        limit: Some(2),
+        order: Some(ListOrder::Descending),
+        status: None,
+        prefix: None,
+        min_rate: None,
+        owner: None,
+    });
+    assert_eq!(names, vec!["exam", "abc-def"]);
+    assert_eq!(next, Some("abc-def".into()));
+
+    let (names, next) = list_names(deps.as_ref(), bid_time, QueryMsg::ListNames {
+        start_after: next,
+        limit: Some(2),
+        order: Some(ListOrder::Descending),
+        status: None,
+        prefix: None,
+        min_rate: None,
+        owner: None,
+    });
+    assert!(names.is_empty());
+    assert_eq!(next, None);
+
+    // Descending prefix search continuing after a name
+    let (names, next) = list_names(deps.as_ref(), bid_time, QueryMsg::ListNames {
+        start_after: Some("example".into()),
+        limit: None,
+        order: Some(ListOrder::Descending),
+        status: None,
+        prefix: Some("exa".into()),
+        min_rate: None,
+        owner: None,
+    });
+    assert_eq!(names, vec!["exam"]);
+    assert_eq!(next, None);
+}
+
+#[test]
+fn clear_controller() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    let deposit_amount: u128 = 30_000;
+    let res = Bid::on("example", "bidder", bid_time)
+        .deposit(deposit_amount)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    let set_controller_time = bid_time + 604_800;
+    let env = mock_env().at_time(set_controller_time);
+    let info = mock_info("bidder", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
+        name: "example".into(),
+        controller: Some("controller".into()),
+    }).unwrap();
+
+    NameStateAsserter::new("example")
+        .controller(Some("controller"))
+        .assert(deps.as_ref(), set_controller_time);
+
+    // Owner can remove the controller
+    let env = mock_env().at_time(set_controller_time);
+    let info = mock_info("bidder", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
+        name: "example".into(),
+        controller: None,
+    }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder"))
+        .controller(None)
+        .assert(deps.as_ref(), set_controller_time);
+}
+
+#[test]
+fn controller_reset_when_counter_bid_wins() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_1_time = 1234;
+    let deposit_amount = 30_000;
+    let res = Bid::on("example", "bidder_1", bid_1_time)
+        .deposit(deposit_amount)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    let set_controller_time = bid_1_time + 604_800;
+    let env = mock_env().at_time(set_controller_time);
+    let info = mock_info("bidder_1", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
+        name: "example".into(),
+        controller: Some("controller_1".into()),
+    }).unwrap();
+
+    // Another bid occurs following bid delay
+    let bid_2_time = bid_1_time + 604_800 + 15_778_476;
+    let deposit_amount = 30_000;
+    let res = Bid::on("example", "bidder_2", bid_2_time)
+        .deposit(deposit_amount)
+        .rate(124)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    // The previous owner still controls the name in the counter delay
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_1"))
+        .bid_owner(Some("bidder_2"))
+        .controller(Some("controller_1"))
+        .assert(deps.as_ref(), bid_2_time + 604_800 - 1);
+
+    // The controller is reset when the new owner takes over
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_2"))
+        .bid_owner(Some("bidder_2"))
+        .controller(None)
+        .assert(deps.as_ref(), bid_2_time + 604_800);
+}
+
+#[test]
+fn controller_kept_when_owner_counters_bid() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_1_time = 1234;
+    let deposit_amount = 30_000;
+    let res = Bid::on("example", "bidder_1", bid_1_time)
+        .deposit(deposit_amount)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    let set_controller_time = bid_1_time + 604_800;
+    let env = mock_env().at_time(set_controller_time);
+    let info = mock_info("bidder_1", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
+        name: "example".into(),
+        controller: Some("controller_1".into()),
+    }).unwrap();
+
+    // Another bid occurs following bid delay
+    let bid_2_time = bid_1_time + 604_800 + 15_778_476;
+    let deposit_amount = 30_000;
+    let res = Bid::on("example", "bidder_2", bid_2_time)
+        .deposit(deposit_amount)
+        .rate(124)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    // Previous owner changes the controller during the counter delay
+    let set_controller_time = bid_2_time + 100;
+    let env = mock_env().at_time(set_controller_time);
+    let info = mock_info("bidder_1", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
+        name: "example".into(),
+        controller: Some("controller_2".into()),
+    }).unwrap();
+
+    // Countered by previous owner
+    let bid_3_time = bid_2_time + 1000;
+    let deposit_amount = 30_000;
+    let res = Bid::on("example", "bidder_1", bid_3_time)
+        .deposit(deposit_amount)
+        .rate(125)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_1"))
+        .bid_owner(Some("bidder_1"))
+        .controller(Some("controller_2"))
+        .assert(deps.as_ref(), bid_3_time);
+
+    // The controller is kept after the counter delay
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_1"))
+        .bid_owner(Some("bidder_1"))
+        .controller(Some("controller_2"))
+        .assert(deps.as_ref(), bid_3_time + 604_800);
+}
+
+#[test]
+fn controller_reset_on_transfer() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_1_time = 1234;
+    let deposit_amount = 30_000;
+    let res = Bid::on("example", "bidder_1", bid_1_time)
+        .deposit(deposit_amount)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    let set_controller_time = bid_1_time + 604_800;
+    let env = mock_env().at_time(set_controller_time);
+    let info = mock_info("bidder_1", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
+        name: "example".into(),
+        controller: Some("controller_1".into()),
+    }).unwrap();
+
+    // Another bid occurs following bid delay
+    let bid_2_time = bid_1_time + 604_800 + 15_778_476;
+    let deposit_amount = 30_000;
+    let res = Bid::on("example", "bidder_2", bid_2_time)
+        .deposit(deposit_amount)
+        .rate(124)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    // Previous owner transfers the name during the counter delay
+    let transfer_time = bid_2_time + 100;
+    let env = mock_env().at_time(transfer_time);
+    let info = mock_info("bidder_1", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::TransferNameOwner {
+        name: "example".into(),
+        to: "receiver".into(),
+    }).unwrap();
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("receiver"))
+        .bid_owner(Some("bidder_2"))
+        .controller(None)
+        .assert(deps.as_ref(), transfer_time);
+
+    // Receiver sets a new controller
+    let env = mock_env().at_time(transfer_time);
+    let info = mock_info("receiver", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameController {
+        name: "example".into(),
+        controller: Some("controller_2".into()),
+    }).unwrap();
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("receiver"))
+        .controller(Some("controller_2"))
+        .assert(deps.as_ref(), transfer_time);
+
+    // Controller is reset when the highest bidder takes over
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_2"))
+        .controller(None)
+        .assert(deps.as_ref(), bid_2_time + 604_800);
+}
+
+#[test]
+fn grant_and_revoke_roles() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    let res = Bid::on("example", "bidder", bid_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    // Only the owner can grant roles
+    let grant_time = bid_time + 604_800;
+    let env = mock_env().at_time(grant_time);
+    let info = mock_info("other", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::GrantRole {
+        name: "example".into(),
+        role: Role::RateManager,
+        address: "manager".into(),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    for (role, address) in [
+        (Role::RateManager, "manager"),
+        (Role::Funder, "funder"),
+        (Role::Controller, "controller_1"),
+        (Role::Controller, "controller_2"),
+    ] {
+        let env = mock_env().at_time(grant_time);
+        let info = mock_info("bidder", &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::GrantRole {
+            name: "example".into(),
+            role,
+            address: address.into(),
+        }).unwrap();
+        assert_eq!(res.messages.len(), 0);
+    }
+
+    let env = mock_env().at_time(grant_time);
+    let res = query(deps.as_ref(), env, QueryMsg::GetNameRoles {
+        name: "example".into(),
+    }).unwrap();
+    let roles: NameRolesResponse = from_binary(&res).unwrap();
+    assert_eq!(roles.name_owner, Some(Addr::unchecked("bidder")));
+    assert_eq!(roles.roles, vec![
+        RoleGrant { role: Role::Controller, address: Addr::unchecked("controller_1") },
+        RoleGrant { role: Role::Controller, address: Addr::unchecked("controller_2") },
+        RoleGrant { role: Role::Funder, address: Addr::unchecked("funder") },
+        RoleGrant { role: Role::RateManager, address: Addr::unchecked("manager") },
+    ]);
+
+    // Revoke one of the controllers
+    let env = mock_env().at_time(grant_time);
+    let info = mock_info("bidder", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::RevokeRole {
+        name: "example".into(),
+        role: Role::Controller,
+        address: "controller_1".into(),
+    }).unwrap();
+
+    NameStateAsserter::new("example")
+        .controller(Some("controller_2"))
+        .assert(deps.as_ref(), grant_time);
+
+    // Funder role does not imply rate manager role
+    let env = mock_env().at_time(grant_time);
+    let info = mock_info("funder", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameRate {
+        name: "example".into(),
+        rate: Uint128::from(150u64),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+}
+
+#[test]
+fn rate_manager_can_set_rate() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    Bid::on("example", "bidder", bid_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let grant_time = bid_time + 604_800;
+    let env = mock_env().at_time(grant_time);
+    let info = mock_info("bidder", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::GrantRole {
+        name: "example".into(),
+        role: Role::RateManager,
+        address: "manager".into(),
+    }).unwrap();
+
+    let set_rate_time = grant_time + 1000;
+    let env = mock_env().at_time(set_rate_time);
+    let info = mock_info("manager", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameRate {
+        name: "example".into(),
+        rate: Uint128::from(150u64),
+    }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder"))
+        .rate(150)
+        .begin_time(set_rate_time)
+        .assert(deps.as_ref(), set_rate_time);
+
+    // Revoked rate manager can no longer set the rate
+    let env = mock_env().at_time(set_rate_time);
+    let info = mock_info("bidder", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::RevokeRole {
+        name: "example".into(),
+        role: Role::RateManager,
+        address: "manager".into(),
+    }).unwrap();
+
+    let env = mock_env().at_time(set_rate_time + 1000);
+    let info = mock_info("manager", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameRate {
+        name: "example".into(),
+        rate: Uint128::from(160u64),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+}
+
+#[test]
+fn transfer_agent_can_transfer() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    Bid::on("example", "bidder", bid_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let grant_time = bid_time + 604_800;
+    let env = mock_env().at_time(grant_time);
+    let info = mock_info("bidder", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::GrantRole {
+        name: "example".into(),
+        role: Role::TransferAgent,
+        address: "agent".into(),
+    }).unwrap();
+
+    let transfer_time = grant_time + 1000;
+    let env = mock_env().at_time(transfer_time);
+    let info = mock_info("agent", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::TransferNameOwner {
+        name: "example".into(),
+        to: "new_owner".into(),
+    }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    // Roles are reset for the new owner
+    let env = mock_env().at_time(transfer_time);
+    let res = query(deps.as_ref(), env, QueryMsg::GetNameRoles {
+        name: "example".into(),
+    }).unwrap();
+    let roles: NameRolesResponse = from_binary(&res).unwrap();
+    assert_eq!(roles.name_owner, Some(Addr::unchecked("new_owner")));
+    assert_eq!(roles.roles, vec![]);
+}
+
+#[test]
+fn funder_can_renew_in_grace_period() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = grace_period_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    Bid::on("example", "bidder_1", bid_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let env = mock_env().at_time(bid_time + 604_800);
+    let info = mock_info("bidder_1", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::GrantRole {
+        name: "example".into(),
+        role: Role::Funder,
+        address: "funder".into(),
+    }).unwrap();
+
+    let fund_time = bid_time + 21073170 + 1000;
+    let env = mock_env().at_time(fund_time);
+    let info = mock_info("funder", &coins(10_000, ABC_COIN));
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
+        name: "example".into(),
+        owner: "bidder_1".into(),
+        referrer: None,
+    }).unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_1"))
+        .current_deposit(9_137)
+        .expire_time(Some(bid_time + 27492760))
+        .assert(deps.as_ref(), fund_time);
+}
+
+#[test]
+fn propose_and_accept_transfer() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    Bid::on("example", "bidder", bid_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    // Only the owner can propose a transfer
+    let propose_time = 1_000_000;
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("other", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::ProposeTransfer {
+        name: "example".into(),
+        to: "receiver".into(),
+        expires: None,
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("bidder", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::ProposeTransfer {
+        name: "example".into(),
+        to: "receiver".into(),
+        expires: Some(Timestamp::from_seconds(propose_time + 1000)),
+    }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let env = mock_env().at_time(propose_time);
+    let res = query(deps.as_ref(), env, QueryMsg::GetPendingTransfer {
+        name: "example".into(),
+    }).unwrap();
+    let pending: PendingTransferResponse = from_binary(&res).unwrap();
+    assert_eq!(pending, PendingTransferResponse {
+        from: Addr::unchecked("bidder"),
+        to: Addr::unchecked("receiver"),
+        kind: TransferKind::NameOwner,
+        expires: Some(Timestamp::from_seconds(propose_time + 1000)),
+    });
+
+    // Ownership is unchanged until accepted
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder"))
+        .assert(deps.as_ref(), propose_time);
+
+    // Only the receiver can accept
+    let accept_time = propose_time + 500;
+    let env = mock_env().at_time(accept_time);
+    let info = mock_info("other", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::AcceptTransfer {
+        name: "example".into(),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    let env = mock_env().at_time(accept_time);
+    let info = mock_info("receiver", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::AcceptTransfer {
+        name: "example".into(),
+    }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("receiver"))
+        .bid_owner(Some("receiver"))
+        .expire_time(Some(bid_time + 21073170))
+        .assert(deps.as_ref(), accept_time);
+
+    // Pending transfer is removed
+    let env = mock_env().at_time(accept_time);
+    let res = query(deps.as_ref(), env, QueryMsg::GetPendingTransfer {
+        name: "example".into(),
+    });
+    assert!(res.is_err());
+}
+
+#[test]
+fn accept_expired_transfer_fails() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    Bid::on("example", "bidder", bid_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let propose_time = 1_000_000;
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("bidder", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::ProposeTransfer {
+        name: "example".into(),
+        to: "receiver".into(),
+        expires: Some(Timestamp::from_seconds(propose_time + 1000)),
+    }).unwrap();
+
+    let env = mock_env().at_time(propose_time + 1000);
+    let info = mock_info("receiver", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::AcceptTransfer {
+        name: "example".into(),
+    });
+    assert!(matches!(res, Err(ContractError::TransferExpired { .. })));
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder"))
+        .assert(deps.as_ref(), propose_time + 1000);
+}
+
+#[test]
+fn cancel_transfer() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_time = 1234;
+    Bid::on("example", "bidder", bid_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let propose_time = 1_000_000;
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("bidder", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::ProposeTransfer {
+        name: "example".into(),
+        to: "receiver".into(),
+        expires: None,
+    }).unwrap();
+
+    // Unrelated address cannot cancel
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("other", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::CancelTransfer {
+        name: "example".into(),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    // Receiver cannot cancel
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("receiver", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::CancelTransfer {
+        name: "example".into(),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("bidder", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::CancelTransfer {
+        name: "example".into(),
+    }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let env = mock_env().at_time(propose_time + 100);
+    let info = mock_info("receiver", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::AcceptTransfer {
+        name: "example".into(),
+    });
+    assert!(res.is_err());
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder"))
+        .assert(deps.as_ref(), propose_time + 100);
+}
+
+#[test]
+fn current_owner_can_cancel_transfer() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_1_time = 1234;
+    Bid::on("example", "bidder_1", bid_1_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let bid_2_time = bid_1_time + 604_800 + 15_778_476;
+    Bid::on("example", "bidder_2", bid_2_time)
+        .deposit(30_000)
+        .rate(124)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    // Owner proposes transfer of their expiring ownership
+    let propose_time = bid_2_time + 100;
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("bidder_1", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::ProposeTransfer {
+        name: "example".into(),
+        to: "receiver".into(),
+        expires: None,
+    }).unwrap();
+
+    // Bid owner does not own the name yet
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("bidder_2", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::CancelTransfer {
+        name: "example".into(),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    // After the counter-delay the new owner can cancel the stale proposal
+    let cancel_time = bid_2_time + 604_800;
+    let env = mock_env().at_time(cancel_time);
+    let info = mock_info("bidder_2", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::CancelTransfer {
+        name: "example".into(),
+    }).unwrap();
+
+    let env = mock_env().at_time(cancel_time);
+    let res = query(deps.as_ref(), env, QueryMsg::GetPendingTransfer {
+        name: "example".into(),
+    });
+    assert!(res.is_err());
+}
+
+#[test]
+fn bid_removes_pending_transfer() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_1_time = 1234;
+    Bid::on("example", "bidder_1", bid_1_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let propose_time = 1_000_000;
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("bidder_1", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::ProposeTransfer {
+        name: "example".into(),
+        to: "receiver".into(),
+        expires: None,
+    }).unwrap();
+
+    let bid_2_time = bid_1_time + 604_800 + 15_778_476;
+    Bid::on("example", "bidder_2", bid_2_time)
+        .deposit(30_000)
+        .rate(124)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let env = mock_env().at_time(bid_2_time);
+    let res = query(deps.as_ref(), env, QueryMsg::GetPendingTransfer {
+        name: "example".into(),
+    });
+    assert!(res.is_err());
+
+    // The stale proposal cannot be accepted once the counter-delay ends
+    let accept_time = bid_2_time + 604_800;
+    let env = mock_env().at_time(accept_time);
+    let info = mock_info("receiver", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::AcceptTransfer {
+        name: "example".into(),
+    });
+    assert!(res.is_err());
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_2"))
+        .assert(deps.as_ref(), accept_time);
+}
+
+#[test]
+fn propose_transfer_during_counter_bid() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let bid_1_time = 1234;
+    let deposit_amount: u128 = 30_000;
+    Bid::on("example", "bidder_1", bid_1_time)
+        .deposit(deposit_amount)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let bid_2_time = bid_1_time + 604_800 + 15_778_476;
+    Bid::on("example", "bidder_2", bid_2_time)
+        .deposit(deposit_amount)
+        .rate(124)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    // Highest bid owner proposes transfer of their bid
+    let propose_time = bid_2_time + 100;
+    let env = mock_env().at_time(propose_time);
+    let info = mock_info("bidder_2", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::ProposeTransfer {
+        name: "example".into(),
+        to: "receiver_2".into(),
+        expires: None,
+    }).unwrap();
+
+    let env = mock_env().at_time(propose_time);
+    let res = query(deps.as_ref(), env, QueryMsg::GetPendingTransfer {
+        name: "example".into(),
+    }).unwrap();
+    let pending: PendingTransferResponse = from_binary(&res).unwrap();
+    assert_eq!(pending.kind, TransferKind::BidOwner);
+    assert_eq!(pending.from, Addr::unchecked("bidder_2"));
+
+    let accept_time = propose_time + 100;
+    let env = mock_env().at_time(accept_time);
+    let info = mock_info("receiver_2", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::AcceptTransfer {
+        name: "example".into(),
+    }).unwrap();
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("bidder_1"))
+        .bid_owner(Some("receiver_2"))
+        .begin_time(bid_2_time)
+        .assert(deps.as_ref(), accept_time);
+
+    // Original owner proposes transfer of their expiring ownership
+    let env = mock_env().at_time(accept_time);
+    let info = mock_info("bidder_1", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::ProposeTransfer {
+        name: "example".into(),
+        to: "receiver_1".into(),
+        expires: None,
+    }).unwrap();
+
+    // The counter-delay ends so the proposing owner no longer owns the name
+    let accept_time = bid_2_time + 604_800;
+    let env = mock_env().at_time(accept_time);
+    let info = mock_info("receiver_1", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::AcceptTransfer {
+        name: "example".into(),
+    });
+    assert!(matches!(res, Err(ContractError::TransferInvalid { .. })));
+
+    NameStateAsserter::new("example")
+        .name_owner(Some("receiver_2"))
+        .bid_owner(Some("receiver_2"))
+        .assert(deps.as_ref(), accept_time);
+}
+
+#[test]
+fn name_state_storage_round_trip() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    Bid::on("example", "bidder", 1234)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    // Name state stored through the indexed map can be read as a bucket
+    let name_state = read_name_state(&deps.storage, "example").unwrap();
+    let bucket_name_state: NameState = bucket_read(&deps.storage, b"name")
+        .load(b"example")
+        .unwrap();
+    assert_eq!(bucket_name_state, name_state);
+
+    // Storage exactly as written by an earlier version
+    let mut old_deps = mock_dependencies(&[]);
+    store_baseline_config(&mut old_deps.storage);
+    for name in &["example", "other", "third"] {
+        store_baseline_name_state(&mut old_deps.storage, name, "bidder", None, 1234);
+    }
+    let name_state = NameState {
+        owner: Addr::unchecked("bidder"),
+        roles: Default::default(),
+        legacy_controller: None,
+        transition_reference_time: Timestamp::from_seconds(1234),
+        rate: Uint128::new(123),
+        begin_time: Timestamp::from_seconds(1234),
+        begin_deposit: Uint128::new(30_000),
+        previous_owner: None,
+        previous_roles: Default::default(),
+        previous_transition_reference_time: Timestamp::zero(),
+    };
+    assert_eq!(read_name_state(&old_deps.storage, "example").unwrap(), name_state);
+
+    let expire_index_count = |deps: Deps| {
+        name_states().idx.expire_time
+            .range(deps.storage, None, None, Order::Ascending)
+            .count()
+    };
+    assert_eq!(expire_index_count(old_deps.as_ref()), 0);
+
+    let migrate_msg = |index_limit| MigrateMsg {
+        admin: None,
+        tax_policy: None,
+        index_limit,
+    };
+
+    // Migration moves the config and creates the missing index entries in
+    // batches
+    assert!(read_config(&old_deps.storage).is_err());
+    let res = migrate(old_deps.as_mut(), mock_env(), migrate_msg(Some(2))).unwrap();
+    assert_eq!(MigrateEvent::parse(&res.events[0]), Ok(MigrateEvent {
+        migrated: 2,
+        complete: false,
+    }));
+    let config = read_config(&old_deps.storage).unwrap();
+    assert_eq!(config.stable_denom, ABC_COIN);
+    assert_eq!(config.min_lease_secs, Timedelta::from_seconds(15_778_476));
+    assert_eq!(expire_index_count(old_deps.as_ref()), 2);
+
+    // Names cannot change until all names are indexed
+    let res = Bid::on("new", "bidder", 1234)
+        .deposit(30_000)
+        .rate(123)
+        .execute(old_deps.as_mut());
+    assert!(matches!(res, Err(ContractError::MigrationInProgress { .. })));
+
+    let res = migrate(old_deps.as_mut(), mock_env(), migrate_msg(Some(2))).unwrap();
+    assert_eq!(MigrateEvent::parse(&res.events[0]), Ok(MigrateEvent {
+        migrated: 1,
+        complete: true,
+    }));
+    assert_eq!(expire_index_count(old_deps.as_ref()), 3);
+
+    // Indexed names are counted in the stats
+    assert_eq!(query_stats(old_deps.as_ref(), 1234).names, Uint64::new(3));
+
+    // Migrating again does not index the names again
+    let res = migrate(old_deps.as_mut(), mock_env(), migrate_msg(None)).unwrap();
+    assert_eq!(MigrateEvent::parse(&res.events[0]), Ok(MigrateEvent {
+        migrated: 0,
+        complete: true,
+    }));
+    assert_eq!(expire_index_count(old_deps.as_ref()), 3);
+    assert_eq!(read_config(&old_deps.storage).unwrap(), config);
+    assert_eq!(read_name_state(&old_deps.storage, "example").unwrap(), name_state);
+
+    Bid::on("new", "bidder", 1234)
+        .deposit(30_000)
+        .rate(123)
+        .execute(old_deps.as_mut())
+        .unwrap();
+    assert_eq!(query_stats(old_deps.as_ref(), 1234).names, Uint64::new(4));
+
+    // Storage written with the current layout is not indexed again
+    let res = migrate(deps.as_mut(), mock_env(), migrate_msg(None)).unwrap();
+    assert_eq!(MigrateEvent::parse(&res.events[0]).unwrap().migrated, 0);
+    assert_eq!(expire_index_count(deps.as_ref()), 1);
+}
+
+fn query_stats(deps: Deps, block_time: u64) -> StatsResponse {
+    let env = mock_env().at_time(block_time);
+    let res = query(deps, env, QueryMsg::Stats {}).unwrap();
+    from_binary(&res).unwrap()
+}
+
+#[test]
+fn stats_query() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    assert_eq!(query_stats(deps.as_ref(), 1000), StatsResponse {
+        names: Uint64::zero(),
+        owned_names: Uint64::zero(),
+        counter_delay_names: Uint64::zero(),
+        total_bids: Uint64::zero(),
+        total_counter_bids: Uint64::zero(),
+        total_fundings: Uint64::zero(),
+        total_rate_changes: Uint64::zero(),
+        total_deposits: Uint128::zero(),
+        total_refunded: Uint128::zero(),
+        total_collected: Uint128::zero(),
+        total_referral: Uint128::zero(),
+    });
+
+    let bid_1_time = 1234;
+    Bid::on("example", "bidder_1", bid_1_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    Bid::on("other", "bidder_2", bid_1_time)
+        .deposit(5_670)
+        .rate(30)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    assert_eq!(query_stats(deps.as_ref(), bid_1_time + 100), StatsResponse {
+        names: Uint64::new(2),
+        owned_names: Uint64::new(2),
+        counter_delay_names: Uint64::new(2),
+        total_bids: Uint64::new(2),
+        total_counter_bids: Uint64::zero(),
+        total_fundings: Uint64::zero(),
+        total_rate_changes: Uint64::zero(),
+        total_deposits: Uint128::from(35_670u64),
+        total_refunded: Uint128::zero(),
+        total_collected: Uint128::from(35_670u64),
+        total_referral: Uint128::zero(),
+    });
+
+    // Second name has expired when the new bid is placed on the first name
+    let bid_2_time = bid_1_time + 604_800 + 15_778_476;
+    Bid::on("example", "bidder_2", bid_2_time)
+        .deposit(30_000)
+        .rate(124)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    assert_eq!(query_stats(deps.as_ref(), bid_2_time + 100), StatsResponse {
+        names: Uint64::new(2),
+        owned_names: Uint64::new(1),
+        counter_delay_names: Uint64::new(1),
+        total_bids: Uint64::new(3),
+        total_counter_bids: Uint64::zero(),
+        total_fundings: Uint64::zero(),
+        total_rate_changes: Uint64::zero(),
+        total_deposits: Uint128::from(65_670u64),
+        total_refunded: Uint128::from(6_676u64),
+        total_collected: Uint128::from(58_994u64),
+        total_referral: Uint128::zero(),
+    });
+
+    let fund_time = bid_2_time + 604_800;
+    let env = mock_env().at_time(fund_time);
+    let info = mock_info("funder", &coins(10_000, ABC_COIN));
+    execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
+        name: "example".into(),
+        owner: "bidder_2".into(),
+        referrer: None,
+    }).unwrap();
+
+    assert_eq!(query_stats(deps.as_ref(), fund_time), StatsResponse {
+        names: Uint64::new(2),
+        owned_names: Uint64::new(1),
+        counter_delay_names: Uint64::zero(),
+        total_bids: Uint64::new(3),
+        total_counter_bids: Uint64::zero(),
+        total_fundings: Uint64::new(1),
+        total_rate_changes: Uint64::zero(),
+        total_deposits: Uint128::from(75_670u64),
+        total_refunded: Uint128::from(6_676u64),
+        total_collected: Uint128::from(68_994u64),
+        total_referral: Uint128::zero(),
+    });
+
+    let env = mock_env().at_time(fund_time);
+    let info = mock_info("bidder_2", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameRate {
+        name: "example".into(),
+        rate: Uint128::from(150u64),
+    }).unwrap();
+
+    // Bid on the expired name is not counted as a new name
+    Bid::on("other", "bidder_3", fund_time)
+        .deposit(5_670)
+        .rate(30)
+        .execute(deps.as_mut())
+        .unwrap();
+    Bid::on("other", "bidder_1", fund_time + 100)
+        .deposit(6_000)
+        .rate(31)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let stats = query_stats(deps.as_ref(), fund_time + 100);
+    assert_eq!(stats.names, Uint64::new(2));
+    assert_eq!(stats.owned_names, Uint64::new(2));
+    // The rate change opened the first name for counter bids again
+    assert_eq!(stats.counter_delay_names, Uint64::new(2));
+    assert_eq!(stats.total_bids, Uint64::new(5));
+    assert_eq!(stats.total_counter_bids, Uint64::new(1));
+    assert_eq!(stats.total_fundings, Uint64::new(1));
+    assert_eq!(stats.total_rate_changes, Uint64::new(1));
+}
+
+fn query_referral_balance(deps: Deps, address: &str) -> Uint128 {
+    let res = query(deps, mock_env(), QueryMsg::GetReferralBalance {
+        address: address.into(),
+    }).unwrap();
+    let response: ReferralBalanceResponse = from_binary(&res).unwrap();
+    response.balance
+}
+
+#[test]
+fn initialization_with_invalid_referral_share_fails() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        referral_share: Decimal::percent(101),
+        ..default_init()
+    };
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg);
+    assert!(matches!(res, Err(ContractError::InvalidConfig { .. })));
+}
+
+#[test]
+fn referrer_accrues_and_withdraws_balance() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        referral_share: Decimal::percent(10),
+        ..default_init()
+    };
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    // Referral share is credited instead of being sent to the collector
+    let bid_time = 1234;
+    let res = Bid::on("example", "bidder", bid_time)
+        .deposit(30_000)
+        .rate(123)
+        .referrer("frontend")
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(NameEvent::parse(&res.events[0]), Ok(NameEvent {
+        new_owner: Some(Addr::unchecked("bidder")),
+        rate: Uint128::from(123u64),
+        begin_time: Timestamp::from_seconds(bid_time),
+        expire_time: Some(Timestamp::from_seconds(bid_time + 21073170)),
+        deposit: Uint128::from(30_000u64),
+        collected: Uint128::from(27_000u64),
+        referrer: Some(Addr::unchecked("frontend")),
+        referral: Uint128::from(3_000u64),
+        ..NameEvent::new(NameAction::Bid, "example")
+    }));
+    assert_eq!(query_referral_balance(deps.as_ref(), "frontend"), Uint128::from(3_000u64));
+
+    // Bids without a referrer send everything to the collector
+    let res = Bid::on("other", "bidder", bid_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    let event = NameEvent::parse(&res.events[0]).unwrap();
+    assert_eq!(event.collected, Uint128::from(30_000u64));
+    assert_eq!(event.referral, Uint128::zero());
+
+    let fund_time = 20_000_000;
+    let env = mock_env().at_time(fund_time);
+    let info = mock_info("funder", &coins(10_000, ABC_COIN));
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
+        name: "example".into(),
+        owner: "bidder".into(),
+        referrer: Some("frontend".into()),
+    }).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(query_referral_balance(deps.as_ref(), "frontend"), Uint128::from(4_000u64));
+
+    let stats = query_stats(deps.as_ref(), fund_time);
+    assert_eq!(stats.total_deposits, Uint128::from(70_000u64));
+    assert_eq!(stats.total_collected, Uint128::from(66_000u64));
+    assert_eq!(stats.total_referral, Uint128::from(4_000u64));
+
+    // Balance is sent in a single message on withdrawal
+    let env = mock_env().at_time(fund_time);
+    let info = mock_info("frontend", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::WithdrawReferralBalance {
+        to: Some("treasury".into()),
+    }).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    match &res.messages[0].msg {
+        CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+            assert_eq!(to_address, "treasury");
+            assert_eq!(amount.len(), 1);
+            assert!(amount[0].amount < Uint128::from(4_000u64));
+        },
+        msg => panic!("Unexpected message type: {:?}", msg),
+    }
+    assert_eq!(ReferralEvent::parse(&res.events[0]), Ok(ReferralEvent {
+        referrer: Addr::unchecked("frontend"),
+        recipient: Addr::unchecked("treasury"),
+        amount: Uint128::from(4_000u64),
+    }));
+    assert_eq!(query_referral_balance(deps.as_ref(), "frontend"), Uint128::zero());
+
+    // Nothing left to withdraw
+    let env = mock_env().at_time(fund_time);
+    let info = mock_info("frontend", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::WithdrawReferralBalance {
+        to: None,
+    });
+    assert!(matches!(res, Err(ContractError::Unfunded { .. })));
+}
+
+fn recipients_init() -> InstantiateMsg {
+    InstantiateMsg {
+        recipients: vec![
+            RecipientMsg {
+                addr: "collector".into(),
+                share: Decimal::percent(50),
+                kind: RecipientKind::Deposit,
+            },
+            RecipientMsg {
+                addr: "treasury".into(),
+                share: Decimal::percent(30),
+                kind: RecipientKind::Send,
+            },
+            RecipientMsg {
+                addr: "grants".into(),
+                share: Decimal::percent(20),
+                kind: RecipientKind::Send,
+            },
+        ],
+        ..default_init()
+    }
+}
+
+#[test]
+fn initialization_with_invalid_recipients_fails() {
+    let invalid_recipients = [
+        vec![],
+        vec![(Decimal::percent(50), "collector"), (Decimal::percent(40), "treasury")],
+        vec![(Decimal::percent(60), "collector"), (Decimal::percent(50), "treasury")],
+        vec![(Decimal::one(), "collector"), (Decimal::zero(), "treasury")],
+    ];
+
+    for recipients in invalid_recipients {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            recipients: recipients.into_iter().map(|(share, addr)| RecipientMsg {
+                addr: addr.into(),
+                share,
+                kind: RecipientKind::Send,
+            }).collect(),
+            ..default_init()
+        };
+        let env = mock_env();
+        let info = mock_info("creator", &[]);
+
+        let res = instantiate(deps.as_mut(), env, info, msg);
+        assert!(matches!(res, Err(ContractError::InvalidConfig { .. })));
+    }
+}
+
+#[test]
+fn bid_splits_deposit_between_recipients() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        tax_policy: TaxPolicy::None {},
+        ..recipients_init()
+    };
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    // Shares are rounded down and the remainder goes to the first recipient
+    let res = Bid::on("example", "bidder", 1234)
+        .deposit(30_001)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages, vec![
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "collector".into(),
+            msg: to_binary(&RootCollectorExecuteMsg::Deposit {}).unwrap(),
+            funds: coins(15_001, ABC_COIN),
+        }),
+        SubMsg::new(BankMsg::Send {
+            to_address: "treasury".into(),
+            amount: coins(9_000, ABC_COIN),
+        }),
+        SubMsg::new(BankMsg::Send {
+            to_address: "grants".into(),
+            amount: coins(6_000, ABC_COIN),
+        }),
+    ]);
+
+    let event = NameEvent::parse(&res.events[0]).unwrap();
+    assert_eq!(event.collected, Uint128::from(30_001u64));
+
+    // Recipients whose share rounds down to zero are skipped
+    let env = mock_env().at_time(20_000_000);
+    let info = mock_info("funder", &coins(4, ABC_COIN));
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
+        name: "example".into(),
+        owner: "bidder".into(),
+        referrer: None,
+    }).unwrap();
+    assert_eq!(res.messages, vec![
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "collector".into(),
+            msg: to_binary(&RootCollectorExecuteMsg::Deposit {}).unwrap(),
+            funds: coins(3, ABC_COIN),
+        }),
+        SubMsg::new(BankMsg::Send {
+            to_address: "treasury".into(),
+            amount: coins(1, ABC_COIN),
+        }),
+    ]);
+}
+
+/// Store config exactly as stored by versions with a single collector and
+/// without a grace period
+fn store_baseline_config(storage: &mut dyn Storage) {
+    storage.set(&to_length_prefixed(b"config"), br#"{
+        "collector_addr": "collector",
+        "stable_denom": "uabc",
+        "min_lease_secs": "15778476",
+        "max_lease_secs": "157784760",
+        "counter_delay_secs": "604800",
+        "transition_delay_secs": "1814400",
+        "bid_delay_secs": "15778476"
+    }"#);
+}
+
+/// Store name state exactly as stored by versions with a single controller
+fn store_baseline_name_state(
+    storage: &mut dyn Storage,
+    name: &str,
+    owner: &str,
+    controller: Option<&str>,
+    begin_time: u64,
+) {
+    let controller = match controller {
+        Some(controller) => format!("\"{}\"", controller),
+        None => "null".into(),
+    };
+    let value = format!(r#"{{
+        "owner": "{owner}",
+        "controller": {controller},
+        "transition_reference_time": "{begin_time}",
+        "rate": "123",
+        "begin_time": "{begin_time}",
+        "begin_deposit": "30000",
+        "previous_owner": null,
+        "previous_transition_reference_time": "0"
+    }}"#, owner = owner, controller = controller, begin_time = begin_time);
+    let mut key = to_length_prefixed(b"name");
+    key.extend_from_slice(name.as_bytes());
+    storage.set(&key, value.as_bytes());
+}
+
+#[test]
+fn migrate_baseline_config() {
+    let mut deps = mock_dependencies(&[]);
+
+    store_baseline_config(&mut deps.storage);
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        admin: Some("admin".into()),
+        tax_policy: None,
+        index_limit: None,
+    }).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.admin, Some(Addr::unchecked("admin")));
+    assert_eq!(config.recipients, vec![
+        Recipient {
+            addr: Addr::unchecked("collector"),
+            share: Decimal::one(),
+            kind: RecipientKind::Deposit,
+        },
+    ]);
+    assert_eq!(config.referral_share, Decimal::zero());
+    assert_eq!(config.stable_denom, ABC_COIN);
+    assert_eq!(config.tax_policy, TaxPolicy::default());
+    assert_eq!(config.min_lease_secs, Timedelta::from_seconds(15_778_476));
+    assert_eq!(config.grace_period_secs, Timedelta::zero());
+    assert_eq!(config.grace_fee_secs, Timedelta::zero());
+}
+
+#[test]
+fn migrate_controller_to_role() {
+    let mut deps = mock_dependencies(&[]);
+
+    store_baseline_config(&mut deps.storage);
+    store_baseline_name_state(&mut deps.storage, "example", "owner", Some("controller"), 1234);
+    store_baseline_name_state(&mut deps.storage, "other", "owner", None, 1234);
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        admin: None,
+        tax_policy: None,
+        index_limit: None,
+    }).unwrap();
+
+    // The controller is granted the controller role
+    let name_state = read_name_state(&deps.storage, "example").unwrap();
+    assert_eq!(name_state.legacy_controller, None);
+    assert_eq!(name_state.roles.grants(), &[
+        RoleGrant {
+            role: Role::Controller,
+            address: Addr::unchecked("controller"),
+        },
+    ]);
+
+    // Roles are held once the counter delay has ended
+    let env = mock_env().at_time(1_000_000);
+    let res = query(deps.as_ref(), env, QueryMsg::GetNameState {
+        name: "example".into(),
+    }).unwrap();
+    let response: NameStateResponse = from_binary(&res).unwrap();
+    assert_eq!(response.controller, Some(Addr::unchecked("controller")));
+    assert!(response.has_role(Role::Controller, &Addr::unchecked("controller")));
+
+    let env = mock_env().at_time(1_000_000);
+    let res = query(deps.as_ref(), env, QueryMsg::GetNameState {
+        name: "other".into(),
+    }).unwrap();
+    let response: NameStateResponse = from_binary(&res).unwrap();
+    assert_eq!(response.controller, None);
+    assert!(response.roles.is_empty());
+}
+
+#[test]
+fn migrate_names_with_separator_to_default_namespace() {
+    let mut deps = mock_dependencies(&[]);
+
+    store_baseline_config(&mut deps.storage);
+    store_baseline_name_state(&mut deps.storage, "example.ust", "owner", None, 1234);
+    store_baseline_name_state(&mut deps.storage, "sub.example.ust", "owner", None, 1234);
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        admin: Some("admin".into()),
+        tax_policy: None,
+        index_limit: None,
+    }).unwrap();
+
+    let env = mock_env();
+    let info = mock_info("admin", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::CreateTld {
+        tld: "ust".into(),
+        config: ust_tld_config(),
+    }).unwrap();
+
+    // Stored names keep the config of the default namespace
+    for name in &["example.ust", "sub.example.ust"] {
+        let env = mock_env().at_time(1_000_000);
+        let res = query(deps.as_ref(), env, QueryMsg::GetNameState {
+            name: name.to_string(),
+        }).unwrap();
+        let response: NameStateResponse = from_binary(&res).unwrap();
+        assert_eq!(response.tld, None);
+        assert_eq!(response.name_owner, Some(Addr::unchecked("owner")));
+        assert_eq!(response.counter_delay_end, Timestamp::from_seconds(1234 + 604_800));
+    }
+
+    let env = mock_env().at_time(1_000_000);
+    let info = mock_info("owner", &coins(1000, ABC_COIN));
+    execute(deps.as_mut(), env, info, ExecuteMsg::FundName {
+        name: "sub.example.ust".into(),
+        owner: "owner".into(),
+        referrer: None,
+    }).unwrap();
+
+    // New names with the separator belong to the TLD
+    Bid::on("new.ust", "bidder", 1_000_000)
+        .deposit(10_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    let env = mock_env().at_time(1_000_000);
+    let res = query(deps.as_ref(), env, QueryMsg::GetNameState {
+        name: "new.ust".into(),
+    }).unwrap();
+    let response: NameStateResponse = from_binary(&res).unwrap();
+    assert_eq!(response.tld, Some("ust".into()));
+    assert_eq!(response.counter_delay_end, Timestamp::from_seconds(1_000_000 + 86_400));
+
+    let res = Bid::on("new.example.ust", "bidder", 1_000_000)
+        .deposit(10_000)
+        .rate(123)
+        .execute(deps.as_mut());
+    assert!(matches!(res, Err(ContractError::InvalidName { .. })));
+}
+
+#[test]
+fn migrate_tax_policy() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::CreateTld {
+        tld: "ust".into(),
+        config: ust_tld_config(),
+    }).unwrap();
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        admin: None,
+        tax_policy: Some(TaxPolicy::None {}),
+        index_limit: None,
+    }).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.tax_policy, TaxPolicy::None {});
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetTldConfig {
+        tld: "ust".into(),
+    }).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.tax_policy, TaxPolicy::None {});
+
+    // Deposits are sent without deducting tax
+    let res = Bid::on("example.ust", "bidder", 1234)
+        .deposit(10_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages, vec![
+        SubMsg::new(BankMsg::Send {
+            to_address: "treasury".into(),
+            amount: coins(10_000, ABC_COIN),
+        }),
+    ]);
+}
+
+#[cfg(not(feature = "terra"))]
+#[test]
+fn instantiate_with_unsupported_tax_policy() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        tax_policy: TaxPolicy::Terra {},
+        ..default_init()
+    };
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg);
+    assert!(matches!(res, Err(ContractError::UnsupportedTaxPolicy { .. })));
+}
+
+fn ust_tld_config() -> TldConfigMsg {
+    TldConfigMsg {
+        recipients: vec![
+            RecipientMsg {
+                addr: "treasury".into(),
+                share: Decimal::one(),
+                kind: RecipientKind::Send,
+            },
+        ],
+        min_lease_secs: Timedelta::from_seconds(2_592_000), // 30 days
+        max_lease_secs: Timedelta::from_seconds(31_556_952), // 1 year
+        counter_delay_secs: Timedelta::from_seconds(86_400), // 1 day
+        transition_delay_secs: Timedelta::from_seconds(604_800), // 1 week
+        bid_delay_secs: Timedelta::from_seconds(2_592_000), // 30 days
+        grace_period_secs: Timedelta::zero(),
+        grace_fee_secs: Timedelta::zero(),
+        referral_share: Decimal::zero(),
+        min_rate: Uint128::from(100u64),
+        retention_secs: Timedelta::zero(),
+        keeper_reward: Uint128::zero(),
+    }
+}
+
+#[test]
+fn create_tld_by_admin() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    // Only the admin can create TLDs
+    let env = mock_env();
+    let info = mock_info("other", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::CreateTld {
+        tld: "ust".into(),
+        config: ust_tld_config(),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    for tld in ["", "UST", "u.st", "u-st"] {
+        let env = mock_env();
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::CreateTld {
+            tld: tld.into(),
+            config: ust_tld_config(),
+        });
+        assert!(matches!(res, Err(ContractError::InvalidTld { .. })));
+    }
+
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::CreateTld {
+        tld: "ust".into(),
+        config: TldConfigMsg {
+            min_lease_secs: Timedelta::from_seconds(31_556_953),
+            ..ust_tld_config()
+        },
+    });
+    assert!(matches!(res, Err(ContractError::InvalidConfig { .. })));
+
+    for tld in ["ust", "dao"] {
+        let env = mock_env();
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::CreateTld {
+            tld: tld.into(),
+            config: ust_tld_config(),
+        }).unwrap();
+        assert_eq!(AdminEvent::parse(&res.events[0]), Ok(AdminEvent {
+            action: AdminAction::CreateTld,
+            admin: Addr::unchecked("creator"),
+            tld: Some(tld.into()),
+            resolver_contract: None,
+        }));
+    }
+
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::CreateTld {
+        tld: "ust".into(),
+        config: ust_tld_config(),
+    });
+    assert!(matches!(res, Err(ContractError::TldExists { .. })));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::ListTlds {
+        start_after: None,
+        limit: None,
+    }).unwrap();
+    let response: TldsResponse = from_binary(&res).unwrap();
+    assert_eq!(response.tlds, vec!["dao".to_string(), "ust".to_string()]);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetTldConfig {
+        tld: "ust".into(),
+    }).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.admin, Some(Addr::unchecked("creator")));
+    assert_eq!(config.recipients, vec![
+        Recipient {
+            addr: Addr::unchecked("treasury"),
+            share: Decimal::one(),
+            kind: RecipientKind::Send,
+        },
+    ]);
+    assert_eq!(config.stable_denom, ABC_COIN);
+    assert_eq!(config.tax_policy, mock_tax_policy());
+    assert_eq!(config.counter_delay_secs, Timedelta::from_seconds(86_400));
+    assert_eq!(config.min_rate, Uint128::from(100u64));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetTldConfig {
+        tld: "app".into(),
+    });
+    assert!(matches!(res, Err(ContractError::UnknownTld { .. })));
+}
+
+#[test]
+fn bid_on_tld_name_uses_tld_config() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        tax_policy: TaxPolicy::None {},
+        ..default_init()
+    };
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::CreateTld {
+        tld: "ust".into(),
+        config: ust_tld_config(),
+    }).unwrap();
+
+    let res = Bid::on("example.app", "bidder", 1234)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut());
+    assert!(matches!(res, Err(ContractError::UnknownTld { .. })));
+
+    for name in ["sub.example.ust", ".ust", "example."] {
+        let res = Bid::on(name, "bidder", 1234)
+            .deposit(30_000)
+            .rate(123)
+            .execute(deps.as_mut());
+        assert!(matches!(res, Err(ContractError::InvalidName { .. })));
+    }
+
+    // Rate is below the minimum of the TLD
+    let res = Bid::on("example.ust", "bidder", 1234)
+        .deposit(3_000)
+        .rate(99)
+        .execute(deps.as_mut());
+    assert!(matches!(res, Err(ContractError::BidRateTooLow { .. })));
+
+    // Deposit covers less than six months but more than the TLD minimum
+    let res = Bid::on("example.ust", "bidder", 1234)
+        .deposit(10_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages, vec![
+        SubMsg::new(BankMsg::Send {
+            to_address: "treasury".into(),
+            amount: coins(10_000, ABC_COIN),
+        }),
+    ]);
+
+    let res = Bid::on("example", "bidder", 1234)
+        .deposit(10_000)
+        .rate(123)
+        .execute(deps.as_mut());
+    assert!(matches!(res, Err(ContractError::BidInvalidInterval { .. })));
+
+    let res = Bid::on("example", "bidder", 1234)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    assert_eq!(res.messages, vec![
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "collector".into(),
+            msg: to_binary(&RootCollectorExecuteMsg::Deposit {}).unwrap(),
+            funds: coins(30_000, ABC_COIN),
+        }),
+    ]);
+
+    // Names are listed with the delays of their own TLD
+    let res = query(deps.as_ref(), mock_env().at_time(1234), QueryMsg::GetAllNameStates {
+        start_after: None,
+        limit: None,
+    }).unwrap();
+    let response: AllNameStatesResponse = from_binary(&res).unwrap();
+    let counter_delay_ends: Vec<_> = response.names.iter()
+        .map(|item| (item.name.as_str(), item.state.counter_delay_end))
+        .collect();
+    assert_eq!(counter_delay_ends, vec![
+        ("example", Timestamp::from_seconds(1234 + 604_800)),
+        ("example.ust", Timestamp::from_seconds(1234 + 86_400)),
+    ]);
+
+    // Only the name in the default namespace is still in counter-delay
+    let res = query(deps.as_ref(), mock_env().at_time(1234 + 86_400), QueryMsg::ListNames {
+        start_after: None,
+        limit: None,
+        order: None,
+        status: Some(NameStatus::CounterDelay),
+        prefix: None,
+        min_rate: None,
+        owner: None,
+    }).unwrap();
+    let response: ListNamesResponse = from_binary(&res).unwrap();
+    let names: Vec<_> = response.names.iter().map(|item| item.name.as_str()).collect();
+    assert_eq!(names, vec!["example"]);
+
+    // Rate cannot be set below the minimum of the TLD
+    let env = mock_env().at_time(1234 + 86_400);
+    let info = mock_info("bidder", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameRate {
+        name: "example.ust".into(),
+        rate: Uint128::from(50u64),
+    });
+    assert!(matches!(res, Err(ContractError::BidRateTooLow { .. })));
+}
+
+#[test]
+fn prune_expired_names() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        tax_policy: TaxPolicy::None {},
+        retention_secs: Timedelta::from_seconds(1_000_000),
+        keeper_reward: Uint128::from(100u64),
+        ..default_init()
+    };
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    let env = mock_env();
+    let info = mock_info("other", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetResolverContract {
+        resolver_contract: Some("resolver".into()),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetResolverContract {
+        resolver_contract: Some("resolver".into()),
+    }).unwrap();
+
+    let env = mock_env();
+    let info = mock_info("sponsor", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundKeeperPool {});
+    assert!(matches!(res, Err(ContractError::Unfunded { .. })));
+
+    let env = mock_env();
+    let info = mock_info("sponsor", &coins(150, ABC_COIN));
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::FundKeeperPool {}).unwrap();
+    assert_eq!(KeeperEvent::parse(&res.events[0]), Ok(KeeperEvent {
+        action: KeeperAction::FundKeeperPool,
+        address: Addr::unchecked("sponsor"),
+        amount: Uint128::from(150u64),
+        balance: Uint128::from(150u64),
+        pruned: 0,
+        last_scanned: None,
+    }));
+
+    Bid::on("example", "bidder", 1234)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    Bid::on("other", "bidder", 1234)
+        .deposit(5_670)
+        .rate(30)
+        .execute(deps.as_mut())
+        .unwrap();
+    Bid::on("forever", "bidder", 1234)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let other_prune_time = 1234 + 16_329_600 + 1_000_000;
+    let example_prune_time = 1234 + 21_073_170 + 1_000_000;
+
+    // Nothing can be pruned until the retention period has passed
+    let env = mock_env().at_time(other_prune_time - 1);
+    let info = mock_info("keeper", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::PruneExpired {
+        names: vec!["example".into(), "other".into()],
+    }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+    assert_eq!(res.events.len(), 1);
+    assert_eq!(KeeperEvent::parse(&res.events[0]), Ok(KeeperEvent {
+        action: KeeperAction::PruneExpired,
+        address: Addr::unchecked("keeper"),
+        amount: Uint128::zero(),
+        balance: Uint128::from(150u64),
+        pruned: 0,
+        last_scanned: None,
+    }));
+
+    let env = mock_env().at_time(other_prune_time);
+    let info = mock_info("keeper", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::PruneExpired {
+        names: vec![
+            "example".into(), "other".into(), "forever".into(), "missing".into(),
+        ],
+    }).unwrap();
+    assert_eq!(res.messages, vec![
+        SubMsg::new(BankMsg::Send {
+            to_address: "keeper".into(),
+            amount: coins(100, ABC_COIN),
+        }),
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "resolver".into(),
+            msg: to_binary(&ResolverExecuteMsg::DropNameValues {
+                names: vec!["other".into()],
+            }).unwrap(),
+            funds: vec![],
+        }),
+    ]);
+    assert_eq!(res.events.len(), 2);
+    assert_eq!(NameEvent::parse(&res.events[0]), Ok(NameEvent {
+        old_owner: Some(Addr::unchecked("bidder")),
+        rate: Uint128::from(30u64),
+        begin_time: Timestamp::from_seconds(1234),
+        expire_time: Some(Timestamp::from_seconds(1234 + 16_329_600)),
+        address: Some(Addr::unchecked("keeper")),
+        ..NameEvent::new(NameAction::Prune, "other")
+    }));
+    assert_eq!(KeeperEvent::parse(&res.events[1]), Ok(KeeperEvent {
+        action: KeeperAction::PruneExpired,
+        address: Addr::unchecked("keeper"),
+        amount: Uint128::from(100u64),
+        balance: Uint128::from(50u64),
+        pruned: 1,
+        last_scanned: None,
+    }));
+    assert_eq!(query_stats(deps.as_ref(), other_prune_time).names, Uint64::new(2));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetNameState {
+        name: "other".into(),
+    });
+    assert!(res.is_err());
+
+    // Keeper reward is limited by the remaining pool
+    let env = mock_env().at_time(example_prune_time);
+    let info = mock_info("keeper", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::PruneExpiredBatch {
+        start_after: None,
+        limit: None,
+    }).unwrap();
+    assert_eq!(res.messages, vec![
+        SubMsg::new(BankMsg::Send {
+            to_address: "keeper".into(),
+            amount: coins(50, ABC_COIN),
+        }),
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "resolver".into(),
+            msg: to_binary(&ResolverExecuteMsg::DropNameValues {
+                names: vec!["example".into()],
+            }).unwrap(),
+            funds: vec![],
+        }),
+    ]);
+    assert_eq!(KeeperEvent::parse(res.events.last().unwrap()), Ok(KeeperEvent {
+        action: KeeperAction::PruneExpired,
+        address: Addr::unchecked("keeper"),
+        amount: Uint128::from(50u64),
+        balance: Uint128::zero(),
+        pruned: 1,
+        last_scanned: Some("example".into()),
+    }));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetKeeperPool {}).unwrap();
+    let response: KeeperPoolResponse = from_binary(&res).unwrap();
+    assert_eq!(response.balance, Uint128::zero());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllNameStates {
+        start_after: None,
+        limit: None,
+    }).unwrap();
+    let response: AllNameStatesResponse = from_binary(&res).unwrap();
+    let names: Vec<_> = response.names.iter().map(|item| item.name.as_str()).collect();
+    assert_eq!(names, vec!["forever"]);
+
+    // Pruned names are open for new bids
+    Bid::on("example", "new_bidder", example_prune_time)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+}
+
+#[test]
+fn prune_expired_batch_in_expire_order() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        tax_policy: TaxPolicy::None {},
+        retention_secs: Timedelta::from_seconds(1_000_000),
+        ..default_init()
+    };
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    Bid::on("early", "bidder", 1234)
+        .deposit(5_670)
+        .rate(30)
+        .execute(deps.as_mut())
+        .unwrap();
+    Bid::on("aaa", "bidder", 1234)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+    Bid::on("forever", "bidder", 1234)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let early_expire_time = 1234 + 16_329_600;
+    let aaa_prune_time = 1234 + 21_073_170 + 1_000_000;
+
+    let prune_batch = |deps: DepsMut, time, start_after: Option<&str>, limit| {
+        let env = mock_env().at_time(time);
+        let info = mock_info("keeper", &[]);
+        let res = execute(deps, env, info, ExecuteMsg::PruneExpiredBatch {
+            start_after: start_after.map(String::from),
+            limit,
+        }).unwrap();
+        KeeperEvent::parse(res.events.last().unwrap()).unwrap()
+    };
+
+    // Only expired names are scanned
+    let event = prune_batch(deps.as_mut(), early_expire_time, None, None);
+    assert_eq!(event.pruned, 0);
+    assert_eq!(event.last_scanned, Some("early".into()));
+
+    let event = prune_batch(deps.as_mut(), early_expire_time, Some("early"), None);
+    assert_eq!(event.pruned, 0);
+    assert_eq!(event.last_scanned, None);
+
+    // Names that expired first are pruned first
+    let event = prune_batch(deps.as_mut(), aaa_prune_time, None, Some(1));
+    assert_eq!(event.pruned, 1);
+    assert_eq!(event.last_scanned, Some("early".into()));
+    assert!(read_option_name_state(&deps.storage, "early").unwrap().is_none());
+
+    // Scanning starts over after a pruned name
+    let event = prune_batch(deps.as_mut(), aaa_prune_time, Some("early"), Some(1));
+    assert_eq!(event.pruned, 1);
+    assert_eq!(event.last_scanned, Some("aaa".into()));
+
+    let event = prune_batch(deps.as_mut(), aaa_prune_time, Some("aaa"), None);
+    assert_eq!(event.pruned, 0);
+    assert_eq!(event.last_scanned, None);
+    assert!(read_option_name_state(&deps.storage, "forever").unwrap().is_some());
+}
+
+fn query_permissions(deps: Deps, name: &str, address: &str, time: u64) -> PermissionsResponse {
+    let env = mock_env().at_time(time);
+    let res = query(deps, env, QueryMsg::GetPermissions {
+        name: name.into(),
+        address: address.into(),
+    }).unwrap();
+    from_binary(&res).unwrap()
+}
+
+fn unavailable_times(response: &PermissionsResponse) -> Vec<(Permission, Option<u64>)> {
+    response.unavailable.iter()
+        .map(|item| (item.permission, item.available_time.map(u64::from)))
+        .collect()
+}
+
+#[test]
+fn permissions_for_owner_and_other() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = grace_period_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    // Unclaimed names can only be bid on
+    let response = query_permissions(deps.as_ref(), "example", "bidder", 1000);
+    assert_eq!(response.status, None);
+    assert_eq!(response.allowed, vec![Permission::Bid]);
+    assert!(unavailable_times(&response).iter().all(|(_, time)| time.is_none()));
+
+    Bid::on("example", "bidder", 1234)
+        .deposit(30_000)
+        .rate(123)
+        .execute(deps.as_mut())
+        .unwrap();
+
+    let counter_delay_end = 1234 + 604_800;
+    let bid_delay_end = counter_delay_end + 15_778_476;
+    let expire_time = 1234 + 21_073_170;
+    let grace_period_end = expire_time + 1_209_600;
+
+    // Bid owner in counter delay
+    let response = query_permissions(deps.as_ref(), "example", "bidder", 1234);
+    assert_eq!(response.status, Some(NameStatus::CounterDelay));
+    assert_eq!(response.allowed, vec![Permission::Fund, Permission::TransferBidOwner]);
+    assert_eq!(unavailable_times(&response), vec![
+        (Permission::Bid, Some(grace_period_end)),
+        (Permission::Renew, Some(expire_time)),
+        (Permission::SetRate, Some(counter_delay_end)),
+        (Permission::TransferNameOwner, Some(counter_delay_end)),
+        (Permission::SetController, Some(counter_delay_end)),
+        (Permission::Prune, Some(grace_period_end)),
+    ]);
+
+    // Other address in counter delay can counter-bid
+    let response = query_permissions(deps.as_ref(), "example", "other", 1234);
+    assert_eq!(response.status, Some(NameStatus::CounterDelay));
+    assert_eq!(response.allowed, vec![Permission::Bid, Permission::Fund]);
+    assert_eq!(unavailable_times(&response), vec![
+        (Permission::Renew, None),
+        (Permission::SetRate, None),
+        (Permission::TransferNameOwner, None),
+        (Permission::TransferBidOwner, None),
+        (Permission::SetController, None),
+        (Permission::Prune, Some(grace_period_end)),
+    ]);
+
+    // Other address after counter delay has to wait for the bid delay
+    let response = query_permissions(deps.as_ref(), "example", "other", counter_delay_end);
+    assert_eq!(response.status, Some(NameStatus::TransitionDelay));
+    assert_eq!(response.allowed, vec![Permission::Fund]);
+    assert_eq!(unavailable_times(&response)[0], (Permission::Bid, Some(bid_delay_end)));
+
+    let response = query_permissions(deps.as_ref(), "example", "bidder", counter_delay_end);
+    assert_eq!(response.allowed, vec![
+        Permission::Fund,
+        Permission::SetRate,
+        Permission::TransferNameOwner,
+        Permission::SetController,
+    ]);
+
+    // Owner in grace period can only renew
+    let response = query_permissions(deps.as_ref(), "example", "bidder", expire_time);
+    assert_eq!(response.status, Some(NameStatus::Grace));
+    assert_eq!(response.allowed, vec![Permission::Renew]);
+
+    // Expired names are open for bids and can be pruned
+    let response = query_permissions(deps.as_ref(), "example", "other", grace_period_end);
+    assert_eq!(response.status, Some(NameStatus::Expired));
+    assert_eq!(response.allowed, vec![Permission::Bid, Permission::Prune]);
+    assert!(unavailable_times(&response).iter().all(|(_, time)| time.is_none()));
+}
+
+proptest! {
+    #[test]
+    fn bid_with_overflowing_rate_fails(
+        rate in (u128::MAX / 16)..=u128::MAX,
+        deposit in 0..=u128::MAX,
+    ) {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = default_init();
+        let env = mock_env();
+        let info = mock_info("creator", &[]);
+        instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+        let res = Bid::on("example", "bidder", 1234)
+            .deposit(deposit)
+            .rate(rate)
+            .execute(deps.as_mut());
+        prop_assert!(matches!(res, Err(ContractError::Overflow { .. })), "{:?}", res);
+    }
+
+    #[test]
+    fn name_state_times_overflow_is_error(
+        begin_time in (u64::MAX - 604_799)..=u64::MAX,
+        current_time in 0..=u64::MAX,
+    ) {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = default_init();
+        let env = mock_env();
+        let info = mock_info("creator", &[]);
+        instantiate(deps.as_mut(), env, info, msg).unwrap();
+        let config = read_config(&deps.storage).unwrap();
+
+        let name_state = NameState {
+            owner: Addr::unchecked("owner"),
+            roles: Default::default(),
+            legacy_controller: None,
+            transition_reference_time: Timestamp::from_seconds(begin_time),
+            rate: Uint128::new(1),
+            begin_time: Timestamp::from_seconds(begin_time),
+            begin_deposit: Uint128::new(u128::MAX),
+            previous_owner: None,
+            previous_roles: Default::default(),
+            previous_transition_reference_time: Timestamp::from_seconds(0),
+        };
+
+        // Times beyond the range of a timestamp are errors instead of panics
+        prop_assert!(name_state.expire_time().is_err());
+        prop_assert!(name_state.counter_delay_end(&config).is_err());
+        let owner_status = name_state.owner_status(&config, Timestamp::from_seconds(current_time));
+        prop_assert!(current_time < begin_time || owner_status.is_err());
+    }
+}
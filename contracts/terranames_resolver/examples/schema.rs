@@ -4,7 +4,8 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use terranames::resolver::{
-    ConfigResponse, InstantiateMsg, ExecuteMsg, QueryMsg, ResolveNameResponse,
+    ConfigResponse, InstantiateMsg, ExecuteMsg, ListRecordsResponse, QueryMsg,
+    ResolveNameResponse, ResolveRecordResponse,
 };
 
 fn main() {
@@ -19,4 +20,6 @@ fn main() {
 
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(ResolveNameResponse), &out_dir);
+    export_schema(&schema_for!(ResolveRecordResponse), &out_dir);
+    export_schema(&schema_for!(ListRecordsResponse), &out_dir);
 }
@@ -1,23 +1,46 @@
 use cosmwasm_std::{
-    entry_point, to_binary, Deps, DepsMut, Env, MessageInfo,
-    QueryResponse, Response, StdResult,
+    entry_point, to_binary, Addr, Deps, DepsMut, Env, Event, MessageInfo,
+    QueryResponse, Response, StdError, StdResult, Storage,
 };
 use snafu::OptionExt;
 
+use terranames::auction::{
+    is_valid_name, NameStateResponse, Role,
+};
+use terranames::events::{MigrateEvent, RecordEvent, ValueEvent};
 use terranames::querier::query_name_state;
 use terranames::resolver::{
-    ConfigResponse, InstantiateMsg, ExecuteMsg, MigrateMsg, QueryMsg,
-    ResolveNameResponse,
+    ConfigResponse, InstantiateMsg, ExecuteMsg, ListRecordsResponse,
+    MigrateMsg, QueryMsg, Record, RecordKey, RecordValidation,
+    ResolveNameResponse, ResolveRecordResponse,
 };
 use terranames::utils::Timestamp;
 
-use crate::errors::{ContractError, NameExpired, Unauthorized};
+use crate::errors::{
+    ContractError, InvalidConfig, InvalidName, InvalidRecordKey,
+    InvalidRecordValue, MigrationInProgress, NameExpired, TooManyRecords,
+    Unauthorized,
+};
 use crate::state::{
-    read_config, read_name_value, store_config, store_name_value, Config,
+    is_epoch_migration_pending, migrate_singletons, read_config,
+    read_epoch_migration, read_name_epoch, read_name_record,
+    read_name_record_keys, read_name_records, read_value_names,
+    remove_epoch_migration, remove_name_epoch, remove_name_records,
+    remove_name_value, start_epoch_migration, store_config,
+    store_epoch_migration, store_name_epoch, store_name_record, Config,
+    OwnerEpoch, MAX_RECORDS,
 };
 
 type ContractResult<T> = Result<T, ContractError>;
 
+/// Default number of values stamped by a migration from a version without
+/// epochs
+const DEFAULT_EPOCH_LIMIT: u32 = 100;
+
+/// Maximum number of values stamped by a migration from a version without
+/// epochs
+const MAX_EPOCH_LIMIT: u32 = 300;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -26,9 +49,11 @@ pub fn instantiate(
     msg: InstantiateMsg,
 ) -> ContractResult<Response> {
     let auction_contract = deps.api.addr_validate(&msg.auction_contract)?;
+    validate_record_validations(&msg.record_validations)?;
 
     let state = Config {
         auction_contract,
+        record_validations: msg.record_validations,
     };
 
     store_config(deps.storage, &state)?;
@@ -45,31 +70,94 @@ pub fn execute(
 ) -> ContractResult<Response> {
     match msg {
         ExecuteMsg::SetNameValue { name, value } => {
-            execute_set_value(deps, env, info, name, value)
+            execute_set_records(deps, env, info, name, vec![(RecordKey::Default {}, value)])
+        },
+        ExecuteMsg::DropNameValues { names } => {
+            execute_drop_values(deps, env, info, names)
+        },
+        ExecuteMsg::SetRecord { name, key, value } => {
+            execute_set_records(deps, env, info, name, vec![(key, Some(value))])
+        },
+        ExecuteMsg::SetRecords { name, records } => {
+            let records = records.into_iter()
+                .map(|Record { key, value }| (key, Some(value)))
+                .collect();
+            execute_set_records(deps, env, info, name, records)
+        },
+        ExecuteMsg::DeleteRecord { name, key } => {
+            execute_set_records(deps, env, info, name, vec![(key, None)])
         },
     }
 }
 
-fn execute_set_value(
-    deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    name: String,
-    value: Option<String>,
-) -> ContractResult<Response> {
+/// Check that record validations have valid keys and formats
+///
+/// Each record can only be validated against one format.
+fn validate_record_validations(
+    record_validations: &[RecordValidation],
+) -> ContractResult<()> {
+    for (index, validation) in record_validations.iter().enumerate() {
+        if !validation.key.is_valid() || !validation.format.is_valid() {
+            return InvalidConfig.fail();
+        }
+        if record_validations[..index].iter().any(|other| other.key == validation.key) {
+            return InvalidConfig.fail();
+        }
+    }
+    Ok(())
+}
+
+/// Validate value of record if a format is configured for the key
+fn validate_record(
+    deps: Deps,
+    config: &Config,
+    key: &RecordKey,
+    value: &str,
+) -> ContractResult<()> {
+    let validation = config.record_validations.iter()
+        .find(|validation| &validation.key == key);
+    if let Some(validation) = validation {
+        if let Err(err) = validation.format.validate(deps.api, value) {
+            let reason = match err {
+                StdError::GenericErr { msg, .. } => msg,
+                err => err.to_string(),
+            };
+            return InvalidRecordValue {
+                key: key.to_string(),
+                reason,
+            }.fail();
+        }
+    }
+    Ok(())
+}
+
+/// Query state of name from the auction contract
+///
+/// Names that are not valid are only accepted if the auction holds them,
+/// since names stored before TLDs existed may contain the separator.
+fn query_auction_name_state(
+    deps: Deps,
+    name: &str,
+) -> ContractResult<NameStateResponse> {
     let config = read_config(deps.storage)?;
-    let name_state = query_name_state(
-        &deps.querier,
-        &config.auction_contract,
-        &name,
-    )?;
+    match query_name_state(&deps.querier, &config.auction_contract, name) {
+        Ok(name_state) => Ok(name_state),
+        Err(_) if !is_valid_name(name) => InvalidName.fail(),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Return state of name if the sender is allowed to set its records
+fn authorize_controller(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    name: &str,
+) -> ContractResult<NameStateResponse> {
+    let name_state = query_auction_name_state(deps, name)?;
 
     // ensure name controller permission
-    if let Some(controller) = name_state.controller {
-        if info.sender != controller {
-            return Unauthorized.fail();
-        }
-    } else {
+    if !name_state.has_role(Role::Controller, &info.sender) {
         return Unauthorized.fail();
     }
 
@@ -79,17 +167,132 @@ fn execute_set_value(
         }
     }
 
-    store_name_value(deps.storage, &name, value.clone())?;
+    Ok(name_state)
+}
 
-    let mut response = Response::new()
-        .add_attribute("action", "set_value");
-    response = if let Some(ref value) = value {
-        response.add_attribute("value", value)
-    } else {
-        response.add_attribute("value_deleted", "")
+/// Remove all records of name and return events for the removed records
+fn remove_records(
+    storage: &mut dyn Storage,
+    name: &str,
+    controller: &Addr,
+) -> StdResult<Vec<Event>> {
+    let mut events = vec![];
+    if remove_name_value(storage, name)? {
+        let event = ValueEvent {
+            name: name.to_string(),
+            owner: None,
+            controller: controller.clone(),
+            value: None,
+            expire_time: None,
+        };
+        events.push(event.into());
+    }
+    for key in remove_name_records(storage, name)? {
+        let event = RecordEvent {
+            name: name.to_string(),
+            owner: None,
+            controller: controller.clone(),
+            key,
+            value: None,
+            expire_time: None,
+        };
+        events.push(event.into());
+    }
+    Ok(events)
+}
+
+/// Set records of name (records with a value of None are deleted)
+///
+/// The default record is reported by a value event and other records by
+/// record events. Records left from a previous ownership epoch or without an
+/// epoch are removed first.
+fn execute_set_records(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+    records: Vec<(RecordKey, Option<String>)>,
+) -> ContractResult<Response> {
+    // Values without an epoch may still be stamped by the migration
+    if is_epoch_migration_pending(deps.storage)? {
+        return MigrationInProgress.fail();
+    }
+
+    let name_state = authorize_controller(deps.as_ref(), &env, &info, &name)?;
+    let epoch = OwnerEpoch {
+        owner: name_state.name_owner.clone().context(NameExpired {})?,
+        transition_reference_time: name_state.transition_reference_time,
     };
 
-    Ok(response)
+    let config = read_config(deps.storage)?;
+    for (key, value) in &records {
+        if !key.is_valid() {
+            return InvalidRecordKey.fail();
+        }
+        if let Some(value) = value {
+            validate_record(deps.as_ref(), &config, key, value)?;
+        }
+    }
+
+    let mut events = vec![];
+    if read_name_epoch(deps.storage, &name)? != Some(epoch.clone()) {
+        events.extend(remove_records(deps.storage, &name, &info.sender)?);
+        store_name_epoch(deps.storage, &name, &epoch)?;
+    }
+
+    for (key, value) in records {
+        store_name_record(deps.storage, &name, &key, value.clone())?;
+
+        events.push(match key {
+            RecordKey::Default {} => ValueEvent {
+                name: name.clone(),
+                owner: name_state.name_owner.clone(),
+                controller: info.sender.clone(),
+                value,
+                expire_time: name_state.expire_time,
+            }.into(),
+            key => RecordEvent {
+                name: name.clone(),
+                owner: name_state.name_owner.clone(),
+                controller: info.sender.clone(),
+                key,
+                value,
+                expire_time: name_state.expire_time,
+            }.into(),
+        });
+    }
+
+    if read_name_record_keys(deps.storage, &name)?.len() > MAX_RECORDS {
+        return TooManyRecords.fail();
+    }
+
+    Ok(Response::new()
+        .add_events(events)
+    )
+}
+
+fn execute_drop_values(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    names: Vec<String>,
+) -> ContractResult<Response> {
+    let config = read_config(deps.storage)?;
+
+    // Only the auction knows when the state of a name has been pruned
+    if info.sender != config.auction_contract {
+        return Unauthorized.fail();
+    }
+
+    let mut events = vec![];
+    for name in names {
+        events.extend(remove_records(deps.storage, &name, &info.sender)?);
+        remove_name_epoch(deps.storage, &name);
+    }
+
+    Ok(Response::new()
+        .add_events(events)
+    )
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -105,6 +308,12 @@ pub fn query(
         QueryMsg::ResolveName { name } => {
             Ok(to_binary(&query_resolve(deps, env, name)?)?)
         },
+        QueryMsg::ResolveRecord { name, key } => {
+            Ok(to_binary(&query_resolve_record(deps, env, name, key)?)?)
+        },
+        QueryMsg::ListRecords { name } => {
+            Ok(to_binary(&query_list_records(deps, env, name)?)?)
+        },
     }
 }
 
@@ -115,37 +324,184 @@ fn query_config(
     let config = read_config(deps.storage)?;
 
     Ok(ConfigResponse {
-        auction_contract: config.auction_contract.into(),
+        auction_contract: config.auction_contract,
+        record_validations: config.record_validations,
+    })
+}
+
+/// Owner, expire time and whether the name has lapsed
+///
+/// Records only resolve if they were set in the current ownership epoch.
+/// Values stored before epochs were tracked resolve until the migration has
+/// stamped them with the epoch of their owner.
+struct ResolvedName {
+    owner: Addr,
+    expire_time: Option<Timestamp>,
+    lapsed: bool,
+    current_epoch: bool,
+    tld: Option<String>,
+}
+
+fn resolve_name_state(
+    deps: Deps,
+    env: &Env,
+    name: &str,
+) -> ContractResult<ResolvedName> {
+    let name_state = query_auction_name_state(deps, name)?;
+
+    let owner = name_state.name_owner.context(NameExpired {})?;
+    let epoch = OwnerEpoch {
+        owner: owner.clone(),
+        transition_reference_time: name_state.transition_reference_time,
+    };
+    let current_epoch = match read_name_epoch(deps.storage, name)? {
+        Some(stored_epoch) => stored_epoch == epoch,
+        None => is_epoch_migration_pending(deps.storage)?,
+    };
+
+    // The auction keeps reporting the owner in the grace period after
+    // expiration but the records are marked as lapsed.
+    let lapsed = match name_state.expire_time {
+        Some(expire_time) => Timestamp::from(env.block.time) >= expire_time,
+        None => false,
+    };
+
+    Ok(ResolvedName {
+        owner,
+        expire_time: name_state.expire_time,
+        lapsed,
+        current_epoch,
+        tld: name_state.tld,
     })
 }
 
 fn query_resolve(
     deps: Deps,
-    _env: Env,
+    env: Env,
     name: String,
 ) -> ContractResult<ResolveNameResponse> {
-    let config = read_config(deps.storage)?;
-    let name_state = query_name_state(
-        &deps.querier,
-        &config.auction_contract,
-        &name,
-    )?;
-    let name_value = read_name_value(deps.storage, &name)?;
-
-    let owner = name_state.name_owner.context(NameExpired {})?;
+    let resolved = resolve_name_state(deps, &env, &name)?;
+    let name_value = match resolved.current_epoch {
+        true => read_name_record(deps.storage, &name, &RecordKey::Default {})?,
+        false => None,
+    };
 
     Ok(ResolveNameResponse {
         value: name_value,
-        owner,
-        expire_time: name_state.expire_time,
+        owner: resolved.owner,
+        expire_time: resolved.expire_time,
+        lapsed: resolved.lapsed,
+        tld: resolved.tld,
+    })
+}
+
+fn query_resolve_record(
+    deps: Deps,
+    env: Env,
+    name: String,
+    key: RecordKey,
+) -> ContractResult<ResolveRecordResponse> {
+    let resolved = resolve_name_state(deps, &env, &name)?;
+    let value = match resolved.current_epoch {
+        true => read_name_record(deps.storage, &name, &key)?,
+        false => None,
+    };
+
+    Ok(ResolveRecordResponse {
+        value,
+        owner: resolved.owner,
+        expire_time: resolved.expire_time,
+        lapsed: resolved.lapsed,
+        tld: resolved.tld,
+    })
+}
+
+fn query_list_records(
+    deps: Deps,
+    env: Env,
+    name: String,
+) -> ContractResult<ListRecordsResponse> {
+    let resolved = resolve_name_state(deps, &env, &name)?;
+    let records = match resolved.current_epoch {
+        true => read_name_records(deps.storage, &name)?,
+        false => vec![],
+    };
+
+    Ok(ListRecordsResponse {
+        records,
+        owner: resolved.owner,
+        expire_time: resolved.expire_time,
+        lapsed: resolved.lapsed,
+        tld: resolved.tld,
     })
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
-    _msg: MigrateMsg,
-) -> StdResult<Response> {
-    Ok(Response::default())
+    msg: MigrateMsg,
+) -> ContractResult<Response> {
+    if migrate_singletons(deps.storage)? {
+        start_epoch_migration(deps.storage)?;
+    }
+
+    if let Some(record_validations) = msg.record_validations {
+        validate_record_validations(&record_validations)?;
+        let mut config = read_config(deps.storage)?;
+        config.record_validations = record_validations;
+        store_config(deps.storage, &config)?;
+    }
+
+    let epoch_limit = msg.epoch_limit.unwrap_or(DEFAULT_EPOCH_LIMIT).min(MAX_EPOCH_LIMIT);
+    let (count, remaining) = continue_epoch_migration(deps, epoch_limit)?;
+
+    let event = MigrateEvent {
+        migrated: count as u64,
+        complete: !remaining,
+    };
+
+    Ok(Response::new()
+        .add_event(event.into())
+    )
+}
+
+/// Stamp at most limit values stored before epochs were tracked with the
+/// epoch of the current owner
+///
+/// Values of names without an owner are left without an epoch so that they
+/// never resolve. Returns the number of values migrated and whether values
+/// remain to be migrated.
+fn continue_epoch_migration(
+    deps: DepsMut,
+    limit: u32,
+) -> ContractResult<(usize, bool)> {
+    let mut migration = match read_epoch_migration(deps.storage)? {
+        Some(migration) => migration,
+        None => return Ok((0, false)),
+    };
+
+    let names = read_value_names(deps.storage, migration.last_name.as_deref(), limit)?;
+    for name in &names {
+        // Names pruned by the auction can no longer be queried
+        if let Ok(name_state) = query_auction_name_state(deps.as_ref(), name) {
+            if let Some(owner) = name_state.name_owner {
+                let epoch = OwnerEpoch {
+                    owner,
+                    transition_reference_time: name_state.transition_reference_time,
+                };
+                store_name_epoch(deps.storage, name, &epoch)?;
+            }
+        }
+        migration.last_name = Some(name.clone());
+    }
+
+    let remaining = names.len() == limit as usize;
+    if remaining {
+        store_epoch_migration(deps.storage, &migration)?;
+    } else {
+        remove_epoch_migration(deps.storage);
+    }
+
+    Ok((names.len(), remaining))
 }
@@ -6,10 +6,22 @@ use snafu::Snafu;
 pub enum ContractError {
     #[snafu(display("StdError: {}", source))]
     Std { source: StdError },
+    #[snafu(display("InvalidName"))]
+    InvalidName { backtrace: Option<snafu::Backtrace> },
     #[snafu(display("NameExpired"))]
     NameExpired { backtrace: Option<snafu::Backtrace> },
     #[snafu(display("Unauthorized"))]
     Unauthorized { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("InvalidRecordKey"))]
+    InvalidRecordKey { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("TooManyRecords"))]
+    TooManyRecords { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("InvalidRecordValue: {}: {}", key, reason))]
+    InvalidRecordValue { key: String, reason: String, backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("InvalidConfig"))]
+    InvalidConfig { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("MigrationInProgress"))]
+    MigrationInProgress { backtrace: Option<snafu::Backtrace> },
 }
 
 impl From<StdError> for ContractError {
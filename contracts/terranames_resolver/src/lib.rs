@@ -4,6 +4,3 @@ pub mod state;
 
 #[cfg(test)]
 mod tests;
-
-#[cfg(test)]
-mod mock_querier;
@@ -1,44 +1,231 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, StdResult, Storage};
-use cosmwasm_storage::{
-    bucket, bucket_read, singleton, singleton_read,
-};
+use cosmwasm_std::{Addr, Order, StdResult, Storage};
+use cw_storage_plus::{Bound, Item, Map};
 
-pub static CONFIG_KEY: &[u8] = b"config";
-pub static VALUE_PREFIX: &[u8] = b"value";
+use terranames::resolver::{Record, RecordKey, RecordValidation};
+use terranames::storage::migrate_singleton;
+use terranames::utils::Timestamp;
+
+const CONFIG_KEY: &str = "config";
+pub const CONFIG: Item<Config> = Item::new(CONFIG_KEY);
+pub const VALUES: Map<&str, Option<String>> = Map::new("value");
+/// Records other than the default record by name and key
+///
+/// The default record is stored in VALUES.
+pub const RECORDS: Map<(&str, &str), String> = Map::new("record");
+/// Ownership epoch that the records of a name were set in
+pub const EPOCHS: Map<&str, OwnerEpoch> = Map::new("epoch");
+/// Progress of stamping epochs on values stored before epochs were tracked
+pub const EPOCH_MIGRATION: Item<EpochMigration> = Item::new("epoch_migration");
+
+/// Maximum number of records of a name in addition to the default record
+pub const MAX_RECORDS: usize = 30;
+
+/// Move singletons stored by earlier versions
+///
+/// Returns true if the config was moved. Storage with singletons was written
+/// before ownership epochs were tracked.
+pub fn migrate_singletons(storage: &mut dyn Storage) -> StdResult<bool> {
+    migrate_singleton(storage, CONFIG_KEY, &CONFIG)
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     /// Auction contract
     pub auction_contract: Addr,
+    /// Records that are validated when set
+    #[serde(default)]
+    pub record_validations: Vec<RecordValidation>,
 }
 
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
-    singleton_read(storage, CONFIG_KEY).load()
+    CONFIG.load(storage)
 }
 
-#[must_use]
 pub fn store_config(
     storage: &mut dyn Storage,
     config: &Config,
 ) -> StdResult<()> {
-    singleton(storage, CONFIG_KEY).save(config)
+    CONFIG.save(storage, config)
+}
+
+/// Ownership epoch of a name
+///
+/// A new epoch begins when the name is won by or transferred to another
+/// owner. Records only resolve in the epoch they were set in. Values stored
+/// before epochs were tracked are stamped with the epoch of the owner at the
+/// migration. Records without a stored epoch never resolve once the
+/// migration is complete.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnerEpoch {
+    /// Owner of the name
+    pub owner: Addr,
+    /// Transition reference timestamp of the owner in the auction
+    pub transition_reference_time: Timestamp,
+}
+
+/// Read ownership epoch that the records of name were set in
+pub fn read_name_epoch(
+    storage: &dyn Storage,
+    name: &str,
+) -> StdResult<Option<OwnerEpoch>> {
+    EPOCHS.may_load(storage, name)
+}
+
+pub fn store_name_epoch(
+    storage: &mut dyn Storage,
+    name: &str,
+    epoch: &OwnerEpoch,
+) -> StdResult<()> {
+    EPOCHS.save(storage, name, epoch)
+}
+
+pub fn remove_name_epoch(storage: &mut dyn Storage, name: &str) {
+    EPOCHS.remove(storage, name);
+}
+
+/// Progress of stamping epochs on values stored before epochs were tracked
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct EpochMigration {
+    /// Last name that was stamped (None if no names were stamped yet)
+    pub last_name: Option<String>,
+}
+
+/// Start stamping epochs on the values stored before epochs were tracked
+///
+/// The values are stamped in batches since the owner of each name is queried
+/// from the auction.
+pub fn start_epoch_migration(storage: &mut dyn Storage) -> StdResult<()> {
+    EPOCH_MIGRATION.save(storage, &EpochMigration::default())
+}
+
+/// Whether values remain to be stamped after a migration
+pub fn is_epoch_migration_pending(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(EPOCH_MIGRATION.may_load(storage)?.is_some())
+}
+
+pub fn read_epoch_migration(
+    storage: &dyn Storage,
+) -> StdResult<Option<EpochMigration>> {
+    EPOCH_MIGRATION.may_load(storage)
+}
+
+pub fn store_epoch_migration(
+    storage: &mut dyn Storage,
+    migration: &EpochMigration,
+) -> StdResult<()> {
+    EPOCH_MIGRATION.save(storage, migration)
+}
+
+pub fn remove_epoch_migration(storage: &mut dyn Storage) {
+    EPOCH_MIGRATION.remove(storage);
+}
+
+/// Return at most limit names with a stored value after start_after
+pub fn read_value_names(
+    storage: &dyn Storage,
+    start_after: Option<&str>,
+    limit: u32,
+) -> StdResult<Vec<String>> {
+    let start = start_after.map(|name| Bound::exclusive(name.as_bytes()));
+    VALUES
+        .keys(storage, start, None, Order::Ascending)
+        .take(limit as usize)
+        .map(|key| Ok(String::from_utf8(key)?))
+        .collect()
 }
 
 pub fn read_name_value(
     storage: &dyn Storage,
     name: &str,
 ) -> StdResult<Option<String>> {
-    bucket_read(storage, VALUE_PREFIX).load(name.as_bytes())
+    VALUES.load(storage, name)
 }
 
-#[must_use]
 pub fn store_name_value(
     storage: &mut dyn Storage,
     name: &str,
     value: Option<String>,
 ) -> StdResult<()> {
-    bucket(storage, VALUE_PREFIX).save(name.as_bytes(), &value)
+    VALUES.save(storage, name, &value)
+}
+
+/// Remove value of name and return whether a value was stored
+pub fn remove_name_value(
+    storage: &mut dyn Storage,
+    name: &str,
+) -> StdResult<bool> {
+    let exists = VALUES.may_load(storage, name)?.is_some();
+    VALUES.remove(storage, name);
+    Ok(exists)
+}
+
+/// Read record of name (None if not set)
+pub fn read_name_record(
+    storage: &dyn Storage,
+    name: &str,
+    key: &RecordKey,
+) -> StdResult<Option<String>> {
+    match key {
+        RecordKey::Default {} => Ok(VALUES.may_load(storage, name)?.flatten()),
+        _ => RECORDS.may_load(storage, (name, &key.to_string())),
+    }
+}
+
+/// Store record of name or delete it if value is None
+pub fn store_name_record(
+    storage: &mut dyn Storage,
+    name: &str,
+    key: &RecordKey,
+    value: Option<String>,
+) -> StdResult<()> {
+    match (key, value) {
+        (RecordKey::Default {}, value) => store_name_value(storage, name, value),
+        (_, Some(value)) => RECORDS.save(storage, (name, &key.to_string()), &value),
+        (_, None) => {
+            RECORDS.remove(storage, (name, &key.to_string()));
+            Ok(())
+        },
+    }
+}
+
+/// Return keys of records of name other than the default record
+pub fn read_name_record_keys(
+    storage: &dyn Storage,
+    name: &str,
+) -> StdResult<Vec<RecordKey>> {
+    RECORDS.prefix(name)
+        .keys(storage, None, None, Order::Ascending)
+        .map(|key| String::from_utf8(key)?.parse())
+        .collect()
+}
+
+/// Return all records of name starting with the default record
+pub fn read_name_records(
+    storage: &dyn Storage,
+    name: &str,
+) -> StdResult<Vec<Record>> {
+    let default = VALUES.may_load(storage, name)?.flatten()
+        .map(|value| Record { key: RecordKey::Default {}, value });
+    let records = RECORDS.prefix(name)
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, value) = item?;
+            Ok(Record { key: String::from_utf8(key)?.parse()?, value })
+        });
+    default.map(Ok).into_iter().chain(records).collect()
+}
+
+/// Remove records of name other than the default record and return the keys
+pub fn remove_name_records(
+    storage: &mut dyn Storage,
+    name: &str,
+) -> StdResult<Vec<RecordKey>> {
+    let keys = read_name_record_keys(storage, name)?;
+    for key in &keys {
+        RECORDS.remove(storage, (name, &key.to_string()));
+    }
+    Ok(keys)
 }
@@ -1,23 +1,66 @@
-use cosmwasm_std::{from_binary, Addr, Uint128};
+use cosmwasm_std::{from_binary, Addr, Deps, Uint128};
 use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_storage::{bucket, bucket_read, singleton};
 
-use terranames::auction::NameStateResponse;
+use terranames::auction::{NameStateResponse, Role, RoleGrant};
+use terranames::events::{MigrateEvent, RecordEvent, ValueEvent};
 use terranames::resolver::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, ResolveNameResponse,
+    ConfigResponse, ExecuteMsg, InstantiateMsg, ListRecordsResponse,
+    MigrateMsg, QueryMsg, Record, RecordFormat, RecordKey, RecordValidation,
+    ResolveNameResponse, ResolveRecordResponse,
 };
+use terranames::testing::auction::{AuctionQuerier, MockNameState};
 use terranames::testing::helpers::EnvBuilder;
+use terranames::testing::mock_dependencies;
 use terranames::utils::Timestamp;
 
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
 use crate::errors::ContractError;
-use crate::mock_querier::mock_dependencies;
+use crate::state::{
+    read_config, read_name_record, read_name_value, store_name_record,
+    store_name_value, Config, MAX_RECORDS,
+};
 
 fn default_init() -> InstantiateMsg {
     InstantiateMsg {
         auction_contract: "auction".into(),
+        record_validations: vec![],
+    }
+}
+
+fn controlled_name_state() -> NameStateResponse {
+    NameStateResponse {
+        name_owner: Some(Addr::unchecked("owner")),
+        bid_owner: Some(Addr::unchecked("owner")),
+        controller: Some(Addr::unchecked("controller")),
+        roles: vec![RoleGrant {
+            role: Role::Controller,
+            address: Addr::unchecked("controller"),
+        }],
+        tld: None,
+
+        rate: Uint128::from(100u64),
+        begin_time: Timestamp::from_seconds(100_000),
+        begin_deposit: Uint128::from(1000u64),
+        current_deposit: Uint128::from(965u64),
+
+        counter_delay_end: Timestamp::from_seconds(110000),
+        transition_delay_end: Timestamp::from_seconds(130000),
+        bid_delay_end: Timestamp::from_seconds(2000000),
+        expire_time: Some(Timestamp::from_seconds(10_100_000)),
+        grace_period_end: Some(Timestamp::from_seconds(10_100_000)),
+        transition_reference_time: Timestamp::from_seconds(100_000),
     }
 }
 
+fn eth_address_key() -> RecordKey {
+    RecordKey::Address { chain: "eth".into() }
+}
+
+fn email_key() -> RecordKey {
+    RecordKey::Text { key: "email".into() }
+}
+
 #[test]
 fn proper_initialization() {
     let mut deps = mock_dependencies(&[]);
@@ -47,10 +90,15 @@ fn set_value_to_string() {
     let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
     assert_eq!(res.messages.len(), 0);
 
-    deps.querier.auction_querier.response = Some(NameStateResponse {
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", NameStateResponse {
         name_owner: Some(Addr::unchecked("owner")),
         bid_owner: Some(Addr::unchecked("owner")),
         controller: Some(Addr::unchecked("controller")),
+        roles: vec![RoleGrant {
+            role: Role::Controller,
+            address: Addr::unchecked("controller"),
+        }],
+        tld: None,
 
         rate: Uint128::from(100u64),
         begin_time: Timestamp::from_seconds(100_000),
@@ -61,7 +109,9 @@ fn set_value_to_string() {
         transition_delay_end: Timestamp::from_seconds(130000),
         bid_delay_end: Timestamp::from_seconds(2000000),
         expire_time: Some(Timestamp::from_seconds(10_100_000)),
-    });
+        grace_period_end: Some(Timestamp::from_seconds(10_100_000)),
+        transition_reference_time: Timestamp::from_seconds(100_000),
+    }));
 
     let env = mock_env().at_time(123456);
     let info = mock_info("controller", &[]);
@@ -71,6 +121,15 @@ fn set_value_to_string() {
     }).unwrap();
     assert_eq!(res.messages.len(), 0);
 
+    assert_eq!(res.events.len(), 1);
+    assert_eq!(ValueEvent::parse(&res.events[0]), Ok(ValueEvent {
+        name: "example".into(),
+        owner: Some(Addr::unchecked("owner")),
+        controller: Addr::unchecked("controller"),
+        value: Some("test_value".into()),
+        expire_time: Some(Timestamp::from_seconds(10_100_000)),
+    }));
+
     let env = mock_env();
     let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
         name: "example".to_string(),
@@ -91,10 +150,15 @@ fn set_value_to_none() {
     let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
     assert_eq!(res.messages.len(), 0);
 
-    deps.querier.auction_querier.response = Some(NameStateResponse {
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", NameStateResponse {
         bid_owner: Some(Addr::unchecked("owner")),
         name_owner: Some(Addr::unchecked("owner")),
         controller: Some(Addr::unchecked("controller")),
+        roles: vec![RoleGrant {
+            role: Role::Controller,
+            address: Addr::unchecked("controller"),
+        }],
+        tld: None,
 
         rate: Uint128::from(100u64),
         begin_time: Timestamp::from_seconds(100_000),
@@ -105,7 +169,9 @@ fn set_value_to_none() {
         transition_delay_end: Timestamp::from_seconds(130000),
         bid_delay_end: Timestamp::from_seconds(2000000),
         expire_time: Some(Timestamp::from_seconds(10_100_000)),
-    });
+        grace_period_end: Some(Timestamp::from_seconds(10_100_000)),
+        transition_reference_time: Timestamp::from_seconds(100_000),
+    }));
 
     let env = mock_env().at_time(123456);
     let info = mock_info("controller", &[]);
@@ -135,10 +201,15 @@ fn set_value_for_zero_bid() {
     let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
     assert_eq!(res.messages.len(), 0);
 
-    deps.querier.auction_querier.response = Some(NameStateResponse {
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", NameStateResponse {
         bid_owner: Some(Addr::unchecked("owner")),
         name_owner: Some(Addr::unchecked("owner")),
         controller: Some(Addr::unchecked("controller")),
+        roles: vec![RoleGrant {
+            role: Role::Controller,
+            address: Addr::unchecked("controller"),
+        }],
+        tld: None,
 
         rate: Uint128::zero(),
         begin_time: Timestamp::from_seconds(100_000),
@@ -149,7 +220,9 @@ fn set_value_for_zero_bid() {
         transition_delay_end: Timestamp::from_seconds(130000),
         bid_delay_end: Timestamp::from_seconds(100_000),
         expire_time: None,
-    });
+        grace_period_end: None,
+        transition_reference_time: Timestamp::from_seconds(100_000),
+    }));
 
     let env = mock_env().at_time(123456);
     let info = mock_info("controller", &[]);
@@ -179,10 +252,15 @@ fn set_value_as_other_fails() {
     let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
     assert_eq!(res.messages.len(), 0);
 
-    deps.querier.auction_querier.response = Some(NameStateResponse {
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", NameStateResponse {
         bid_owner: Some(Addr::unchecked("owner")),
         name_owner: Some(Addr::unchecked("owner")),
         controller: Some(Addr::unchecked("controller")),
+        roles: vec![RoleGrant {
+            role: Role::Controller,
+            address: Addr::unchecked("controller"),
+        }],
+        tld: None,
 
         rate: Uint128::zero(),
         begin_time: Timestamp::from_seconds(100_000),
@@ -193,7 +271,9 @@ fn set_value_as_other_fails() {
         transition_delay_end: Timestamp::from_seconds(130000),
         bid_delay_end: Timestamp::from_seconds(100_000),
         expire_time: None,
-    });
+        grace_period_end: None,
+        transition_reference_time: Timestamp::from_seconds(100_000),
+    }));
 
     // Fails when called as other sender
     let env = mock_env().at_time(123456);
@@ -225,10 +305,12 @@ fn set_value_when_controller_is_none_fails() {
     let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
     assert_eq!(res.messages.len(), 0);
 
-    deps.querier.auction_querier.response = Some(NameStateResponse {
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", NameStateResponse {
         bid_owner: Some(Addr::unchecked("owner")),
         name_owner: Some(Addr::unchecked("owner")),
         controller: None,
+        roles: vec![],
+        tld: None,
 
         rate: Uint128::zero(),
         begin_time: Timestamp::from_seconds(100_000),
@@ -239,7 +321,9 @@ fn set_value_when_controller_is_none_fails() {
         transition_delay_end: Timestamp::from_seconds(130_000),
         bid_delay_end: Timestamp::from_seconds(100_000),
         expire_time: None,
-    });
+        grace_period_end: None,
+        transition_reference_time: Timestamp::from_seconds(100_000),
+    }));
 
     // Fails when called as any sender
     let env = mock_env().at_time(123456);
@@ -262,10 +346,15 @@ fn set_value_when_expired_fails() {
     let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
     assert_eq!(res.messages.len(), 0);
 
-    deps.querier.auction_querier.response = Some(NameStateResponse {
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", NameStateResponse {
         bid_owner: Some(Addr::unchecked("owner")),
         name_owner: Some(Addr::unchecked("owner")),
         controller: Some(Addr::unchecked("controller")),
+        roles: vec![RoleGrant {
+            role: Role::Controller,
+            address: Addr::unchecked("controller"),
+        }],
+        tld: None,
 
         rate: Uint128::from(100u64),
         begin_time: Timestamp::from_seconds(100_000),
@@ -276,7 +365,9 @@ fn set_value_when_expired_fails() {
         transition_delay_end: Timestamp::from_seconds(130_000),
         bid_delay_end: Timestamp::from_seconds(2000000),
         expire_time: Some(Timestamp::from_seconds(10_100_000)),
-    });
+        grace_period_end: Some(Timestamp::from_seconds(10_100_000)),
+        transition_reference_time: Timestamp::from_seconds(100_000),
+    }));
 
     // Fails when called after expiration
     let env = mock_env().at_time(10_100_000);
@@ -287,3 +378,1047 @@ fn set_value_when_expired_fails() {
     });
     assert!(matches!(res, Err(ContractError::NameExpired { .. })));
 }
+
+#[test]
+fn resolve_in_grace_period_is_lapsed() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", NameStateResponse {
+        bid_owner: Some(Addr::unchecked("owner")),
+        name_owner: Some(Addr::unchecked("owner")),
+        controller: Some(Addr::unchecked("controller")),
+        roles: vec![RoleGrant {
+            role: Role::Controller,
+            address: Addr::unchecked("controller"),
+        }],
+        tld: None,
+
+        rate: Uint128::from(100u64),
+        begin_time: Timestamp::from_seconds(100_000),
+        begin_deposit: Uint128::from(1000u64),
+        current_deposit: Uint128::from(965u64),
+
+        counter_delay_end: Timestamp::from_seconds(110_000),
+        transition_delay_end: Timestamp::from_seconds(130_000),
+        bid_delay_end: Timestamp::from_seconds(2000000),
+        expire_time: Some(Timestamp::from_seconds(10_100_000)),
+        grace_period_end: Some(Timestamp::from_seconds(11_309_600)),
+        transition_reference_time: Timestamp::from_seconds(100_000),
+    }));
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+        name: "example".to_string(),
+        value: Some("test_value".into()),
+    }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    // Value resolves before expiration
+    let env = mock_env().at_time(10_099_999);
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
+        name: "example".to_string(),
+    }).unwrap();
+    let resolved: ResolveNameResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, Some("test_value".into()));
+    assert!(!resolved.lapsed);
+
+    // Value still resolves after expiration but is marked as lapsed
+    let env = mock_env().at_time(10_100_000);
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
+        name: "example".to_string(),
+    }).unwrap();
+    let resolved: ResolveNameResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, Some("test_value".into()));
+    assert_eq!(resolved.owner, Addr::unchecked("owner"));
+    assert!(resolved.lapsed);
+}
+
+#[test]
+fn set_value_for_tld_name() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let response = NameStateResponse {
+        name_owner: Some(Addr::unchecked("owner")),
+        bid_owner: Some(Addr::unchecked("owner")),
+        controller: Some(Addr::unchecked("controller")),
+        roles: vec![RoleGrant {
+            role: Role::Controller,
+            address: Addr::unchecked("controller"),
+        }],
+        tld: None,
+
+        rate: Uint128::from(100u64),
+        begin_time: Timestamp::from_seconds(100_000),
+        begin_deposit: Uint128::from(1000u64),
+        current_deposit: Uint128::from(965u64),
+
+        counter_delay_end: Timestamp::from_seconds(110000),
+        transition_delay_end: Timestamp::from_seconds(130000),
+        bid_delay_end: Timestamp::from_seconds(2000000),
+        expire_time: Some(Timestamp::from_seconds(10_100_000)),
+        grace_period_end: Some(Timestamp::from_seconds(10_100_000)),
+        transition_reference_time: Timestamp::from_seconds(100_000),
+    };
+    deps.querier.set_contract("auction", AuctionQuerier::new()
+        .with_name("example", response.clone())
+        .with_name("example.ust", NameStateResponse {
+            tld: Some("ust".into()),
+            ..response.clone()
+        })
+        // Stored before TLDs existed
+        .with_name("old.example.ust", response)
+    );
+
+    for (name, value) in [("example", "default_value"), ("example.ust", "ust_value")] {
+        let env = mock_env().at_time(123456);
+        let info = mock_info("controller", &[]);
+        execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+            name: name.to_string(),
+            value: Some(value.to_string()),
+        }).unwrap();
+    }
+
+    let env = mock_env();
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
+        name: "example".to_string(),
+    }).unwrap();
+    let resolved: ResolveNameResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, Some("default_value".into()));
+    assert_eq!(resolved.tld, None);
+
+    let env = mock_env();
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
+        name: "example.ust".to_string(),
+    }).unwrap();
+    let resolved: ResolveNameResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, Some("ust_value".into()));
+    assert_eq!(resolved.tld, Some("ust".into()));
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+        name: "sub.example.ust".to_string(),
+        value: None,
+    });
+    assert!(matches!(res, Err(ContractError::InvalidName { .. })));
+
+    let env = mock_env();
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
+        name: "example.".to_string(),
+    });
+    assert!(matches!(res, Err(ContractError::InvalidName { .. })));
+
+    // Names held by the auction from before TLDs existed are in the default
+    // namespace
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+        name: "old.example.ust".to_string(),
+        value: Some("old_value".to_string()),
+    }).unwrap();
+
+    let env = mock_env();
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
+        name: "old.example.ust".to_string(),
+    }).unwrap();
+    let resolved: ResolveNameResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, Some("old_value".into()));
+    assert_eq!(resolved.tld, None);
+}
+
+#[test]
+fn set_values_for_separate_names() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let time = Timestamp::from_seconds(2_000_000);
+    deps.querier.set_contract("auction", AuctionQuerier::new()
+        .with_name_state(
+            "first",
+            &MockNameState::new("owner_1", 100, 1_000_000, 5_000)
+                .with_controller("controller_1"),
+            time,
+        )
+        .with_name_state(
+            "second",
+            &MockNameState::new("owner_2", 100, 1_000_000, 5_000)
+                .with_controller("controller_2"),
+            time,
+        )
+    );
+
+    // Controllers can only set the value of their own name
+    let env = mock_env().at_time(time.value());
+    let info = mock_info("controller_1", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+        name: "second".to_string(),
+        value: Some("value_1".into()),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    for (name, controller) in [("first", "controller_1"), ("second", "controller_2")] {
+        let env = mock_env().at_time(time.value());
+        let info = mock_info(controller, &[]);
+        execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+            name: name.to_string(),
+            value: Some(format!("{}_value", name)),
+        }).unwrap();
+    }
+
+    for (name, owner) in [("first", "owner_1"), ("second", "owner_2")] {
+        let env = mock_env().at_time(time.value());
+        let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
+            name: name.to_string(),
+        }).unwrap();
+        let resolved: ResolveNameResponse = from_binary(&res).unwrap();
+        assert_eq!(resolved.value, Some(format!("{}_value", name)));
+        assert_eq!(resolved.owner.as_str(), owner);
+    }
+
+    // Names unknown to the auction cannot be set or resolved
+    let env = mock_env().at_time(time.value());
+    let info = mock_info("controller_1", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+        name: "third".to_string(),
+        value: Some("value".into()),
+    });
+    assert!(matches!(res, Err(ContractError::Std { .. })));
+
+    let env = mock_env().at_time(time.value());
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
+        name: "third".to_string(),
+    });
+    assert!(matches!(res, Err(ContractError::Std { .. })));
+}
+
+#[test]
+fn drop_values_from_auction() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    store_name_value(&mut deps.storage, "example", Some("test_value".into())).unwrap();
+
+    let env = mock_env();
+    let info = mock_info("other", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::DropNameValues {
+        names: vec!["example".into()],
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    let env = mock_env();
+    let info = mock_info("auction", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::DropNameValues {
+        names: vec!["example".into(), "missing".into()],
+    }).unwrap();
+    assert_eq!(res.events.len(), 1);
+    assert_eq!(ValueEvent::parse(&res.events[0]), Ok(ValueEvent {
+        name: "example".into(),
+        owner: None,
+        controller: Addr::unchecked("auction"),
+        value: None,
+        expire_time: None,
+    }));
+
+    assert!(read_name_value(&deps.storage, "example").is_err());
+}
+
+#[test]
+fn value_storage_round_trip() {
+    let mut deps = mock_dependencies(&[]);
+
+    // Config stored as a singleton by an earlier version is moved by the
+    // migration
+    let config = Config {
+        auction_contract: Addr::unchecked("auction"),
+        record_validations: vec![],
+    };
+    singleton(&mut deps.storage, b"config").save(&config).unwrap();
+    assert!(read_config(&deps.storage).is_err());
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg { record_validations: None, epoch_limit: None }).unwrap();
+    assert_eq!(read_config(&deps.storage).unwrap(), config);
+
+    let env = mock_env();
+    let res = query(deps.as_ref(), env, QueryMsg::Config {}).unwrap();
+    let config_response: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config_response.auction_contract.as_str(), "auction");
+
+    // Values stored in the map can be read as a bucket and back
+    store_name_value(&mut deps.storage, "example", Some("value".into())).unwrap();
+    let bucket_value: Option<String> = bucket_read(&deps.storage, b"value")
+        .load(b"example")
+        .unwrap();
+    assert_eq!(bucket_value, Some("value".into()));
+
+    bucket(&mut deps.storage, b"value").save(b"other", &None::<String>).unwrap();
+    bucket(&mut deps.storage, b"value").save(b"third", &Some("abc".to_string())).unwrap();
+    assert_eq!(read_name_value(&deps.storage, "other").unwrap(), None);
+    assert_eq!(read_name_value(&deps.storage, "third").unwrap(), Some("abc".into()));
+}
+
+#[test]
+fn set_record_and_resolve() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", controlled_name_state()));
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "example".to_string(),
+        key: eth_address_key(),
+        value: "0x0123".to_string(),
+    }).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    assert_eq!(res.events.len(), 1);
+    assert_eq!(RecordEvent::parse(&res.events[0]), Ok(RecordEvent {
+        name: "example".into(),
+        owner: Some(Addr::unchecked("owner")),
+        controller: Addr::unchecked("controller"),
+        key: eth_address_key(),
+        value: Some("0x0123".into()),
+        expire_time: Some(Timestamp::from_seconds(10_100_000)),
+    }));
+
+    let env = mock_env().at_time(123456);
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveRecord {
+        name: "example".to_string(),
+        key: eth_address_key(),
+    }).unwrap();
+    let resolved: ResolveRecordResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, Some("0x0123".into()));
+    assert_eq!(resolved.owner, Addr::unchecked("owner"));
+    assert_eq!(resolved.expire_time, Some(Timestamp::from_seconds(10_100_000)));
+    assert!(!resolved.lapsed);
+
+    // Other records and the default record are not affected
+    let env = mock_env();
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveRecord {
+        name: "example".to_string(),
+        key: email_key(),
+    }).unwrap();
+    let resolved: ResolveRecordResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, None);
+
+    let env = mock_env();
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveRecord {
+        name: "example".to_string(),
+        key: RecordKey::Default {},
+    }).unwrap();
+    let resolved: ResolveRecordResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, None);
+}
+
+#[test]
+fn set_name_value_is_default_record() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", controlled_name_state()));
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+        name: "example".to_string(),
+        value: Some("test_value".to_string()),
+    }).unwrap();
+
+    let env = mock_env();
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveRecord {
+        name: "example".to_string(),
+        key: RecordKey::Default {},
+    }).unwrap();
+    let resolved: ResolveRecordResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, Some("test_value".into()));
+
+    // Setting the default record emits a value event
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "example".to_string(),
+        key: RecordKey::Default {},
+        value: "other_value".to_string(),
+    }).unwrap();
+    assert_eq!(res.events.len(), 1);
+    assert_eq!(ValueEvent::parse(&res.events[0]), Ok(ValueEvent {
+        name: "example".into(),
+        owner: Some(Addr::unchecked("owner")),
+        controller: Addr::unchecked("controller"),
+        value: Some("other_value".into()),
+        expire_time: Some(Timestamp::from_seconds(10_100_000)),
+    }));
+
+    assert_eq!(read_name_value(&deps.storage, "example").unwrap(), Some("other_value".into()));
+}
+
+#[test]
+fn set_records_and_list() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", controlled_name_state()));
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecords {
+        name: "example".to_string(),
+        records: vec![
+            Record { key: email_key(), value: "user@example.com".into() },
+            Record { key: RecordKey::ContentHash {}, value: "e301".into() },
+            Record { key: RecordKey::Default {}, value: "default_value".into() },
+            Record { key: eth_address_key(), value: "0x0123".into() },
+        ],
+    }).unwrap();
+    assert_eq!(res.events.len(), 4);
+
+    let env = mock_env().at_time(123456);
+    let res = query(deps.as_ref(), env, QueryMsg::ListRecords {
+        name: "example".to_string(),
+    }).unwrap();
+    let list: ListRecordsResponse = from_binary(&res).unwrap();
+    assert_eq!(list.records, vec![
+        Record { key: RecordKey::Default {}, value: "default_value".into() },
+        Record { key: eth_address_key(), value: "0x0123".into() },
+        Record { key: RecordKey::ContentHash {}, value: "e301".into() },
+        Record { key: email_key(), value: "user@example.com".into() },
+    ]);
+    assert_eq!(list.owner, Addr::unchecked("owner"));
+    assert_eq!(list.expire_time, Some(Timestamp::from_seconds(10_100_000)));
+    assert!(!list.lapsed);
+    assert_eq!(list.tld, None);
+
+    // Delete a record
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::DeleteRecord {
+        name: "example".to_string(),
+        key: email_key(),
+    }).unwrap();
+    assert_eq!(res.events.len(), 1);
+    assert_eq!(RecordEvent::parse(&res.events[0]), Ok(RecordEvent {
+        name: "example".into(),
+        owner: Some(Addr::unchecked("owner")),
+        controller: Addr::unchecked("controller"),
+        key: email_key(),
+        value: None,
+        expire_time: Some(Timestamp::from_seconds(10_100_000)),
+    }));
+
+    let env = mock_env();
+    let res = query(deps.as_ref(), env, QueryMsg::ListRecords {
+        name: "example".to_string(),
+    }).unwrap();
+    let list: ListRecordsResponse = from_binary(&res).unwrap();
+    assert_eq!(list.records.len(), 3);
+    assert!(!list.records.iter().any(|record| record.key == email_key()));
+}
+
+#[test]
+fn set_record_as_other_fails() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", controlled_name_state()));
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("owner", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "example".to_string(),
+        key: email_key(),
+        value: "user@example.com".to_string(),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("other", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::DeleteRecord {
+        name: "example".to_string(),
+        key: email_key(),
+    });
+    assert!(matches!(res, Err(ContractError::Unauthorized { .. })));
+}
+
+#[test]
+fn set_record_with_invalid_key_fails() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", controlled_name_state()));
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "example".to_string(),
+        key: RecordKey::Text { key: "".into() },
+        value: "value".to_string(),
+    });
+    assert!(matches!(res, Err(ContractError::InvalidRecordKey { .. })));
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecords {
+        name: "example".to_string(),
+        records: vec![
+            Record { key: email_key(), value: "user@example.com".into() },
+            Record { key: RecordKey::Address { chain: "x".repeat(65) }, value: "value".into() },
+        ],
+    });
+    assert!(matches!(res, Err(ContractError::InvalidRecordKey { .. })));
+}
+
+#[test]
+fn set_too_many_records_fails() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", controlled_name_state()));
+
+    let records: Vec<_> = (0..MAX_RECORDS)
+        .map(|i| Record {
+            key: RecordKey::Text { key: format!("key{}", i) },
+            value: "value".into(),
+        })
+        .collect();
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetRecords {
+        name: "example".to_string(),
+        records,
+    }).unwrap();
+
+    // The default record does not count towards the limit
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+        name: "example".to_string(),
+        value: Some("value".to_string()),
+    }).unwrap();
+
+    // Replacing an existing record is allowed
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "example".to_string(),
+        key: RecordKey::Text { key: "key0".into() },
+        value: "other_value".to_string(),
+    }).unwrap();
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "example".to_string(),
+        key: email_key(),
+        value: "user@example.com".to_string(),
+    });
+    assert!(matches!(res, Err(ContractError::TooManyRecords { .. })));
+}
+
+#[test]
+fn drop_records_from_auction() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    store_name_value(&mut deps.storage, "example", Some("test_value".into())).unwrap();
+    store_name_record(&mut deps.storage, "example", &email_key(), Some("user@example.com".into())).unwrap();
+    store_name_record(&mut deps.storage, "other", &email_key(), Some("other@example.com".into())).unwrap();
+
+    let env = mock_env();
+    let info = mock_info("auction", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::DropNameValues {
+        names: vec!["example".into()],
+    }).unwrap();
+    assert_eq!(res.events.len(), 2);
+    assert_eq!(RecordEvent::parse(&res.events[1]), Ok(RecordEvent {
+        name: "example".into(),
+        owner: None,
+        controller: Addr::unchecked("auction"),
+        key: email_key(),
+        value: None,
+        expire_time: None,
+    }));
+
+    assert_eq!(read_name_record(&deps.storage, "example", &email_key()).unwrap(), None);
+    assert_eq!(read_name_record(&deps.storage, "example", &RecordKey::Default {}).unwrap(), None);
+    assert_eq!(read_name_record(&deps.storage, "other", &email_key()).unwrap(), Some("other@example.com".into()));
+}
+
+#[test]
+fn records_from_previous_epoch_resolve_as_empty() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    let time = Timestamp::from_seconds(2_000_000);
+    let first_owner = MockNameState::new("owner_1", 100, 1_000_000, 5_000)
+        .with_controller("controller_1");
+    deps.querier.set_contract("auction", AuctionQuerier::new()
+        .with_name_state("example", &first_owner, time));
+
+    let env = mock_env().at_time(time.value());
+    let info = mock_info("controller_1", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetRecords {
+        name: "example".to_string(),
+        records: vec![
+            Record { key: RecordKey::Default {}, value: "value_1".into() },
+            Record { key: email_key(), value: "owner_1@example.com".into() },
+        ],
+    }).unwrap();
+
+    let env = mock_env().at_time(time.value());
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
+        name: "example".to_string(),
+    }).unwrap();
+    let resolved: ResolveNameResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, Some("value_1".into()));
+
+    // Name is won by another owner
+    let second_owner = MockNameState::new("owner_2", 100, 1_000_000, 5_000)
+        .with_controller("controller_2");
+    deps.querier.set_contract("auction", AuctionQuerier::new()
+        .with_name_state("example", &second_owner, time));
+
+    let env = mock_env().at_time(time.value());
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveName {
+        name: "example".to_string(),
+    }).unwrap();
+    let resolved: ResolveNameResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, None);
+    assert_eq!(resolved.owner, Addr::unchecked("owner_2"));
+
+    let env = mock_env().at_time(time.value());
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveRecord {
+        name: "example".to_string(),
+        key: email_key(),
+    }).unwrap();
+    let resolved: ResolveRecordResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, None);
+
+    let env = mock_env().at_time(time.value());
+    let res = query(deps.as_ref(), env, QueryMsg::ListRecords {
+        name: "example".to_string(),
+    }).unwrap();
+    let list: ListRecordsResponse = from_binary(&res).unwrap();
+    assert!(list.records.is_empty());
+
+    // Records of the previous epoch are removed when the new controller
+    // sets a record
+    let env = mock_env().at_time(time.value());
+    let info = mock_info("controller_2", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "example".to_string(),
+        key: eth_address_key(),
+        value: "0x0123".to_string(),
+    }).unwrap();
+    assert_eq!(res.events.len(), 3);
+    assert_eq!(ValueEvent::parse(&res.events[0]), Ok(ValueEvent {
+        name: "example".into(),
+        owner: None,
+        controller: Addr::unchecked("controller_2"),
+        value: None,
+        expire_time: None,
+    }));
+    assert_eq!(RecordEvent::parse(&res.events[1]), Ok(RecordEvent {
+        name: "example".into(),
+        owner: None,
+        controller: Addr::unchecked("controller_2"),
+        key: email_key(),
+        value: None,
+        expire_time: None,
+    }));
+
+    let env = mock_env().at_time(time.value());
+    let res = query(deps.as_ref(), env, QueryMsg::ListRecords {
+        name: "example".to_string(),
+    }).unwrap();
+    let list: ListRecordsResponse = from_binary(&res).unwrap();
+    assert_eq!(list.records, vec![
+        Record { key: eth_address_key(), value: "0x0123".into() },
+    ]);
+    assert_eq!(read_name_record(&deps.storage, "example", &email_key()).unwrap(), None);
+
+    // Name is won again by the same owner after expiration
+    let renewed_owner = MockNameState {
+        transition_reference_time: Timestamp::from_seconds(1_500_000),
+        ..second_owner
+    };
+    deps.querier.set_contract("auction", AuctionQuerier::new()
+        .with_name_state("example", &renewed_owner, time));
+
+    let env = mock_env().at_time(time.value());
+    let res = query(deps.as_ref(), env, QueryMsg::ResolveRecord {
+        name: "example".to_string(),
+        key: eth_address_key(),
+    }).unwrap();
+    let resolved: ResolveRecordResponse = from_binary(&res).unwrap();
+    assert_eq!(resolved.value, None);
+}
+
+#[test]
+fn values_stored_before_epochs_resolve_after_migrate() {
+    let mut deps = mock_dependencies(&[]);
+
+    // Config and values stored by a version without ownership epochs
+    let config = Config {
+        auction_contract: Addr::unchecked("auction"),
+        record_validations: vec![],
+    };
+    singleton(&mut deps.storage, b"config").save(&config).unwrap();
+    for name in &["example", "other", "pruned"] {
+        bucket(&mut deps.storage, b"value").save(name.as_bytes(), &Some("legacy".to_string())).unwrap();
+    }
+
+    let time = Timestamp::from_seconds(2_000_000);
+    let other_owner = MockNameState::new("owner_1", 100, 1_000_000, 5_000)
+        .with_controller("controller_1");
+    deps.querier.set_contract("auction", AuctionQuerier::new()
+        .with_name("example", controlled_name_state())
+        .with_name_state("other", &other_owner, time));
+
+    // Values are stamped with the epoch of the current owner in batches
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        record_validations: None,
+        epoch_limit: Some(2),
+    }).unwrap();
+    assert_eq!(MigrateEvent::parse(&res.events[0]), Ok(MigrateEvent {
+        migrated: 2,
+        complete: false,
+    }));
+
+    // Records cannot be set until all values are stamped
+    let env = mock_env().at_time(time.value());
+    let info = mock_info("controller_1", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "other".to_string(),
+        key: email_key(),
+        value: "owner_1@example.com".to_string(),
+    });
+    assert!(matches!(res, Err(ContractError::MigrationInProgress { .. })));
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        record_validations: None,
+        epoch_limit: Some(2),
+    }).unwrap();
+    assert_eq!(MigrateEvent::parse(&res.events[0]), Ok(MigrateEvent {
+        migrated: 1,
+        complete: true,
+    }));
+
+    let resolve_value = |deps: Deps, name: &str| {
+        let env = mock_env().at_time(time.value());
+        let res = query(deps, env, QueryMsg::ResolveName {
+            name: name.to_string(),
+        }).unwrap();
+        from_binary::<ResolveNameResponse>(&res).unwrap().value
+    };
+    assert_eq!(resolve_value(deps.as_ref(), "example"), Some("legacy".into()));
+    assert_eq!(resolve_value(deps.as_ref(), "other"), Some("legacy".into()));
+
+    // Setting a record keeps the stamped value
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "example".to_string(),
+        key: email_key(),
+        value: "owner@example.com".to_string(),
+    }).unwrap();
+    assert_eq!(res.events.len(), 1);
+
+    let env = mock_env().at_time(123456);
+    let res = query(deps.as_ref(), env, QueryMsg::ListRecords {
+        name: "example".to_string(),
+    }).unwrap();
+    let list: ListRecordsResponse = from_binary(&res).unwrap();
+    assert_eq!(list.records, vec![
+        Record { key: RecordKey::Default {}, value: "legacy".into() },
+        Record { key: email_key(), value: "owner@example.com".into() },
+    ]);
+
+    // Legacy values do not resolve for a new owner, even if the name was
+    // transferred without a new transition reference time
+    let transferred = MockNameState::new("owner_2", 100, 1_000_000, 5_000)
+        .with_controller("controller_2");
+    let pruned_owner = MockNameState::new("owner_3", 100, 1_000_000, 5_000)
+        .with_controller("controller_3");
+    deps.querier.set_contract("auction", AuctionQuerier::new()
+        .with_name("example", controlled_name_state())
+        .with_name_state("other", &transferred, time)
+        .with_name_state("pruned", &pruned_owner, time));
+
+    assert_eq!(resolve_value(deps.as_ref(), "other"), None);
+    assert_eq!(resolve_value(deps.as_ref(), "pruned"), None);
+
+    let env = mock_env().at_time(time.value());
+    let info = mock_info("controller_2", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "other".to_string(),
+        key: email_key(),
+        value: "owner_2@example.com".to_string(),
+    }).unwrap();
+    assert_eq!(res.events.len(), 2);
+    assert_eq!(ValueEvent::parse(&res.events[0]).unwrap().value, None);
+    assert_eq!(resolve_value(deps.as_ref(), "other"), None);
+}
+
+fn mock_record_validations() -> Vec<RecordValidation> {
+    vec![
+        RecordValidation {
+            key: RecordKey::Default {},
+            format: RecordFormat::NativeAddress {},
+        },
+        RecordValidation {
+            key: eth_address_key(),
+            format: RecordFormat::EvmAddress {},
+        },
+        RecordValidation {
+            key: RecordKey::Address { chain: "cosmos".into() },
+            format: RecordFormat::Bech32Address { prefix: "cosmos".into() },
+        },
+        RecordValidation {
+            key: RecordKey::Text { key: "url".into() },
+            format: RecordFormat::Url {},
+        },
+        RecordValidation {
+            key: RecordKey::ContentHash {},
+            format: RecordFormat::ContentHash {},
+        },
+    ]
+}
+
+#[test]
+fn set_records_with_validation() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        record_validations: mock_record_validations(),
+        ..default_init()
+    };
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    deps.querier.set_contract("auction", AuctionQuerier::new().with_name("example", controlled_name_state()));
+
+    // Default record must be a native address
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+        name: "example".to_string(),
+        value: Some("x".into()),
+    });
+    match res {
+        Err(ContractError::InvalidRecordValue { key, .. }) => assert_eq!(key, "default"),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+        name: "example".to_string(),
+        value: Some("terra1x46rqay4d3cssq8gxxvqz8xt6nwlz4td20k38v".into()),
+    }).unwrap();
+
+    // Values can always be cleared
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetNameValue {
+        name: "example".to_string(),
+        value: None,
+    }).unwrap();
+
+    // EVM address with invalid checksum
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecord {
+        name: "example".to_string(),
+        key: eth_address_key(),
+        value: "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD".into(),
+    });
+    match res {
+        Err(err @ ContractError::InvalidRecordValue { .. }) => {
+            assert_eq!(
+                err.to_string(),
+                "InvalidRecordValue: address:eth: Invalid EVM address or EIP-55 checksum",
+            );
+        },
+        res => panic!("Unexpected result: {:?}", res),
+    }
+
+    // A single invalid record fails all of the records
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::SetRecords {
+        name: "example".to_string(),
+        records: vec![
+            Record { key: eth_address_key(), value: "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".into() },
+            Record { key: RecordKey::Text { key: "url".into() }, value: "example.com".into() },
+        ],
+    });
+    assert!(matches!(res, Err(ContractError::InvalidRecordValue { .. })));
+    assert_eq!(read_name_record(&deps.storage, "example", &eth_address_key()).unwrap(), None);
+
+    let env = mock_env().at_time(123456);
+    let info = mock_info("controller", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::SetRecords {
+        name: "example".to_string(),
+        records: vec![
+            Record { key: eth_address_key(), value: "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".into() },
+            Record {
+                key: RecordKey::Address { chain: "cosmos".into() },
+                value: "cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu".into(),
+            },
+            Record { key: RecordKey::Text { key: "url".into() }, value: "https://example.com".into() },
+            Record {
+                key: RecordKey::ContentHash {},
+                value: "0xe3010170122029f2d17be6139079dc48696d1f582a8530eb9805b561eda517e22a892c7e3f1f".into(),
+            },
+            // Records without validation are not checked
+            Record { key: email_key(), value: "not an email".into() },
+        ],
+    }).unwrap();
+
+    let env = mock_env().at_time(123456);
+    let res = query(deps.as_ref(), env, QueryMsg::ListRecords {
+        name: "example".to_string(),
+    }).unwrap();
+    let list: ListRecordsResponse = from_binary(&res).unwrap();
+    assert_eq!(list.records.len(), 5);
+}
+
+#[test]
+fn instantiate_with_invalid_record_validations() {
+    let mut deps = mock_dependencies(&[]);
+
+    // Duplicate key
+    let mut record_validations = mock_record_validations();
+    record_validations.push(RecordValidation {
+        key: RecordKey::Default {},
+        format: RecordFormat::Url {},
+    });
+    let msg = InstantiateMsg {
+        record_validations,
+        ..default_init()
+    };
+    let res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+    assert!(matches!(res, Err(ContractError::InvalidConfig { .. })));
+
+    // Empty bech32 prefix
+    let msg = InstantiateMsg {
+        record_validations: vec![RecordValidation {
+            key: RecordKey::Address { chain: "cosmos".into() },
+            format: RecordFormat::Bech32Address { prefix: "".into() },
+        }],
+        ..default_init()
+    };
+    let res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+    assert!(matches!(res, Err(ContractError::InvalidConfig { .. })));
+
+    // Invalid key
+    let msg = InstantiateMsg {
+        record_validations: vec![RecordValidation {
+            key: RecordKey::Text { key: "".into() },
+            format: RecordFormat::Url {},
+        }],
+        ..default_init()
+    };
+    let res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+    assert!(matches!(res, Err(ContractError::InvalidConfig { .. })));
+}
+
+#[test]
+fn migrate_record_validations() {
+    let mut deps = mock_dependencies(&[]);
+
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), default_init()).unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        record_validations: Some(vec![
+            RecordValidation { key: email_key(), format: RecordFormat::Url {} },
+            RecordValidation { key: email_key(), format: RecordFormat::Url {} },
+        ]),
+        epoch_limit: None,
+    });
+    assert!(matches!(res, Err(ContractError::InvalidConfig { .. })));
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        record_validations: Some(mock_record_validations()),
+        epoch_limit: None,
+    }).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.record_validations, mock_record_validations());
+
+    // Validations are kept if not set
+    migrate(deps.as_mut(), mock_env(), MigrateMsg { record_validations: None, epoch_limit: None }).unwrap();
+    assert_eq!(read_config(&deps.storage).unwrap().record_validations, mock_record_validations());
+}
@@ -5,6 +5,7 @@ use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use terranames::root_collector::{
     ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, StateResponse,
+    StatsResponse,
 };
 
 fn main() {
@@ -19,4 +20,5 @@ fn main() {
 
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(StateResponse), &out_dir);
+    export_schema(&schema_for!(StatsResponse), &out_dir);
 }
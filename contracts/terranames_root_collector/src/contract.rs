@@ -1,26 +1,36 @@
 use cosmwasm_std::{
     entry_point, from_binary, to_binary, Addr, BankMsg, Coin, CosmosMsg,
-    Decimal, Deps, DepsMut, Env, MessageInfo, QuerierWrapper, QueryResponse,
-    Response, StdResult, Uint128, WasmMsg,
+    Decimal, Deps, DepsMut, Env, Fraction, MessageInfo, QuerierWrapper,
+    QueryResponse, Response, StdResult, Uint128, WasmMsg,
 };
 
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use terranames::events::{CollectorAction, CollectorEvent, MigrateEvent};
 use terranames::root_collector::{
     ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, ReceiveMsg,
-    StakeStateResponse, StateResponse, QueryMsg,
+    StakeStateResponse, StateResponse, StatsResponse, QueryMsg,
 };
-use terranames::terra::deduct_coin_tax;
+use terranames::tax::TaxPolicy;
 
 use crate::errors::{
-    ContractError, InsufficientFunds, InsufficientTokens, Unauthorized,
+    ContractError, InsufficientFunds, InsufficientTokens, MigrationInProgress,
+    Unauthorized, UnsupportedTaxPolicy,
 };
 use crate::state::{
-    read_config, read_option_stake_state, read_stake_state, read_state,
-    store_config, store_stake_state, store_state, Config, StakeState, State,
+    continue_stats_migration, is_stats_migration_pending, migrate_singletons,
+    migrate_stats, read_config, read_option_stake_state, read_stake_state,
+    read_state, read_stats, store_config, store_stake_state, store_state,
+    store_stats, Config, StakeState, State,
 };
 
 type ContractResult<T> = Result<T, ContractError>;
 
+/// Default number of stake states counted by a migration adding stats
+const DEFAULT_STAKE_LIMIT: u32 = 300;
+
+/// Maximum number of stake states counted by a migration adding stats
+const MAX_STAKE_LIMIT: u32 = 1000;
+
 /// Return the funds of type denom attached in the request.
 fn get_sent_funds(info: &MessageInfo, denom: &str) -> Uint128 {
     info.funds
@@ -41,7 +51,7 @@ fn send_dividend_msg(
         BankMsg::Send {
             to_address: to.into(),
             amount: vec![
-                deduct_coin_tax(
+                config.tax_policy.deduct_coin_tax(
                     querier,
                     Coin {
                         denom: config.stable_denom.clone(),
@@ -71,6 +81,14 @@ fn send_tokens_msg(
     ))
 }
 
+/// Check that the tax policy is supported by this build
+fn validate_tax_policy(tax_policy: &TaxPolicy) -> ContractResult<()> {
+    if !tax_policy.is_supported() {
+        return UnsupportedTaxPolicy.fail();
+    }
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -78,10 +96,13 @@ pub fn instantiate(
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> ContractResult<Response> {
+    validate_tax_policy(&msg.tax_policy)?;
+
     let config = Config {
         base_token: deps.api.addr_validate(&msg.base_token)?,
         stable_denom: msg.stable_denom,
         unstake_delay: msg.unstake_delay,
+        tax_policy: msg.tax_policy,
     };
 
     store_config(deps.storage, &config)?;
@@ -104,6 +125,11 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> ContractResult<Response> {
+    // Unstaking tokens are not accounted for until all stakes are counted
+    if is_stats_migration_pending(deps.storage)? {
+        return MigrationInProgress.fail();
+    }
+
     match msg {
         ExecuteMsg::Deposit {} => {
             execute_deposit(deps, env, info)
@@ -133,7 +159,8 @@ fn execute_deposit(
     let config = read_config(deps.storage)?;
     let mut state = read_state(deps.storage)?;
 
-    let deposit = get_sent_funds(&info, &config.stable_denom) + state.residual;
+    let received = get_sent_funds(&info, &config.stable_denom);
+    let deposit = received.checked_add(state.residual)?;
     let (deposit_per_stake, residual) = if !state.total_staked.is_zero() {
         let deposit_per_stake = Decimal::from_ratio(deposit, state.total_staked);
         let residual = deposit.checked_sub(deposit_per_stake * state.total_staked)?;
@@ -142,16 +169,29 @@ fn execute_deposit(
         (Decimal::zero(), deposit)
     };
 
-    state.multiplier = state.multiplier + deposit_per_stake;
+    let multiplier_numer = Uint128::from(state.multiplier.numerator())
+        .checked_add(deposit_per_stake.numerator().into())?;
+    state.multiplier = Decimal::from_ratio(multiplier_numer, Decimal::one().denominator());
     state.residual = residual;
 
     store_state(deps.storage, &state)?;
 
+    let mut stats = read_stats(deps.storage)?;
+    stats.total_deposits = stats.total_deposits.checked_add(received)?;
+    store_stats(deps.storage, &stats)?;
+
+    let event = CollectorEvent {
+        action: CollectorAction::Deposit,
+        address: info.sender,
+        recipient: None,
+        amount: received,
+        staked_amount: state.total_staked,
+        unstaked_amount: Uint128::zero(),
+        multiplier: state.multiplier,
+    };
+
     Ok(Response::new()
-        .add_attribute("action", "deposit")
-        .add_attribute("amount", deposit)
-        .add_attribute("multiplier", state.multiplier.to_string())
-        .add_attribute("residual", state.residual)
+        .add_event(event.into())
     )
 }
 
@@ -175,20 +215,33 @@ fn execute_unstake_tokens(
         return InsufficientTokens.fail();
     }
 
-    stake_state.update_dividend(state.multiplier);
-    stake_state.update_unstaked_amount(env.block.time.into(), config.unstake_delay);
+    stake_state.update_dividend(state.multiplier)?;
+    stake_state.update_unstaked_amount(env.block.time.into(), config.unstake_delay)?;
 
     stake_state.staked_amount = stake_state.staked_amount.checked_sub(amount)?;
-    stake_state.unstaking_amount += amount;
+    stake_state.unstaking_amount = stake_state.unstaking_amount.checked_add(amount)?;
     stake_state.unstaking_begin_time = Some(env.block.time.into());
     store_stake_state(deps.storage, &info.sender, &stake_state)?;
 
     state.total_staked = state.total_staked.checked_sub(amount)?;
     store_state(deps.storage, &state)?;
 
+    let mut stats = read_stats(deps.storage)?;
+    stats.tokens_unstaking = stats.tokens_unstaking.checked_add(amount)?;
+    store_stats(deps.storage, &stats)?;
+
+    let event = CollectorEvent {
+        action: CollectorAction::UnstakeTokens,
+        address: info.sender,
+        recipient: None,
+        amount,
+        staked_amount: stake_state.staked_amount,
+        unstaked_amount: stake_state.unstaked_amount,
+        multiplier: state.multiplier,
+    };
+
     Ok(Response::new()
-        .add_attribute("action", "unstake_tokens")
-        .add_attribute("staked_amount", stake_state.staked_amount)
+        .add_event(event.into())
     )
 }
 
@@ -200,6 +253,7 @@ fn execute_withdraw_tokens(
     to: Option<Addr>,
 ) -> ContractResult<Response> {
     let config = read_config(deps.storage)?;
+    let state = read_state(deps.storage)?;
 
     let opt_stake_state = read_option_stake_state(deps.storage, &info.sender)?;
     let mut stake_state = if let Some(stake_state) = opt_stake_state {
@@ -208,7 +262,7 @@ fn execute_withdraw_tokens(
         return InsufficientTokens.fail();
     };
 
-    stake_state.update_unstaked_amount(env.block.time.into(), config.unstake_delay);
+    stake_state.update_unstaked_amount(env.block.time.into(), config.unstake_delay)?;
 
     if stake_state.unstaked_amount < amount {
         return InsufficientTokens.fail();
@@ -219,18 +273,32 @@ fn execute_withdraw_tokens(
     stake_state.unstaked_amount = stake_state.unstaked_amount.checked_sub(amount)?;
     store_stake_state(deps.storage, &info.sender, &stake_state)?;
 
+    let mut stats = read_stats(deps.storage)?;
+    stats.tokens_unstaking = stats.tokens_unstaking.checked_sub(amount)?;
+    store_stats(deps.storage, &stats)?;
+
+    let recipient = to.unwrap_or_else(|| info.sender.clone());
     messages.push(
         send_tokens_msg(
             &config,
-            &to.unwrap_or(info.sender),
+            &recipient,
             amount,
         )?,
     );
 
+    let event = CollectorEvent {
+        action: CollectorAction::WithdrawTokens,
+        address: info.sender,
+        recipient: Some(recipient),
+        amount,
+        staked_amount: stake_state.staked_amount,
+        unstaked_amount: stake_state.unstaked_amount,
+        multiplier: state.multiplier,
+    };
+
     Ok(Response::new()
         .add_messages(messages)
-        .add_attribute("action", "withdraw_tokens")
-        .add_attribute("unstaked_amount", stake_state.unstaked_amount)
+        .add_event(event.into())
     )
 }
 
@@ -250,27 +318,42 @@ fn execute_withdraw_dividends(
         return InsufficientFunds.fail();
     };
 
-    stake_state.update_dividend(state.multiplier);
+    stake_state.update_dividend(state.multiplier)?;
     if stake_state.dividend.is_zero() {
         return InsufficientFunds.fail();
     }
 
-    let mut messages = vec![];
-    messages.push(
+    let recipient = to.unwrap_or_else(|| info.sender.clone());
+    let messages = vec![
         send_dividend_msg(
             &deps.querier,
             &config,
-            &to.unwrap_or(info.sender.clone()),
+            &recipient,
             stake_state.dividend,
         )?,
-    );
+    ];
+
+    let event = CollectorEvent {
+        action: CollectorAction::WithdrawDividends,
+        address: info.sender.clone(),
+        recipient: Some(recipient),
+        amount: stake_state.dividend,
+        staked_amount: stake_state.staked_amount,
+        unstaked_amount: stake_state.unstaked_amount,
+        multiplier: state.multiplier,
+    };
+
+    let mut stats = read_stats(deps.storage)?;
+    stats.total_dividends_withdrawn = stats.total_dividends_withdrawn
+        .checked_add(stake_state.dividend)?;
+    store_stats(deps.storage, &stats)?;
 
     stake_state.dividend = Uint128::zero();
     store_stake_state(deps.storage, &info.sender, &stake_state)?;
 
     Ok(Response::new()
         .add_messages(messages)
-        .add_attribute("action", "withdraw_dividends")
+        .add_event(event.into())
     )
 }
 
@@ -305,8 +388,8 @@ fn execute_receive_stake(
     let opt_stake_state = read_option_stake_state(deps.storage, &token_sender)?;
 
     let stake_state = if let Some(mut stake_state) = opt_stake_state {
-        stake_state.update_dividend(state.multiplier);
-        stake_state.staked_amount += wrapper.amount;
+        stake_state.update_dividend(state.multiplier)?;
+        stake_state.staked_amount = stake_state.staked_amount.checked_add(wrapper.amount)?;
         stake_state
     } else {
         StakeState {
@@ -320,14 +403,21 @@ fn execute_receive_stake(
     };
     store_stake_state(deps.storage, &token_sender, &stake_state)?;
 
-    state.total_staked += wrapper.amount;
+    state.total_staked = state.total_staked.checked_add(wrapper.amount)?;
     store_state(deps.storage, &state)?;
 
+    let event = CollectorEvent {
+        action: CollectorAction::Stake,
+        address: token_sender,
+        recipient: None,
+        amount: wrapper.amount,
+        staked_amount: stake_state.staked_amount,
+        unstaked_amount: stake_state.unstaked_amount,
+        multiplier: state.multiplier,
+    };
+
     Ok(Response::new()
-        .add_attribute("action", "receive")
-        .add_attribute("receive_type", "stake")
-        .add_attribute("staked_amount", stake_state.staked_amount)
-        .add_attribute("multiplier", stake_state.multiplier.to_string())
+        .add_event(event.into())
     )
 }
 
@@ -351,6 +441,9 @@ pub fn query(
                 deps.api.addr_validate(&address)?,
             )?)?)
         },
+        QueryMsg::Stats {} => {
+            Ok(to_binary(&query_stats(deps, env)?)?)
+        },
     }
 }
 
@@ -360,9 +453,10 @@ fn query_config(
 ) -> ContractResult<ConfigResponse> {
     let config = read_config(deps.storage)?;
     Ok(ConfigResponse {
-        base_token: config.base_token.into(),
+        base_token: config.base_token,
         stable_denom: config.stable_denom,
         unstake_delay: config.unstake_delay,
+        tax_policy: config.tax_policy,
     })
 }
 
@@ -378,6 +472,18 @@ fn query_state(
     })
 }
 
+fn query_stats(
+    deps: Deps,
+    _env: Env,
+) -> ContractResult<StatsResponse> {
+    let stats = read_stats(deps.storage)?;
+    Ok(StatsResponse {
+        total_deposits: stats.total_deposits,
+        total_dividends_withdrawn: stats.total_dividends_withdrawn,
+        tokens_unstaking: stats.tokens_unstaking,
+    })
+}
+
 fn query_stake_state(
     deps: Deps,
     env: Env,
@@ -389,10 +495,13 @@ fn query_stake_state(
 
     let (unstaking_amount, unstaked_amount) = stake_state.unstaking_unstaked_amount(
         env.block.time.into(), config.unstake_delay,
-    );
-    let unstake_time = stake_state.unstaking_begin_time.map(|t| t + config.unstake_delay);
+    )?;
+    let unstake_time = match stake_state.unstaking_begin_time {
+        Some(begin_time) => Some(begin_time.checked_add(config.unstake_delay)?),
+        None => None,
+    };
 
-    let dividend = stake_state.dividend(state.multiplier);
+    let dividend = stake_state.dividend(state.multiplier)?;
 
     Ok(StakeStateResponse {
         staked_amount: stake_state.staked_amount,
@@ -406,9 +515,29 @@ fn query_stake_state(
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
-    _msg: MigrateMsg,
+    msg: MigrateMsg,
 ) -> ContractResult<Response> {
-    Ok(Response::default())
+    migrate_singletons(deps.storage)?;
+    migrate_stats(deps.storage)?;
+
+    if let Some(tax_policy) = msg.tax_policy {
+        validate_tax_policy(&tax_policy)?;
+        let mut config = read_config(deps.storage)?;
+        config.tax_policy = tax_policy;
+        store_config(deps.storage, &config)?;
+    }
+
+    let stake_limit = msg.stake_limit.unwrap_or(DEFAULT_STAKE_LIMIT).min(MAX_STAKE_LIMIT);
+    let (count, remaining) = continue_stats_migration(deps.storage, stake_limit)?;
+
+    let event = MigrateEvent {
+        migrated: count as u64,
+        complete: !remaining,
+    };
+
+    Ok(Response::new()
+        .add_event(event.into())
+    )
 }
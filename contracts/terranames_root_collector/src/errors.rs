@@ -14,6 +14,10 @@ pub enum ContractError {
     InsufficientTokens { backtrace: Option<snafu::Backtrace> },
     #[snafu(display("Insufficient funds"))]
     InsufficientFunds { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Tax policy is not supported"))]
+    UnsupportedTaxPolicy { backtrace: Option<snafu::Backtrace> },
+    #[snafu(display("Migration in progress"))]
+    MigrationInProgress { backtrace: Option<snafu::Backtrace> },
 }
 
 impl From<StdError> for ContractError {
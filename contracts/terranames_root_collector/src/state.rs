@@ -1,13 +1,29 @@
-use cosmwasm_std::{Addr, Decimal, Fraction, StdResult, Storage, Uint128};
-use cosmwasm_storage::{bucket, bucket_read, singleton, singleton_read};
+use cosmwasm_std::{
+    Addr, Decimal, Fraction, OverflowError, Order, StdResult, Storage, Uint128,
+};
+use cw_storage_plus::{Bound, Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use terranames::storage::migrate_singleton;
+use terranames::tax::TaxPolicy;
 use terranames::utils::{Timedelta, Timestamp};
 
-pub static CONFIG_KEY: &[u8] = b"config";
-pub static STATE_KEY: &[u8] = b"state";
-pub static STAKE_STATE_PREFIX: &[u8] = b"stake";
+const CONFIG_KEY: &str = "config";
+const STATE_KEY: &str = "state";
+pub const CONFIG: Item<Config> = Item::new(CONFIG_KEY);
+pub const STATE: Item<State> = Item::new(STATE_KEY);
+pub const STAKE_STATES: Map<&Addr, StakeState> = Map::new("stake");
+pub const STATS: Item<Stats> = Item::new("stats");
+/// Progress of counting the stake states after stats were introduced
+pub const STATS_MIGRATION: Item<StatsMigration> = Item::new("stats_migration");
+
+/// Move singletons stored by earlier versions
+pub fn migrate_singletons(storage: &mut dyn Storage) -> StdResult<()> {
+    migrate_singleton(storage, CONFIG_KEY, &CONFIG)?;
+    migrate_singleton(storage, STATE_KEY, &STATE)?;
+    Ok(())
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -17,18 +33,22 @@ pub struct Config {
     pub stable_denom: String,
     /// Unstake delay
     pub unstake_delay: Timedelta,
+    /// Tax deducted from dividends sent by the contract
+    ///
+    /// Configs stored by earlier versions use the default policy.
+    #[serde(default)]
+    pub tax_policy: TaxPolicy,
 }
 
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
-    singleton_read(storage, CONFIG_KEY).load()
+    CONFIG.load(storage)
 }
 
-#[must_use]
 pub fn store_config(
     storage: &mut dyn Storage,
     config: &Config,
 ) -> StdResult<()> {
-    singleton(storage, CONFIG_KEY).save(config)
+    CONFIG.save(storage, config)
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -42,15 +62,14 @@ pub struct State {
 }
 
 pub fn read_state(storage: &dyn Storage) -> StdResult<State> {
-    singleton_read(storage, STATE_KEY).load()
+    STATE.load(storage)
 }
 
-#[must_use]
 pub fn store_state(
     storage: &mut dyn Storage,
     state: &State,
 ) -> StdResult<()> {
-    singleton(storage, STATE_KEY).save(state)
+    STATE.save(storage, state)
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -75,7 +94,7 @@ pub struct StakeState {
 
 impl StakeState {
     /// Return full dividend
-    pub fn dividend(&self, global_multiplier: Decimal) -> Uint128 {
+    pub fn dividend(&self, global_multiplier: Decimal) -> Result<Uint128, OverflowError> {
         // Compute dividend since last state update
         let dividend_per_token_numer = global_multiplier.numerator().saturating_sub(
             self.multiplier.numerator()
@@ -84,19 +103,24 @@ impl StakeState {
             dividend_per_token_numer, Decimal::one().denominator(),
         );
 
-        self.dividend + new_dividend
+        self.dividend.checked_add(new_dividend)
     }
 
     /// Update dividend in state
-    pub fn update_dividend(&mut self, global_multiplier: Decimal) {
-        self.dividend = self.dividend(global_multiplier);
+    pub fn update_dividend(&mut self, global_multiplier: Decimal) -> Result<(), OverflowError> {
+        self.dividend = self.dividend(global_multiplier)?;
         self.multiplier = global_multiplier;
+        Ok(())
     }
 
     /// Return full unstaked amount
-    pub fn unstaking_unstaked_amount(&self, timestamp: Timestamp, unstake_delay: Timedelta) -> (Uint128, Uint128) {
+    pub fn unstaking_unstaked_amount(
+        &self,
+        timestamp: Timestamp,
+        unstake_delay: Timedelta,
+    ) -> Result<(Uint128, Uint128), OverflowError> {
         let (new_unstaking, add_unstaked) = if let Some(begin_time) = self.unstaking_begin_time {
-            if begin_time + unstake_delay < timestamp {
+            if begin_time.checked_add(unstake_delay)? < timestamp {
                 (Uint128::zero(), self.unstaking_amount)
             } else {
                 (self.unstaking_amount, Uint128::zero())
@@ -105,18 +129,23 @@ impl StakeState {
             (Uint128::zero(), Uint128::zero())
         };
 
-        (new_unstaking, self.unstaked_amount + add_unstaked)
+        Ok((new_unstaking, self.unstaked_amount.checked_add(add_unstaked)?))
     }
 
     /// Update unstaked amount in state
-    pub fn update_unstaked_amount(&mut self, timestamp: Timestamp, unstake_delay: Timedelta) {
+    pub fn update_unstaked_amount(
+        &mut self,
+        timestamp: Timestamp,
+        unstake_delay: Timedelta,
+    ) -> Result<(), OverflowError> {
         if let Some(begin_time) = self.unstaking_begin_time {
-            if begin_time + unstake_delay <= timestamp {
-                self.unstaked_amount += self.unstaking_amount;
+            if begin_time.checked_add(unstake_delay)? <= timestamp {
+                self.unstaked_amount = self.unstaked_amount.checked_add(self.unstaking_amount)?;
                 self.unstaking_amount = Uint128::zero();
                 self.unstaking_begin_time = None;
             }
         }
+        Ok(())
     }
 }
 
@@ -124,16 +153,14 @@ pub fn read_stake_state(
     storage: &dyn Storage,
     address: &Addr
 ) -> StdResult<StakeState> {
-    bucket_read(storage, STAKE_STATE_PREFIX)
-        .load(address.as_ref().as_bytes())
+    STAKE_STATES.load(storage, address)
 }
 
 pub fn read_option_stake_state(
     storage: &dyn Storage,
     address: &Addr,
 ) -> StdResult<Option<StakeState>> {
-    bucket_read(storage, STAKE_STATE_PREFIX)
-        .may_load(address.as_ref().as_bytes())
+    STAKE_STATES.may_load(storage, address)
 }
 
 pub fn store_stake_state(
@@ -141,5 +168,97 @@ pub fn store_stake_state(
     address: &Addr,
     stake_state: &StakeState,
 ) -> StdResult<()> {
-    bucket(storage, STAKE_STATE_PREFIX).save(address.as_ref().as_bytes(), stake_state)
+    STAKE_STATES.save(storage, address, stake_state)
+}
+
+/// Running totals of funds and tokens handled by the collector
+///
+/// The totals of funds only include funds handled after the stats were
+/// introduced.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Stats {
+    /// Total stablecoin deposits received
+    pub total_deposits: Uint128,
+    /// Total dividends withdrawn by stakers
+    pub total_dividends_withdrawn: Uint128,
+    /// Tokens unstaked that have not been withdrawn yet
+    pub tokens_unstaking: Uint128,
+}
+
+/// Read stats (zero if never stored)
+pub fn read_stats(storage: &dyn Storage) -> StdResult<Stats> {
+    Ok(STATS.may_load(storage)?.unwrap_or_default())
+}
+
+/// Progress of counting the stake states into the stats
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct StatsMigration {
+    /// Last staker that was counted (None if no stakers were counted yet)
+    pub last_staker: Option<Addr>,
+}
+
+/// Start storing stats for storage written before the stats were introduced
+///
+/// Tokens that are unstaking are counted from the stake states in batches
+/// by continue_stats_migration so that withdrawing tokens unstaked before
+/// the migration is accounted for. Returns true if the migration started.
+pub fn migrate_stats(storage: &mut dyn Storage) -> StdResult<bool> {
+    if STATS.may_load(storage)?.is_some() {
+        return Ok(false);
+    }
+
+    STATS.save(storage, &Stats::default())?;
+    STATS_MIGRATION.save(storage, &StatsMigration::default())?;
+    Ok(true)
+}
+
+/// Whether stake states remain to be counted after a migration
+pub fn is_stats_migration_pending(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(STATS_MIGRATION.may_load(storage)?.is_some())
+}
+
+/// Count the unstaking tokens of at most limit stake states
+///
+/// Returns the number of stake states counted and whether stake states
+/// remain to be counted.
+pub fn continue_stats_migration(
+    storage: &mut dyn Storage,
+    limit: u32,
+) -> StdResult<(usize, bool)> {
+    let mut migration = match STATS_MIGRATION.may_load(storage)? {
+        Some(migration) => migration,
+        None => return Ok((0, false)),
+    };
+
+    let start = migration.last_staker.as_ref().map(|addr| Bound::exclusive(addr.as_bytes()));
+    let items: Vec<(Vec<u8>, StakeState)> = STAKE_STATES
+        .range(storage, start, None, Order::Ascending)
+        .take(limit as usize)
+        .collect::<StdResult<_>>()?;
+
+    let mut stats = read_stats(storage)?;
+    for (key, stake_state) in &items {
+        stats.tokens_unstaking = stats.tokens_unstaking
+            .checked_add(stake_state.unstaking_amount)?
+            .checked_add(stake_state.unstaked_amount)?;
+        let staker = String::from_utf8(key.clone())?;
+        migration.last_staker = Some(Addr::unchecked(staker));
+    }
+    store_stats(storage, &stats)?;
+
+    let remaining = items.len() == limit as usize;
+    if remaining {
+        STATS_MIGRATION.save(storage, &migration)?;
+    } else {
+        STATS_MIGRATION.remove(storage);
+    }
+
+    Ok((items.len(), remaining))
+}
+
+pub fn store_stats(
+    storage: &mut dyn Storage,
+    stats: &Stats,
+) -> StdResult<()> {
+    STATS.save(storage, stats)
 }
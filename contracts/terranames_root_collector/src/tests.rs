@@ -1,21 +1,28 @@
 use std::str::FromStr;
 
 use cosmwasm_std::{
-    coins, from_binary, to_binary, BankMsg, CosmosMsg, Decimal, SubMsg,
+    coins, from_binary, to_binary, Addr, BankMsg, CosmosMsg, Decimal, SubMsg,
     Uint128, WasmMsg,
 };
 use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_storage::{bucket, bucket_read, singleton};
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use terranames::events::{CollectorAction, CollectorEvent, MigrateEvent};
 use terranames::root_collector::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, ReceiveMsg,
-    StakeStateResponse, StateResponse,
+    ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+    ReceiveMsg, StakeStateResponse, StateResponse, StatsResponse,
 };
+use terranames::tax::TaxPolicy;
 use terranames::testing::helpers::EnvBuilder;
+use terranames::testing::mock_dependencies;
 use terranames::utils::{Timedelta, Timestamp};
 
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
 use crate::errors::ContractError;
-use crate::mock_querier::mock_dependencies;
+use crate::state::{
+    read_config, read_option_stake_state, read_stake_state, read_state,
+    store_stake_state, StakeState, State, STATS,
+};
 
 static ABC_COIN: &str = "uabc";
 
@@ -24,6 +31,15 @@ fn default_init() -> InstantiateMsg {
         base_token: "token_contract".into(),
         stable_denom: ABC_COIN.into(),
         unstake_delay: Timedelta::from_seconds(1_814_400), // 3 weeks
+        tax_policy: mock_tax_policy(),
+    }
+}
+
+/// Tax policy matching the default mock Terra tax
+fn mock_tax_policy() -> TaxPolicy {
+    TaxPolicy::Fixed {
+        rate: Decimal::from_ratio(405u128, 100_000u128),
+        cap: Some(Uint128::new(1_500_000)),
     }
 }
 
@@ -45,6 +61,7 @@ fn proper_initialization() {
     assert_eq!(config.base_token.as_str(), "token_contract");
     assert_eq!(config.stable_denom, "uabc");
     assert_eq!(config.unstake_delay.value(), 1_814_400);
+    assert_eq!(config.tax_policy, mock_tax_policy());
 
     let env = mock_env();
     let res = query(deps.as_ref(), env, QueryMsg::State {}).unwrap();
@@ -165,6 +182,13 @@ fn deposit_funds_with_residual() {
     let res = execute(deps.as_mut(), env, info, ExecuteMsg::Deposit {}).unwrap();
     assert_eq!(res.messages.len(), 0);
 
+    assert_eq!(res.events.len(), 1);
+    let event = CollectorEvent::parse(&res.events[0]).unwrap();
+    assert_eq!(event.action, CollectorAction::Deposit);
+    assert_eq!(event.address, Addr::unchecked("auction"));
+    assert_eq!(event.amount.u128(), deposit_amount_2);
+    assert_eq!(event.staked_amount.u128(), stake_amount);
+
     // Since there is only a single staker, the full deposit is paid as dividend
     // to this account. Note: Calculation is off by one due to rounding.
     let env = mock_env();
@@ -399,7 +423,7 @@ fn withdraw_tokens() {
         SubMsg { msg: CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }), .. } => {
             assert_eq!(contract_addr.as_str(), "token_contract");
             assert_eq!(funds, &[]);
-            let cw20_msg: Cw20ExecuteMsg = from_binary(&msg).unwrap();
+            let cw20_msg: Cw20ExecuteMsg = from_binary(msg).unwrap();
             match cw20_msg {
                 Cw20ExecuteMsg::Transfer { recipient, amount } => {
                     assert_eq!(recipient.as_str(), "staker_1");
@@ -440,7 +464,7 @@ fn withdraw_tokens() {
         SubMsg { msg: CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }), .. } => {
             assert_eq!(contract_addr.as_str(), "token_contract");
             assert_eq!(funds, &[]);
-            let cw20_msg: Cw20ExecuteMsg = from_binary(&msg).unwrap();
+            let cw20_msg: Cw20ExecuteMsg = from_binary(msg).unwrap();
             match cw20_msg {
                 Cw20ExecuteMsg::Transfer { recipient, amount } => {
                     assert_eq!(recipient.as_str(), "staker_1");
@@ -520,7 +544,7 @@ fn withdraw_tokens_to_address() {
         SubMsg { msg: CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }), .. } => {
             assert_eq!(contract_addr.as_str(), "token_contract");
             assert_eq!(funds, &[]);
-            let cw20_msg: Cw20ExecuteMsg = from_binary(&msg).unwrap();
+            let cw20_msg: Cw20ExecuteMsg = from_binary(msg).unwrap();
             match cw20_msg {
                 Cw20ExecuteMsg::Transfer { recipient, amount } => {
                     assert_eq!(recipient.as_str(), "recipient");
@@ -1047,3 +1071,253 @@ fn withdraw_dividends_fails_for_withdraw_zero() {
     });
     assert!(matches!(res, Err(ContractError::InsufficientFunds { .. })));
 }
+
+#[test]
+fn stake_storage_round_trip() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    // Config and state stored as singletons by an earlier version are moved
+    // by the migration
+    let config = read_config(&deps.storage).unwrap();
+    let state = State {
+        multiplier: Decimal::from_str("0.25").unwrap(),
+        total_staked: Uint128::from(4_000u64),
+        residual: Uint128::from(3u64),
+    };
+    let mut old_deps = mock_dependencies(&[]);
+    singleton(&mut old_deps.storage, b"config").save(&config).unwrap();
+    singleton(&mut old_deps.storage, b"state").save(&state).unwrap();
+    assert!(read_config(&old_deps.storage).is_err());
+
+    migrate(old_deps.as_mut(), mock_env(), MigrateMsg { tax_policy: None, stake_limit: None }).unwrap();
+    assert_eq!(read_config(&old_deps.storage).unwrap(), config);
+    assert_eq!(read_state(&old_deps.storage).unwrap(), state);
+
+    let stake_state = StakeState {
+        staked_amount: Uint128::from(1_000u64),
+        unstaking_amount: Uint128::from(200u64),
+        unstaking_begin_time: Some(Timestamp::from_seconds(1234)),
+        unstaked_amount: Uint128::zero(),
+        multiplier: Decimal::from_str("0.5").unwrap(),
+        dividend: Uint128::from(30u64),
+    };
+
+    // Stake state stored in the map can be read as a bucket
+    let staker = Addr::unchecked("staker");
+    store_stake_state(&mut deps.storage, &staker, &stake_state).unwrap();
+    let bucket_stake_state: StakeState = bucket_read(&deps.storage, b"stake")
+        .load(b"staker")
+        .unwrap();
+    assert_eq!(bucket_stake_state, stake_state);
+
+    // Stake state stored in a bucket by an earlier version can be read
+    bucket(&mut deps.storage, b"stake").save(b"other", &stake_state).unwrap();
+    let other = Addr::unchecked("other");
+    assert_eq!(read_stake_state(&deps.storage, &other).unwrap(), stake_state);
+    assert_eq!(read_option_stake_state(&deps.storage, &Addr::unchecked("none")).unwrap(), None);
+}
+
+#[test]
+fn stats_query() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let stake_amount: u128 = 1_000;
+    let env = mock_env().at_time(1_000_000);
+    let info = mock_info("token_contract", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::Receive(Cw20ReceiveMsg {
+        amount: Uint128::from(stake_amount),
+        sender: "staker".into(),
+        msg: to_binary(&ReceiveMsg::Stake { }).unwrap(),
+    })).unwrap();
+
+    let env = mock_env().at_time(1_000_000);
+    let info = mock_info("auction", &coins(5_000, ABC_COIN));
+    execute(deps.as_mut(), env, info, ExecuteMsg::Deposit {}).unwrap();
+
+    let env = mock_env().at_time(1_000_000);
+    let info = mock_info("staker", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::UnstakeTokens {
+        amount: Uint128::from(400u64),
+    }).unwrap();
+
+    let env = mock_env().at_time(1_000_000);
+    let info = mock_info("staker", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::WithdrawDividends {
+        to: None,
+    }).unwrap();
+
+    let env = mock_env().at_time(1_000_000);
+    let res = query(deps.as_ref(), env, QueryMsg::Stats {}).unwrap();
+    let stats: StatsResponse = from_binary(&res).unwrap();
+    assert_eq!(stats, StatsResponse {
+        total_deposits: Uint128::from(5_000u64),
+        total_dividends_withdrawn: Uint128::from(5_000u64),
+        tokens_unstaking: Uint128::from(400u64),
+    });
+
+    // Withdraw part of the tokens after the unstake delay
+    let withdraw_time = 1_000_000 + 1_814_400;
+    let env = mock_env().at_time(withdraw_time);
+    let info = mock_info("staker", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::WithdrawTokens {
+        amount: Uint128::from(300u64),
+        to: None,
+    }).unwrap();
+
+    let env = mock_env().at_time(withdraw_time);
+    let res = query(deps.as_ref(), env, QueryMsg::Stats {}).unwrap();
+    let stats: StatsResponse = from_binary(&res).unwrap();
+    assert_eq!(stats.tokens_unstaking, Uint128::from(100u64));
+    assert_eq!(stats.total_deposits, Uint128::from(5_000u64));
+}
+
+#[test]
+fn migrate_stats_counts_tokens_unstaking() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    let env = mock_env().at_time(1_000_000);
+    let info = mock_info("token_contract", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::Receive(Cw20ReceiveMsg {
+        amount: Uint128::from(1_000u64),
+        sender: "staker".into(),
+        msg: to_binary(&ReceiveMsg::Stake { }).unwrap(),
+    })).unwrap();
+
+    let env = mock_env().at_time(1_000_000);
+    let info = mock_info("token_contract", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::Receive(Cw20ReceiveMsg {
+        amount: Uint128::from(500u64),
+        sender: "other_staker".into(),
+        msg: to_binary(&ReceiveMsg::Stake { }).unwrap(),
+    })).unwrap();
+
+    for (staker, amount) in &[("staker", 400u64), ("other_staker", 100u64)] {
+        let env = mock_env().at_time(1_000_000);
+        let info = mock_info(staker, &[]);
+        execute(deps.as_mut(), env, info, ExecuteMsg::UnstakeTokens {
+            amount: Uint128::from(*amount),
+        }).unwrap();
+    }
+
+    // Storage as written by a version without stats
+    STATS.remove(&mut deps.storage);
+
+    // Stakes are counted in batches
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        tax_policy: None,
+        stake_limit: Some(1),
+    }).unwrap();
+    assert_eq!(MigrateEvent::parse(&res.events[0]), Ok(MigrateEvent {
+        migrated: 1,
+        complete: false,
+    }));
+
+    // Tokens cannot be withdrawn until all stakes are counted
+    let env = mock_env().at_time(1_000_000 + 1_814_400);
+    let info = mock_info("staker", &[]);
+    let res = execute(deps.as_mut(), env, info, ExecuteMsg::WithdrawTokens {
+        amount: Uint128::from(400u64),
+        to: None,
+    });
+    assert!(matches!(res, Err(ContractError::MigrationInProgress { .. })));
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        tax_policy: None,
+        stake_limit: Some(1),
+    }).unwrap();
+    assert_eq!(MigrateEvent::parse(&res.events[0]), Ok(MigrateEvent {
+        migrated: 1,
+        complete: false,
+    }));
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        tax_policy: None,
+        stake_limit: Some(1),
+    }).unwrap();
+    assert_eq!(MigrateEvent::parse(&res.events[0]), Ok(MigrateEvent {
+        migrated: 0,
+        complete: true,
+    }));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Stats {}).unwrap();
+    let stats: StatsResponse = from_binary(&res).unwrap();
+    assert_eq!(stats, StatsResponse {
+        total_deposits: Uint128::zero(),
+        total_dividends_withdrawn: Uint128::zero(),
+        tokens_unstaking: Uint128::from(500u64),
+    });
+
+    // Migrating again does not count the stakes again
+    migrate(deps.as_mut(), mock_env(), MigrateMsg { tax_policy: None, stake_limit: None }).unwrap();
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Stats {}).unwrap();
+    let stats: StatsResponse = from_binary(&res).unwrap();
+    assert_eq!(stats.tokens_unstaking, Uint128::from(500u64));
+
+    // Tokens unstaked before the migration can be withdrawn
+    let env = mock_env().at_time(1_000_000 + 1_814_400);
+    let info = mock_info("staker", &[]);
+    execute(deps.as_mut(), env, info, ExecuteMsg::WithdrawTokens {
+        amount: Uint128::from(400u64),
+        to: None,
+    }).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Stats {}).unwrap();
+    let stats: StatsResponse = from_binary(&res).unwrap();
+    assert_eq!(stats.tokens_unstaking, Uint128::from(100u64));
+}
+
+#[test]
+fn migrate_tax_policy() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = default_init();
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+    instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {
+        tax_policy: Some(TaxPolicy::None {}),
+        stake_limit: None,
+    }).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(config.tax_policy, TaxPolicy::None {});
+
+    // Leaving out the tax policy keeps the current policy
+    migrate(deps.as_mut(), mock_env(), MigrateMsg { tax_policy: None, stake_limit: None }).unwrap();
+    assert_eq!(read_config(&deps.storage).unwrap().tax_policy, TaxPolicy::None {});
+}
+
+#[cfg(not(feature = "terra"))]
+#[test]
+fn instantiate_with_unsupported_tax_policy() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        tax_policy: TaxPolicy::Terra {},
+        ..default_init()
+    };
+    let env = mock_env();
+    let info = mock_info("creator", &[]);
+
+    let res = instantiate(deps.as_mut(), env, info, msg);
+    assert!(matches!(res, Err(ContractError::UnsupportedTaxPolicy { .. })));
+}
@@ -1,16 +1,56 @@
-use cosmwasm_std::{Addr, Uint128};
+use std::convert::TryFrom;
+use std::fmt;
+
+use cosmwasm_std::{
+    Addr, Decimal, OverflowError, OverflowOperation, Uint128, Uint64,
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::tax::TaxPolicy;
 use crate::utils::{Timedelta, Timestamp};
 
 /// Rate is provided as number of stablecoins per day
 pub const RATE_SEC_DENOM: Timedelta = Timedelta::from_seconds(24 * 60 * 60);
 
+/// Separator between the label and the TLD of a name
+pub const TLD_SEPARATOR: char = '.';
+
+/// Split name into the label and the TLD
+///
+/// The TLD is the part after the last separator. Names without a separator
+/// belong to the default namespace and have no TLD.
+pub fn split_name(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once(TLD_SEPARATOR) {
+        Some((label, tld)) => (label, Some(tld)),
+        None => (name, None),
+    }
+}
+
+/// Return whether tld is a valid TLD
+///
+/// TLDs consist of lowercase ASCII letters and digits only.
+pub fn is_valid_tld(tld: &str) -> bool {
+    !tld.is_empty() && tld.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+}
+
+/// Return whether name is a valid name
+///
+/// The label must be non-empty and must not contain the separator, and the
+/// TLD (if any) must be valid.
+pub fn is_valid_name(name: &str) -> bool {
+    let (label, tld) = split_name(name);
+    let tld_is_valid = match tld {
+        Some(tld) => is_valid_tld(tld),
+        None => true,
+    };
+    !label.is_empty() && !label.contains(TLD_SEPARATOR) && tld_is_valid
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
-    /// Collector of funds
-    pub collector_addr: String,
+    /// Recipients of collected funds (shares must sum to 1)
+    pub recipients: Vec<RecipientMsg>,
     /// Stablecoin denomination
     pub stable_denom: String,
     /// Minimum number of seconds to allow bidding for
@@ -23,6 +63,86 @@ pub struct InstantiateMsg {
     pub transition_delay_secs: Timedelta,
     /// Number of seconds until a new bid can start
     pub bid_delay_secs: Timedelta,
+    /// Number of seconds after expiry where only the owner can renew
+    pub grace_period_secs: Timedelta,
+    /// Number of seconds of rate charged as fee for renewing in grace period
+    pub grace_fee_secs: Timedelta,
+    /// Share of collector-bound funds credited to the referrer (at most 1)
+    pub referral_share: Decimal,
+    /// Minimum rate allowed for bids
+    #[serde(default)]
+    pub min_rate: Uint128,
+    /// Number of seconds after the grace period until a name can be pruned
+    #[serde(default)]
+    pub retention_secs: Timedelta,
+    /// Reward paid from the keeper pool for each pruned name
+    #[serde(default)]
+    pub keeper_reward: Uint128,
+    /// Tax deducted from funds sent by the contract
+    #[serde(default)]
+    pub tax_policy: TaxPolicy,
+}
+
+/// Config of names under a TLD
+///
+/// The stablecoin denomination and tax policy are shared with the default
+/// namespace.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TldConfigMsg {
+    /// Recipients of collected funds (shares must sum to 1)
+    pub recipients: Vec<RecipientMsg>,
+    /// Minimum number of seconds to allow bidding for
+    pub min_lease_secs: Timedelta,
+    /// Maximum number of seconds to allow bidding for at once
+    pub max_lease_secs: Timedelta,
+    /// Number of seconds to allow counter-bids
+    pub counter_delay_secs: Timedelta,
+    /// Number of transition delay seconds after successful counter-bid
+    pub transition_delay_secs: Timedelta,
+    /// Number of seconds until a new bid can start
+    pub bid_delay_secs: Timedelta,
+    /// Number of seconds after expiry where only the owner can renew
+    pub grace_period_secs: Timedelta,
+    /// Number of seconds of rate charged as fee for renewing in grace period
+    pub grace_fee_secs: Timedelta,
+    /// Share of collector-bound funds credited to the referrer (at most 1)
+    pub referral_share: Decimal,
+    /// Minimum rate allowed for bids
+    pub min_rate: Uint128,
+    /// Number of seconds after the grace period until a name can be pruned
+    pub retention_secs: Timedelta,
+    /// Reward paid from the keeper pool for each pruned name
+    pub keeper_reward: Uint128,
+}
+
+/// How collected funds are delivered to a recipient
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RecipientKind {
+    /// Root collector contract called with `Deposit {}`
+    Deposit,
+    /// Plain bank send
+    Send,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RecipientMsg {
+    /// Address of the recipient
+    pub addr: String,
+    /// Share of collected funds
+    pub share: Decimal,
+    /// How funds are delivered
+    pub kind: RecipientKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Recipient {
+    /// Address of the recipient
+    pub addr: Addr,
+    /// Share of collected funds
+    pub share: Decimal,
+    /// How funds are delivered
+    pub kind: RecipientKind,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -33,12 +153,16 @@ pub enum ExecuteMsg {
         name: String,
         /// Amount of stablecoin to bid for full the full interval length
         rate: Uint128,
+        /// Frontend that referred the bid
+        referrer: Option<String>,
     },
     FundName {
         /// Name to fund
         name: String,
         /// Current owner (fails if this is not the owner)
         owner: String,
+        /// Frontend that referred the funding
+        referrer: Option<String>,
     },
     SetNameRate {
         /// Name to change rate of
@@ -55,8 +179,66 @@ pub enum ExecuteMsg {
     SetNameController {
         /// Name to set controller for
         name: String,
-        /// New controller (someone who can set values only)
-        controller: String,
+        /// New controller (someone who can set values only) or None to clear
+        controller: Option<String>,
+    },
+    GrantRole {
+        /// Name to grant role for
+        name: String,
+        /// Role to grant
+        role: Role,
+        /// Address to grant role to
+        address: String,
+    },
+    RevokeRole {
+        /// Name to revoke role for
+        name: String,
+        /// Role to revoke
+        role: Role,
+        /// Address to revoke role from
+        address: String,
+    },
+    ProposeTransfer {
+        /// Name to propose transfer of
+        name: String,
+        /// Destination that must accept the transfer
+        to: String,
+        /// Time when the proposal expires (no expiry if None)
+        expires: Option<Timestamp>,
+    },
+    AcceptTransfer {
+        /// Name to accept transfer of
+        name: String,
+    },
+    CancelTransfer {
+        /// Name to cancel pending transfer of
+        name: String,
+    },
+    WithdrawReferralBalance {
+        /// Address to withdraw to (sender if None)
+        to: Option<String>,
+    },
+    CreateTld {
+        /// TLD to create (without separator)
+        tld: String,
+        /// Config of names under the TLD
+        config: TldConfigMsg,
+    },
+    SetResolverContract {
+        /// Resolver to drop values of pruned names from (None to clear)
+        resolver_contract: Option<String>,
+    },
+    FundKeeperPool {},
+    PruneExpired {
+        /// Names to prune (names that cannot be pruned are skipped)
+        names: Vec<String>,
+    },
+    PruneExpiredBatch {
+        /// Start after the last name scanned by the previous batch (starts
+        /// over if that name was pruned)
+        start_after: Option<String>,
+        /// Number of expired names to scan in order of expire time
+        limit: Option<u32>,
     },
 }
 
@@ -74,15 +256,177 @@ pub enum QueryMsg {
         /// Number of values to return
         limit: Option<u32>,
     },
+    GetNameRoles {
+        /// Name to obtain roles for
+        name: String,
+    },
+    GetPendingTransfer {
+        /// Name to obtain pending transfer for
+        name: String,
+    },
+    ListNames {
+        /// Start after (for pagination)
+        start_after: Option<String>,
+        /// Number of values to return
+        limit: Option<u32>,
+        /// Order of names (ascending if None)
+        order: Option<ListOrder>,
+        /// Only return names in this status
+        status: Option<NameStatus>,
+        /// Only return names starting with this prefix
+        prefix: Option<String>,
+        /// Only return names with at least this rate
+        min_rate: Option<Uint128>,
+        /// Only return names owned by this address
+        owner: Option<String>,
+    },
+    Stats {},
+    GetReferralBalance {
+        /// Referrer to obtain balance for
+        address: String,
+    },
+    GetTldConfig {
+        /// TLD to obtain config for
+        tld: String,
+    },
+    ListTlds {
+        /// Start after (for pagination)
+        start_after: Option<String>,
+        /// Number of values to return
+        limit: Option<u32>,
+    },
+    GetKeeperPool {},
+    GetPermissions {
+        /// Name to obtain permissions for
+        name: String,
+        /// Address to obtain permissions for
+        address: String,
+    },
+}
+
+/// Action that an address can take on a name
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Place a bid with BidName
+    Bid,
+    /// Add to the deposit with FundName
+    Fund,
+    /// Renew the name in the grace period with FundName
+    Renew,
+    /// Change the rate with SetNameRate
+    SetRate,
+    /// Transfer the name with TransferNameOwner or ProposeTransfer
+    TransferNameOwner,
+    /// Transfer the highest bid with TransferNameOwner or ProposeTransfer
+    TransferBidOwner,
+    /// Set the controller with SetNameController and manage roles with
+    /// GrantRole and RevokeRole
+    SetController,
+    /// Remove the expired name with PruneExpired
+    Prune,
+}
+
+/// Order of listed names
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ListOrder {
+    Ascending,
+    Descending,
+}
+
+/// Status of the ownership of a name
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NameStatus {
+    /// Bid was placed and can be countered
+    CounterDelay,
+    /// Counter-bid won and the ownership is being transitioned
+    TransitionDelay,
+    /// Owned
+    Valid,
+    /// Expired but can still be renewed by the owner
+    Grace,
+    /// Expired and open for new bids
+    Expired,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct MigrateMsg {}
+pub struct MigrateMsg {
+    /// Admin allowed to create TLDs (unchanged if None)
+    #[serde(default)]
+    pub admin: Option<String>,
+    /// Tax policy of all namespaces (unchanged if None)
+    #[serde(default)]
+    pub tax_policy: Option<TaxPolicy>,
+    /// Maximum number of names to index when migrating from the bucket
+    /// layout (default 300)
+    ///
+    /// Migrate again to index the remaining names until the migrate event
+    /// reports that the migration is complete. Execute messages are
+    /// rejected until all names are indexed.
+    #[serde(default)]
+    pub index_limit: Option<u32>,
+}
+
+/// Role that the owner can delegate to other addresses
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Can set values in the resolver
+    Controller,
+    /// Can renew the name in the grace period
+    Funder,
+    /// Can change the rate of the name
+    RateManager,
+    /// Can transfer the name to a new owner
+    TransferAgent,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Role::Controller => "controller",
+            Role::Funder => "funder",
+            Role::RateManager => "rate_manager",
+            Role::TransferAgent => "transfer_agent",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoleGrant {
+    /// Role granted
+    pub role: Role,
+    /// Address holding the role
+    pub address: Addr,
+}
+
+/// Ownership that a pending transfer applies to
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferKind {
+    /// Ownership of the name
+    NameOwner,
+    /// Ownership of the highest bid during counter-delay
+    BidOwner,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
-    /// Collector of funds
-    pub collector_addr: Addr,
+    /// Admin allowed to create TLDs
+    pub admin: Option<Addr>,
+    /// Resolver that values of pruned names are dropped from
+    pub resolver_contract: Option<Addr>,
+    /// Recipients of collected funds
+    pub recipients: Vec<Recipient>,
     /// Stablecoin denomination
     pub stable_denom: String,
     /// Minimum number of seconds to allow bidding for
@@ -95,6 +439,25 @@ pub struct ConfigResponse {
     pub transition_delay_secs: Timedelta,
     /// Number of seconds until a new bid can start
     pub bid_delay_secs: Timedelta,
+    /// Number of seconds after expiry where only the owner can renew
+    pub grace_period_secs: Timedelta,
+    /// Number of seconds of rate charged as fee for renewing in grace period
+    pub grace_fee_secs: Timedelta,
+    /// Share of collector-bound funds credited to the referrer
+    pub referral_share: Decimal,
+    /// Minimum rate allowed for bids
+    pub min_rate: Uint128,
+    /// Number of seconds after the grace period until a name can be pruned
+    pub retention_secs: Timedelta,
+    /// Reward paid from the keeper pool for each pruned name
+    pub keeper_reward: Uint128,
+    /// Tax deducted from funds sent by the contract
+    pub tax_policy: TaxPolicy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TldsResponse {
+    pub tlds: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -104,7 +467,18 @@ pub struct NameStateResponse {
     /// Owner of the current highest bid
     pub bid_owner: Option<Addr>,
     /// Controller of the name
+    ///
+    /// This is the first address in the roles granted the controller role.
+    #[serde(default)]
     pub controller: Option<Addr>,
+    /// Roles delegated by the owner of the name
+    pub roles: Vec<RoleGrant>,
+    /// TLD of the name or None for names in the default namespace
+    ///
+    /// Names stored before TLDs existed are in the default namespace even if
+    /// they contain the separator.
+    #[serde(default)]
+    pub tld: Option<String>,
 
     /// Amount of stablecoin per RATE_SEC_DENOM charged
     pub rate: Uint128,
@@ -123,6 +497,22 @@ pub struct NameStateResponse {
     pub bid_delay_end: Timestamp,
     /// Expire timestamp
     pub expire_time: Option<Timestamp>,
+    /// Grace period end timestamp
+    pub grace_period_end: Option<Timestamp>,
+
+    /// Transition reference timestamp of the name owner
+    ///
+    /// Changes when the name is won by a new owner. Together with the name
+    /// owner it identifies the current ownership epoch of the name.
+    #[serde(default)]
+    pub transition_reference_time: Timestamp,
+}
+
+impl NameStateResponse {
+    /// Return whether address has been granted role for the name
+    pub fn has_role(&self, role: Role, address: &Addr) -> bool {
+        self.roles.iter().any(|grant| grant.role == role && &grant.address == address)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -136,26 +526,283 @@ pub struct AllNameStatesResponse {
     pub names: Vec<NameStateItem>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListNamesResponse {
+    pub names: Vec<NameStateItem>,
+    /// Name to continue listing after, or None if there are no more names
+    ///
+    /// This can be set even if fewer than limit names were returned since
+    /// the number of names scanned in one query is bounded.
+    pub next_start_after: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NameRolesResponse {
+    /// Owner of the name
+    pub name_owner: Option<Addr>,
+    /// Roles delegated by the owner of the name
+    pub roles: Vec<RoleGrant>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingTransferResponse {
+    /// Owner that proposed the transfer
+    pub from: Addr,
+    /// Destination that must accept the transfer
+    pub to: Addr,
+    /// Ownership that is transferred
+    pub kind: TransferKind,
+    /// Time when the proposal expires
+    pub expires: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatsResponse {
+    /// Number of names that have been bid on and not pruned
+    ///
+    /// Names that expired are counted until they are pruned.
+    pub names: Uint64,
+    /// Number of names that have not expired
+    pub owned_names: Uint64,
+    /// Number of names with a bid in the counter delay
+    pub counter_delay_names: Uint64,
+    /// Total number of bids
+    pub total_bids: Uint64,
+    /// Total number of bids that countered a bid in the counter delay since
+    /// the contract was created
+    pub total_counter_bids: Uint64,
+    /// Total number of times names were funded
+    pub total_fundings: Uint64,
+    /// Total number of rate changes
+    pub total_rate_changes: Uint64,
+    /// Total deposits received
+    pub total_deposits: Uint128,
+    /// Total deposits refunded to previous bidders
+    pub total_refunded: Uint128,
+    /// Total deposits sent to the recipients
+    pub total_collected: Uint128,
+    /// Total deposits credited to referrers
+    pub total_referral: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KeeperPoolResponse {
+    /// Balance available for keeper rewards
+    pub balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnavailablePermission {
+    /// Action that is currently unavailable
+    pub permission: Permission,
+    /// Time when the action becomes available if the name state is not
+    /// changed in the meantime (None if never)
+    pub available_time: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PermissionsResponse {
+    /// Status of the name (None if no bids have been placed)
+    pub status: Option<NameStatus>,
+    /// Actions that the address can take at the current time
+    pub allowed: Vec<Permission>,
+    /// Actions that the address cannot take at the current time
+    pub unavailable: Vec<UnavailablePermission>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralBalanceResponse {
+    /// Balance that the referrer can withdraw
+    pub balance: Uint128,
+}
+
+/// Split rate into the whole and the remaining part of RATE_SEC_DENOM
+///
+/// Multiplying the parts separately keeps the intermediate values within
+/// u128 whenever the final result fits.
+fn split_rate(rate: Uint128) -> (Uint128, u128) {
+    let denom = RATE_SEC_DENOM.value() as u128;
+    (Uint128::new(rate.u128() / denom), rate.u128() % denom)
+}
+
 /// Return deposit needed for seconds and rate rounded down.
 ///
-/// Rounded down to nearest raw unit (e.g. to 1 uusd NOT 1 whole usd).
-pub fn deposit_from_seconds_floor(seconds: Timedelta, rate: Uint128) -> Uint128 {
-    rate.multiply_ratio(seconds, RATE_SEC_DENOM)
+/// Rounded down to nearest raw unit (e.g. to 1 uusd NOT 1 whole usd). Returns
+/// an error if the deposit overflows.
+pub fn checked_deposit_from_seconds_floor(
+    seconds: Timedelta,
+    rate: Uint128,
+) -> Result<Uint128, OverflowError> {
+    let (whole, remainder) = split_rate(rate);
+    let seconds = u128::from(seconds);
+    // Remainder is less than RATE_SEC_DENOM so this cannot overflow
+    let partial = remainder * seconds / RATE_SEC_DENOM.value() as u128;
+    whole.checked_mul(Uint128::new(seconds))?.checked_add(Uint128::new(partial))
 }
 
 /// Return deposit needed for seconds and rate rounded up.
 ///
-/// Rounded up to nearest raw unit (e.g. to 1 uusd NOT 1 whole usd).
-pub fn deposit_from_seconds_ceil(seconds: Timedelta, rate: Uint128) -> Uint128 {
-    let a: u128 = (seconds.value() as u128) * rate.u128() + (RATE_SEC_DENOM.value() as u128) - 1;
-    Uint128::from(1u64).multiply_ratio(a, RATE_SEC_DENOM)
+/// Rounded up to nearest raw unit (e.g. to 1 uusd NOT 1 whole usd). Returns
+/// an error if the deposit overflows.
+pub fn checked_deposit_from_seconds_ceil(
+    seconds: Timedelta,
+    rate: Uint128,
+) -> Result<Uint128, OverflowError> {
+    let (whole, remainder) = split_rate(rate);
+    let seconds = u128::from(seconds);
+    let denom = RATE_SEC_DENOM.value() as u128;
+    // Remainder is less than RATE_SEC_DENOM so this cannot overflow
+    let partial = (remainder * seconds + denom - 1) / denom;
+    whole.checked_mul(Uint128::new(seconds))?.checked_add(Uint128::new(partial))
 }
 
 /// Return number of seconds corresponding to deposit and rate
-pub fn seconds_from_deposit(deposit: Uint128, rate: Uint128) -> Option<Timedelta> {
+///
+/// Returns None if the rate is zero and an error if the number of seconds
+/// does not fit in a Timedelta.
+pub fn checked_seconds_from_deposit(
+    deposit: Uint128,
+    rate: Uint128,
+) -> Result<Option<Timedelta>, OverflowError> {
     if rate.is_zero() {
-        None
-    } else {
-        Some(Timedelta::from_seconds(deposit.multiply_ratio(RATE_SEC_DENOM, rate).u128() as u64))
+        return Ok(None);
+    }
+
+    let denom = Uint128::from(RATE_SEC_DENOM.value());
+    let whole = Uint128::new(deposit.u128() / rate.u128());
+    let remainder = Uint128::new(deposit.u128() % rate.u128());
+    let seconds = whole.checked_mul(denom)?
+        .checked_add(Uint128::new(remainder.checked_mul(denom)?.u128() / rate.u128()))?;
+
+    match u64::try_from(seconds.u128()) {
+        Ok(seconds) => Ok(Some(Timedelta::from_seconds(seconds))),
+        Err(_) => Err(OverflowError::new(OverflowOperation::Mul, deposit, denom)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::from_slice;
+    use proptest::prelude::*;
+
+    #[test]
+    fn split_name_at_last_separator() {
+        assert_eq!(split_name("example"), ("example", None));
+        assert_eq!(split_name("example.ust"), ("example", Some("ust")));
+        assert_eq!(split_name("sub.example.dao"), ("sub.example", Some("dao")));
+        assert_eq!(split_name("example."), ("example", Some("")));
+    }
+
+    #[test]
+    fn name_validation() {
+        assert!(is_valid_name("example"));
+        assert!(is_valid_name("example.app"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name(".ust"));
+        assert!(!is_valid_name("example."));
+        assert!(!is_valid_name("sub.example.dao"));
+        assert!(!is_valid_name("example.UST"));
+        assert!(!is_valid_name("example.u-st"));
+    }
+
+    #[test]
+    fn deposit_from_seconds_rounding() {
+        let seconds = Timedelta::from_seconds(15_778_476);
+        let rate = Uint128::new(123);
+        assert_eq!(checked_deposit_from_seconds_floor(seconds, rate), Ok(Uint128::new(22_462)));
+        assert_eq!(checked_deposit_from_seconds_ceil(seconds, rate), Ok(Uint128::new(22_463)));
+        assert_eq!(
+            checked_seconds_from_deposit(Uint128::new(30_000), rate),
+            Ok(Some(Timedelta::from_seconds(21_073_170))),
+        );
+        assert_eq!(checked_seconds_from_deposit(Uint128::new(30_000), Uint128::zero()), Ok(None));
+    }
+
+    #[test]
+    fn time_fields_in_messages() {
+        let msg: ExecuteMsg = from_slice(
+            br#"{"propose_transfer":{"name":"example","to":"receiver","expires":"2021-08-01T00:00:00Z"}}"#,
+        ).unwrap();
+        let expected = ExecuteMsg::ProposeTransfer {
+            name: "example".into(),
+            to: "receiver".into(),
+            expires: Some(Timestamp::from_seconds(1_627_776_000)),
+        };
+        assert_eq!(msg, expected);
+        let msg: ExecuteMsg = from_slice(
+            br#"{"propose_transfer":{"name":"example","to":"receiver","expires":"1627776000"}}"#,
+        ).unwrap();
+        assert_eq!(msg, expected);
+
+        let config = |counter_delay: &str| format!(
+            r#"{{"recipients":[],"min_lease_secs":"182d","max_lease_secs":"1y","counter_delay_secs":{},"transition_delay_secs":"1w","bid_delay_secs":"0s","grace_period_secs":"30d","grace_fee_secs":"7d","referral_share":"0","min_rate":"0","retention_secs":"0","keeper_reward":"0"}}"#,
+            counter_delay,
+        );
+        let msg: TldConfigMsg = from_slice(config(r#""1h""#).as_bytes()).unwrap();
+        assert_eq!(msg.counter_delay_secs, Timedelta::HOUR);
+        assert_eq!(msg.max_lease_secs, Timedelta::YEAR);
+        assert_eq!(msg.transition_delay_secs, Timedelta::WEEK);
+        let msg: TldConfigMsg = from_slice(config(r#""3600""#).as_bytes()).unwrap();
+        assert_eq!(msg.counter_delay_secs, Timedelta::HOUR);
+
+        // The contract JSON deserializer cannot hand integers to types that
+        // also accept strings. Integers are rejected without panicking.
+        assert!(from_slice::<TldConfigMsg>(config("3600").as_bytes()).is_err());
+        assert!(from_slice::<ExecuteMsg>(
+            br#"{"propose_transfer":{"name":"example","to":"receiver","expires":1627776000}}"#,
+        ).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn deposit_near_max_rate_is_error(
+            rate in (u128::MAX - 1_000_000)..=u128::MAX,
+            seconds in (2 * RATE_SEC_DENOM.value())..=u64::MAX,
+        ) {
+            let seconds = Timedelta::from_seconds(seconds);
+            let rate = Uint128::new(rate);
+            prop_assert!(checked_deposit_from_seconds_floor(seconds, rate).is_err());
+            prop_assert!(checked_deposit_from_seconds_ceil(seconds, rate).is_err());
+        }
+
+        #[test]
+        fn deposit_ceil_is_at_most_one_above_floor(rate in any::<u128>(), seconds in any::<u64>()) {
+            let seconds = Timedelta::from_seconds(seconds);
+            let rate = Uint128::new(rate);
+            let floor = checked_deposit_from_seconds_floor(seconds, rate);
+            let ceil = checked_deposit_from_seconds_ceil(seconds, rate);
+            if let (Ok(floor), Ok(ceil)) = (floor, ceil) {
+                prop_assert!(floor <= ceil);
+                prop_assert!(ceil.u128() - floor.u128() <= 1);
+            }
+        }
+
+        #[test]
+        fn seconds_near_max_deposit_is_error(
+            deposit in (u128::MAX - 1_000_000)..=u128::MAX,
+            rate in 1u128..=1_000_000,
+        ) {
+            let deposit = Uint128::new(deposit);
+            let rate = Uint128::new(rate);
+            prop_assert!(checked_seconds_from_deposit(deposit, rate).is_err());
+        }
+
+        #[test]
+        fn seconds_from_deposit_never_panics(deposit in any::<u128>(), rate in any::<u128>()) {
+            let _ = checked_seconds_from_deposit(Uint128::new(deposit), Uint128::new(rate));
+        }
+
+        #[test]
+        fn seconds_from_floor_deposit_round_trip(
+            rate in 1u128..=u64::MAX as u128,
+            seconds in 0u64..=u32::MAX as u64,
+        ) {
+            let rate = Uint128::new(rate);
+            let deposit = checked_deposit_from_seconds_floor(Timedelta::from_seconds(seconds), rate)?;
+            let max_seconds = checked_seconds_from_deposit(deposit, rate)?;
+            prop_assert!(max_seconds <= Some(Timedelta::from_seconds(seconds)));
+        }
     }
 }
@@ -0,0 +1,1067 @@
+//! Events emitted by the Terranames contracts
+//!
+//! Every event has a fixed set of attributes regardless of which handler
+//! emitted it so that indexers can decode them without tracking the handler
+//! logic. Amounts are encoded as integers and timestamps as seconds. The chain
+//! rejects attributes with empty values so optional attributes are left out
+//! when there is no value. The parsers also accept empty values for optional
+//! attributes.
+//!
+//! The contracts emit events of type `terranames_<action>`. The chain reports
+//! these prefixed as `wasm-terranames_<action>` and adds the
+//! `_contract_address` attribute. The parsers accept both forms.
+
+use std::error::Error;
+use std::fmt;
+
+use cosmwasm_std::{Addr, Decimal, Event, Uint128};
+
+use crate::auction::Role;
+use crate::resolver::RecordKey;
+use crate::utils::Timestamp;
+
+/// Prefix of all event types emitted by the contracts
+pub const EVENT_TYPE_PREFIX: &str = "terranames_";
+
+/// Prefix added to contract events by the chain
+const WASM_EVENT_PREFIX: &str = "wasm-";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventParseError {
+    /// Event type is not one of the Terranames event types
+    UnknownType { ty: String },
+    /// Required attribute is missing
+    MissingAttribute { key: &'static str },
+    /// Attribute value could not be decoded
+    InvalidAttribute { key: &'static str, value: String },
+}
+
+impl fmt::Display for EventParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventParseError::UnknownType { ty } => {
+                write!(f, "Unknown event type: {}", ty)
+            },
+            EventParseError::MissingAttribute { key } => {
+                write!(f, "Missing event attribute: {}", key)
+            },
+            EventParseError::InvalidAttribute { key, value } => {
+                write!(f, "Invalid event attribute {}: {}", key, value)
+            },
+        }
+    }
+}
+
+impl Error for EventParseError {}
+
+type ParseResult<T> = Result<T, EventParseError>;
+
+/// Return the action of a Terranames event type
+///
+/// Accepts the type both with and without the prefix added by the chain.
+fn event_action(ty: &str) -> Option<&str> {
+    ty.strip_prefix(WASM_EVENT_PREFIX)
+        .unwrap_or(ty)
+        .strip_prefix(EVENT_TYPE_PREFIX)
+}
+
+/// Reader for decoding event attributes
+struct Attributes<'a>(&'a Event);
+
+impl<'a> Attributes<'a> {
+    fn get(&self, key: &'static str) -> ParseResult<&'a str> {
+        self.0.attributes.iter()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.as_str())
+            .ok_or(EventParseError::MissingAttribute { key })
+    }
+
+    /// Get value of optional attribute, treating empty values as missing
+    fn get_optional(&self, key: &'static str) -> Option<&'a str> {
+        self.get(key).ok().filter(|value| !value.is_empty())
+    }
+
+    fn invalid(key: &'static str, value: &str) -> EventParseError {
+        EventParseError::InvalidAttribute { key, value: value.into() }
+    }
+
+    fn string(&self, key: &'static str) -> ParseResult<String> {
+        self.get(key).map(String::from)
+    }
+
+    fn addr(&self, key: &'static str) -> ParseResult<Addr> {
+        match self.get(key)? {
+            "" => Err(EventParseError::MissingAttribute { key }),
+            value => Ok(Addr::unchecked(value)),
+        }
+    }
+
+    fn option_addr(&self, key: &'static str) -> ParseResult<Option<Addr>> {
+        Ok(self.get_optional(key).map(Addr::unchecked))
+    }
+
+    fn option_string(&self, key: &'static str) -> ParseResult<Option<String>> {
+        Ok(self.get_optional(key).map(String::from))
+    }
+
+    fn uint64(&self, key: &'static str) -> ParseResult<u64> {
+        let value = self.get(key)?;
+        value.parse().map_err(|_| Self::invalid(key, value))
+    }
+
+    fn uint128(&self, key: &'static str) -> ParseResult<Uint128> {
+        let value = self.get(key)?;
+        value.parse::<u128>()
+            .map(Uint128::new)
+            .map_err(|_| Self::invalid(key, value))
+    }
+
+    fn decimal(&self, key: &'static str) -> ParseResult<Decimal> {
+        let value = self.get(key)?;
+        value.parse().map_err(|_| Self::invalid(key, value))
+    }
+
+    fn timestamp(&self, key: &'static str) -> ParseResult<Timestamp> {
+        let value = self.get(key)?;
+        value.parse()
+            .map(Timestamp::from_seconds)
+            .map_err(|_| Self::invalid(key, value))
+    }
+
+    fn option_timestamp(&self, key: &'static str) -> ParseResult<Option<Timestamp>> {
+        match self.get_optional(key) {
+            None => Ok(None),
+            Some(_) => self.timestamp(key).map(Some),
+        }
+    }
+
+    fn bool(&self, key: &'static str) -> ParseResult<bool> {
+        let value = self.get(key)?;
+        value.parse().map_err(|_| Self::invalid(key, value))
+    }
+
+    fn record_key(&self, key: &'static str) -> ParseResult<RecordKey> {
+        let value = self.get(key)?;
+        value.parse().map_err(|_| Self::invalid(key, value))
+    }
+
+    fn option_role(&self, key: &'static str) -> ParseResult<Option<Role>> {
+        let value = match self.get_optional(key) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        match value {
+            "controller" => Ok(Some(Role::Controller)),
+            "funder" => Ok(Some(Role::Funder)),
+            "rate_manager" => Ok(Some(Role::RateManager)),
+            "transfer_agent" => Ok(Some(Role::TransferAgent)),
+            _ => Err(Self::invalid(key, value)),
+        }
+    }
+}
+
+/// Remove attributes with empty values since the chain rejects those
+fn without_empty_values(mut event: Event) -> Event {
+    event.attributes.retain(|attr| !attr.value.is_empty());
+    event
+}
+
+fn option_to_string<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn timestamp_to_string(value: Timestamp) -> String {
+    value.value().to_string()
+}
+
+fn option_timestamp_to_string(value: Option<Timestamp>) -> String {
+    value.map(timestamp_to_string).unwrap_or_default()
+}
+
+/// Action that caused a name event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NameAction {
+    Bid,
+    Fund,
+    SetRate,
+    Transfer,
+    ProposeTransfer,
+    CancelTransfer,
+    SetController,
+    GrantRole,
+    RevokeRole,
+    Prune,
+}
+
+impl NameAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NameAction::Bid => "bid",
+            NameAction::Fund => "fund",
+            NameAction::SetRate => "set_rate",
+            NameAction::Transfer => "transfer",
+            NameAction::ProposeTransfer => "propose_transfer",
+            NameAction::CancelTransfer => "cancel_transfer",
+            NameAction::SetController => "set_controller",
+            NameAction::GrantRole => "grant_role",
+            NameAction::RevokeRole => "revoke_role",
+            NameAction::Prune => "prune",
+        }
+    }
+
+    fn from_action(action: &str) -> Option<NameAction> {
+        Some(match action {
+            "bid" => NameAction::Bid,
+            "fund" => NameAction::Fund,
+            "set_rate" => NameAction::SetRate,
+            "transfer" => NameAction::Transfer,
+            "propose_transfer" => NameAction::ProposeTransfer,
+            "cancel_transfer" => NameAction::CancelTransfer,
+            "set_controller" => NameAction::SetController,
+            "grant_role" => NameAction::GrantRole,
+            "revoke_role" => NameAction::RevokeRole,
+            "prune" => NameAction::Prune,
+            _ => return None,
+        })
+    }
+}
+
+/// Event emitted by the auction contract when the state of a name changes
+///
+/// Attributes:
+/// - `name`: Name that changed
+/// - `old_owner`: Holder of the affected ownership before the action (the
+///   highest bid, or the name when transferring during counter-delay)
+/// - `new_owner`: Holder of the affected ownership after the action
+/// - `rate`: Rate of the name after the action
+/// - `begin_time`: Begin time of the current bid after the action
+/// - `expire_time`: Expire time after the action (omitted if never expiring)
+/// - `deposit`: Amount received from the sender
+/// - `refund`: Amount refunded to the previous bidder
+/// - `collected`: Amount sent to the revenue recipients
+/// - `referrer`: Referrer credited for the action (omitted if none)
+/// - `referral`: Amount credited to the referrer
+/// - `fee`: Amount charged as fee (included in collected and referral)
+/// - `role`: Role granted or revoked (omitted unless a role changed)
+/// - `address`: Address the role or proposed transfer applies to, or the
+///   keeper that pruned the name (omitted if not applicable)
+#[derive(Clone, Debug, PartialEq)]
+pub struct NameEvent {
+    pub action: NameAction,
+    pub name: String,
+    pub old_owner: Option<Addr>,
+    pub new_owner: Option<Addr>,
+    pub rate: Uint128,
+    pub begin_time: Timestamp,
+    pub expire_time: Option<Timestamp>,
+    pub deposit: Uint128,
+    pub refund: Uint128,
+    pub collected: Uint128,
+    pub referrer: Option<Addr>,
+    pub referral: Uint128,
+    pub fee: Uint128,
+    pub role: Option<Role>,
+    pub address: Option<Addr>,
+}
+
+impl NameEvent {
+    /// Create event with no owners and zero amounts
+    pub fn new(action: NameAction, name: impl Into<String>) -> Self {
+        NameEvent {
+            action,
+            name: name.into(),
+            old_owner: None,
+            new_owner: None,
+            rate: Uint128::zero(),
+            begin_time: Timestamp::zero(),
+            expire_time: None,
+            deposit: Uint128::zero(),
+            refund: Uint128::zero(),
+            collected: Uint128::zero(),
+            referrer: None,
+            referral: Uint128::zero(),
+            fee: Uint128::zero(),
+            role: None,
+            address: None,
+        }
+    }
+
+    pub fn to_event(&self) -> Event {
+        without_empty_values(
+            Event::new(format!("{}{}", EVENT_TYPE_PREFIX, self.action.as_str()))
+                .add_attribute("name", &self.name)
+                .add_attribute("old_owner", option_to_string(&self.old_owner))
+                .add_attribute("new_owner", option_to_string(&self.new_owner))
+                .add_attribute("rate", self.rate)
+                .add_attribute("begin_time", timestamp_to_string(self.begin_time))
+                .add_attribute("expire_time", option_timestamp_to_string(self.expire_time))
+                .add_attribute("deposit", self.deposit)
+                .add_attribute("refund", self.refund)
+                .add_attribute("collected", self.collected)
+                .add_attribute("referrer", option_to_string(&self.referrer))
+                .add_attribute("referral", self.referral)
+                .add_attribute("fee", self.fee)
+                .add_attribute("role", option_to_string(&self.role))
+                .add_attribute("address", option_to_string(&self.address))
+        )
+    }
+
+    pub fn parse(event: &Event) -> ParseResult<Self> {
+        let action = event_action(&event.ty)
+            .and_then(NameAction::from_action)
+            .ok_or_else(|| EventParseError::UnknownType { ty: event.ty.clone() })?;
+        let attrs = Attributes(event);
+
+        Ok(NameEvent {
+            action,
+            name: attrs.string("name")?,
+            old_owner: attrs.option_addr("old_owner")?,
+            new_owner: attrs.option_addr("new_owner")?,
+            rate: attrs.uint128("rate")?,
+            begin_time: attrs.timestamp("begin_time")?,
+            expire_time: attrs.option_timestamp("expire_time")?,
+            deposit: attrs.uint128("deposit")?,
+            refund: attrs.uint128("refund")?,
+            collected: attrs.uint128("collected")?,
+            referrer: attrs.option_addr("referrer")?,
+            referral: attrs.uint128("referral")?,
+            fee: attrs.uint128("fee")?,
+            role: attrs.option_role("role")?,
+            address: attrs.option_addr("address")?,
+        })
+    }
+}
+
+impl From<NameEvent> for Event {
+    fn from(event: NameEvent) -> Event {
+        event.to_event()
+    }
+}
+
+/// Event emitted by the resolver contract when a value is set
+///
+/// Event type is `terranames_set_value`.
+///
+/// Attributes:
+/// - `name`: Name that the value was set for
+/// - `owner`: Owner of the name (omitted if not owned)
+/// - `controller`: Controller that set the value (the auction contract when
+///   the value was dropped because the name was pruned)
+/// - `value`: Value that was set (omitted if deleted or empty)
+/// - `deleted`: Whether the value was deleted (`true` or `false`)
+/// - `expire_time`: Expire time of the name (omitted if never expiring)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueEvent {
+    pub name: String,
+    pub owner: Option<Addr>,
+    pub controller: Addr,
+    pub value: Option<String>,
+    pub expire_time: Option<Timestamp>,
+}
+
+impl ValueEvent {
+    const ACTION: &'static str = "set_value";
+
+    pub fn to_event(&self) -> Event {
+        without_empty_values(
+            Event::new(format!("{}{}", EVENT_TYPE_PREFIX, Self::ACTION))
+                .add_attribute("name", &self.name)
+                .add_attribute("owner", option_to_string(&self.owner))
+                .add_attribute("controller", &self.controller)
+                .add_attribute("value", self.value.clone().unwrap_or_default())
+                .add_attribute("deleted", self.value.is_none().to_string())
+                .add_attribute("expire_time", option_timestamp_to_string(self.expire_time))
+        )
+    }
+
+    pub fn parse(event: &Event) -> ParseResult<Self> {
+        if event_action(&event.ty) != Some(Self::ACTION) {
+            return Err(EventParseError::UnknownType { ty: event.ty.clone() });
+        }
+        let attrs = Attributes(event);
+
+        let value = if attrs.bool("deleted")? {
+            None
+        } else {
+            Some(attrs.get_optional("value").unwrap_or_default().into())
+        };
+
+        Ok(ValueEvent {
+            name: attrs.string("name")?,
+            owner: attrs.option_addr("owner")?,
+            controller: attrs.addr("controller")?,
+            value,
+            expire_time: attrs.option_timestamp("expire_time")?,
+        })
+    }
+}
+
+impl From<ValueEvent> for Event {
+    fn from(event: ValueEvent) -> Event {
+        event.to_event()
+    }
+}
+
+/// Event emitted by the resolver contract when a record other than the
+/// default record is set
+///
+/// Event type is `terranames_set_record`. The default record is reported by
+/// `terranames_set_value`.
+///
+/// Attributes:
+/// - `name`: Name that the record was set for
+/// - `owner`: Owner of the name (omitted if not owned)
+/// - `controller`: Controller that set the record (the auction contract when
+///   the record was dropped because the name was pruned)
+/// - `key`: Key of the record, e.g. `address:terra` or `text:email`
+/// - `value`: Value that was set (omitted if deleted or empty)
+/// - `deleted`: Whether the record was deleted (`true` or `false`)
+/// - `expire_time`: Expire time of the name (omitted if never expiring)
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordEvent {
+    pub name: String,
+    pub owner: Option<Addr>,
+    pub controller: Addr,
+    pub key: RecordKey,
+    pub value: Option<String>,
+    pub expire_time: Option<Timestamp>,
+}
+
+impl RecordEvent {
+    const ACTION: &'static str = "set_record";
+
+    pub fn to_event(&self) -> Event {
+        without_empty_values(
+            Event::new(format!("{}{}", EVENT_TYPE_PREFIX, Self::ACTION))
+                .add_attribute("name", &self.name)
+                .add_attribute("owner", option_to_string(&self.owner))
+                .add_attribute("controller", &self.controller)
+                .add_attribute("key", self.key.to_string())
+                .add_attribute("value", self.value.clone().unwrap_or_default())
+                .add_attribute("deleted", self.value.is_none().to_string())
+                .add_attribute("expire_time", option_timestamp_to_string(self.expire_time))
+        )
+    }
+
+    pub fn parse(event: &Event) -> ParseResult<Self> {
+        if event_action(&event.ty) != Some(Self::ACTION) {
+            return Err(EventParseError::UnknownType { ty: event.ty.clone() });
+        }
+        let attrs = Attributes(event);
+
+        let value = if attrs.bool("deleted")? {
+            None
+        } else {
+            Some(attrs.get_optional("value").unwrap_or_default().into())
+        };
+
+        Ok(RecordEvent {
+            name: attrs.string("name")?,
+            owner: attrs.option_addr("owner")?,
+            controller: attrs.addr("controller")?,
+            key: attrs.record_key("key")?,
+            value,
+            expire_time: attrs.option_timestamp("expire_time")?,
+        })
+    }
+}
+
+impl From<RecordEvent> for Event {
+    fn from(event: RecordEvent) -> Event {
+        event.to_event()
+    }
+}
+
+/// Event emitted by the auction contract when a referral balance is withdrawn
+///
+/// Event type is `terranames_withdraw_referral_balance`.
+///
+/// Attributes:
+/// - `referrer`: Referrer that withdrew the balance
+/// - `recipient`: Address that received the funds
+/// - `amount`: Amount withdrawn
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReferralEvent {
+    pub referrer: Addr,
+    pub recipient: Addr,
+    pub amount: Uint128,
+}
+
+impl ReferralEvent {
+    const ACTION: &'static str = "withdraw_referral_balance";
+
+    pub fn to_event(&self) -> Event {
+        Event::new(format!("{}{}", EVENT_TYPE_PREFIX, Self::ACTION))
+            .add_attribute("referrer", &self.referrer)
+            .add_attribute("recipient", &self.recipient)
+            .add_attribute("amount", self.amount)
+    }
+
+    pub fn parse(event: &Event) -> ParseResult<Self> {
+        if event_action(&event.ty) != Some(Self::ACTION) {
+            return Err(EventParseError::UnknownType { ty: event.ty.clone() });
+        }
+        let attrs = Attributes(event);
+
+        Ok(ReferralEvent {
+            referrer: attrs.addr("referrer")?,
+            recipient: attrs.addr("recipient")?,
+            amount: attrs.uint128("amount")?,
+        })
+    }
+}
+
+impl From<ReferralEvent> for Event {
+    fn from(event: ReferralEvent) -> Event {
+        event.to_event()
+    }
+}
+
+/// Action that caused an admin event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AdminAction {
+    CreateTld,
+    SetResolverContract,
+}
+
+impl AdminAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminAction::CreateTld => "create_tld",
+            AdminAction::SetResolverContract => "set_resolver_contract",
+        }
+    }
+
+    fn from_action(action: &str) -> Option<AdminAction> {
+        Some(match action {
+            "create_tld" => AdminAction::CreateTld,
+            "set_resolver_contract" => AdminAction::SetResolverContract,
+            _ => return None,
+        })
+    }
+}
+
+/// Event emitted by the auction contract for actions of the admin
+///
+/// Attributes:
+/// - `admin`: Admin that performed the action
+/// - `tld`: TLD that was created (omitted unless a TLD was created)
+/// - `resolver_contract`: Resolver contract that was set (omitted if the
+///   resolver contract was cleared or not applicable)
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdminEvent {
+    pub action: AdminAction,
+    pub admin: Addr,
+    pub tld: Option<String>,
+    pub resolver_contract: Option<Addr>,
+}
+
+impl AdminEvent {
+    pub fn to_event(&self) -> Event {
+        without_empty_values(
+            Event::new(format!("{}{}", EVENT_TYPE_PREFIX, self.action.as_str()))
+                .add_attribute("admin", &self.admin)
+                .add_attribute("tld", self.tld.clone().unwrap_or_default())
+                .add_attribute("resolver_contract", option_to_string(&self.resolver_contract))
+        )
+    }
+
+    pub fn parse(event: &Event) -> ParseResult<Self> {
+        let action = event_action(&event.ty)
+            .and_then(AdminAction::from_action)
+            .ok_or_else(|| EventParseError::UnknownType { ty: event.ty.clone() })?;
+        let attrs = Attributes(event);
+
+        Ok(AdminEvent {
+            action,
+            admin: attrs.addr("admin")?,
+            tld: attrs.option_string("tld")?,
+            resolver_contract: attrs.option_addr("resolver_contract")?,
+        })
+    }
+}
+
+impl From<AdminEvent> for Event {
+    fn from(event: AdminEvent) -> Event {
+        event.to_event()
+    }
+}
+
+/// Action that caused a keeper event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeeperAction {
+    FundKeeperPool,
+    PruneExpired,
+}
+
+impl KeeperAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeeperAction::FundKeeperPool => "fund_keeper_pool",
+            KeeperAction::PruneExpired => "prune_expired",
+        }
+    }
+
+    fn from_action(action: &str) -> Option<KeeperAction> {
+        Some(match action {
+            "fund_keeper_pool" => KeeperAction::FundKeeperPool,
+            "prune_expired" => KeeperAction::PruneExpired,
+            _ => return None,
+        })
+    }
+}
+
+/// Event emitted by the auction contract when the keeper pool is funded or
+/// expired names are pruned
+///
+/// Each pruned name is also reported in a name event.
+///
+/// Attributes:
+/// - `address`: Address that funded the pool, or the keeper that pruned
+/// - `amount`: Amount added to the pool, or the reward paid to the keeper
+/// - `balance`: Balance of the keeper pool after the action
+/// - `pruned`: Number of names pruned
+/// - `last_scanned`: Last name scanned when pruning a batch of names
+///   (omitted if not applicable)
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeeperEvent {
+    pub action: KeeperAction,
+    pub address: Addr,
+    pub amount: Uint128,
+    pub balance: Uint128,
+    pub pruned: u64,
+    pub last_scanned: Option<String>,
+}
+
+impl KeeperEvent {
+    pub fn to_event(&self) -> Event {
+        without_empty_values(
+            Event::new(format!("{}{}", EVENT_TYPE_PREFIX, self.action.as_str()))
+                .add_attribute("address", &self.address)
+                .add_attribute("amount", self.amount)
+                .add_attribute("balance", self.balance)
+                .add_attribute("pruned", self.pruned.to_string())
+                .add_attribute("last_scanned", self.last_scanned.clone().unwrap_or_default())
+        )
+    }
+
+    pub fn parse(event: &Event) -> ParseResult<Self> {
+        let action = event_action(&event.ty)
+            .and_then(KeeperAction::from_action)
+            .ok_or_else(|| EventParseError::UnknownType { ty: event.ty.clone() })?;
+        let attrs = Attributes(event);
+
+        Ok(KeeperEvent {
+            action,
+            address: attrs.addr("address")?,
+            amount: attrs.uint128("amount")?,
+            balance: attrs.uint128("balance")?,
+            pruned: attrs.uint64("pruned")?,
+            last_scanned: attrs.option_string("last_scanned")?,
+        })
+    }
+}
+
+impl From<KeeperEvent> for Event {
+    fn from(event: KeeperEvent) -> Event {
+        event.to_event()
+    }
+}
+
+/// Action that caused a collector event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CollectorAction {
+    Deposit,
+    Stake,
+    UnstakeTokens,
+    WithdrawTokens,
+    WithdrawDividends,
+}
+
+impl CollectorAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CollectorAction::Deposit => "deposit",
+            CollectorAction::Stake => "stake",
+            CollectorAction::UnstakeTokens => "unstake_tokens",
+            CollectorAction::WithdrawTokens => "withdraw_tokens",
+            CollectorAction::WithdrawDividends => "withdraw_dividends",
+        }
+    }
+
+    fn from_action(action: &str) -> Option<CollectorAction> {
+        Some(match action {
+            "deposit" => CollectorAction::Deposit,
+            "stake" => CollectorAction::Stake,
+            "unstake_tokens" => CollectorAction::UnstakeTokens,
+            "withdraw_tokens" => CollectorAction::WithdrawTokens,
+            "withdraw_dividends" => CollectorAction::WithdrawDividends,
+            _ => return None,
+        })
+    }
+}
+
+/// Event emitted by the root collector contract
+///
+/// Attributes:
+/// - `address`: Address that performed the action (the depositor, or the
+///   staker)
+/// - `recipient`: Address that received funds or tokens (omitted if nothing
+///   was sent)
+/// - `amount`: Amount moved (stablecoins for deposits and dividends, tokens
+///   otherwise)
+/// - `staked_amount`: Tokens staked by address after the action (total
+///   staked for deposits)
+/// - `unstaked_amount`: Tokens unstaked by address and ready for withdrawal
+/// - `multiplier`: Dividend multiplier after the action
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollectorEvent {
+    pub action: CollectorAction,
+    pub address: Addr,
+    pub recipient: Option<Addr>,
+    pub amount: Uint128,
+    pub staked_amount: Uint128,
+    pub unstaked_amount: Uint128,
+    pub multiplier: Decimal,
+}
+
+impl CollectorEvent {
+    pub fn to_event(&self) -> Event {
+        without_empty_values(
+            Event::new(format!("{}{}", EVENT_TYPE_PREFIX, self.action.as_str()))
+                .add_attribute("address", &self.address)
+                .add_attribute("recipient", option_to_string(&self.recipient))
+                .add_attribute("amount", self.amount)
+                .add_attribute("staked_amount", self.staked_amount)
+                .add_attribute("unstaked_amount", self.unstaked_amount)
+                .add_attribute("multiplier", self.multiplier.to_string())
+        )
+    }
+
+    pub fn parse(event: &Event) -> ParseResult<Self> {
+        let action = event_action(&event.ty)
+            .and_then(CollectorAction::from_action)
+            .ok_or_else(|| EventParseError::UnknownType { ty: event.ty.clone() })?;
+        let attrs = Attributes(event);
+
+        Ok(CollectorEvent {
+            action,
+            address: attrs.addr("address")?,
+            recipient: attrs.option_addr("recipient")?,
+            amount: attrs.uint128("amount")?,
+            staked_amount: attrs.uint128("staked_amount")?,
+            unstaked_amount: attrs.uint128("unstaked_amount")?,
+            multiplier: attrs.decimal("multiplier")?,
+        })
+    }
+}
+
+impl From<CollectorEvent> for Event {
+    fn from(event: CollectorEvent) -> Event {
+        event.to_event()
+    }
+}
+
+/// Event emitted by the contracts when migrated
+///
+/// Event type is `terranames_migrate`. Migrations that update stored entries
+/// in batches are repeated until the event reports that the migration is
+/// complete.
+///
+/// Attributes:
+/// - `migrated`: Number of entries migrated by this call
+/// - `complete`: Whether the migration is complete
+#[derive(Clone, Debug, PartialEq)]
+pub struct MigrateEvent {
+    pub migrated: u64,
+    pub complete: bool,
+}
+
+impl MigrateEvent {
+    const ACTION: &'static str = "migrate";
+
+    pub fn to_event(&self) -> Event {
+        Event::new(format!("{}{}", EVENT_TYPE_PREFIX, Self::ACTION))
+            .add_attribute("migrated", self.migrated.to_string())
+            .add_attribute("complete", self.complete.to_string())
+    }
+
+    pub fn parse(event: &Event) -> ParseResult<Self> {
+        if event_action(&event.ty) != Some(Self::ACTION) {
+            return Err(EventParseError::UnknownType { ty: event.ty.clone() });
+        }
+        let attrs = Attributes(event);
+
+        Ok(MigrateEvent {
+            migrated: attrs.uint64("migrated")?,
+            complete: attrs.bool("complete")?,
+        })
+    }
+}
+
+impl From<MigrateEvent> for Event {
+    fn from(event: MigrateEvent) -> Event {
+        event.to_event()
+    }
+}
+
+/// Any event emitted by the Terranames contracts
+#[derive(Clone, Debug, PartialEq)]
+pub enum TerranamesEvent {
+    Name(NameEvent),
+    Referral(ReferralEvent),
+    Admin(AdminEvent),
+    Keeper(KeeperEvent),
+    Value(ValueEvent),
+    Record(RecordEvent),
+    Collector(CollectorEvent),
+    Migrate(MigrateEvent),
+}
+
+impl TerranamesEvent {
+    /// Parse event emitted by any of the contracts
+    ///
+    /// Returns UnknownType for events not emitted by the Terranames contracts
+    /// so that indexers can skip those.
+    pub fn parse(event: &Event) -> ParseResult<Self> {
+        let action = event_action(&event.ty)
+            .ok_or_else(|| EventParseError::UnknownType { ty: event.ty.clone() })?;
+
+        if NameAction::from_action(action).is_some() {
+            NameEvent::parse(event).map(TerranamesEvent::Name)
+        } else if AdminAction::from_action(action).is_some() {
+            AdminEvent::parse(event).map(TerranamesEvent::Admin)
+        } else if KeeperAction::from_action(action).is_some() {
+            KeeperEvent::parse(event).map(TerranamesEvent::Keeper)
+        } else if CollectorAction::from_action(action).is_some() {
+            CollectorEvent::parse(event).map(TerranamesEvent::Collector)
+        } else if action == ValueEvent::ACTION {
+            ValueEvent::parse(event).map(TerranamesEvent::Value)
+        } else if action == RecordEvent::ACTION {
+            RecordEvent::parse(event).map(TerranamesEvent::Record)
+        } else if action == ReferralEvent::ACTION {
+            ReferralEvent::parse(event).map(TerranamesEvent::Referral)
+        } else if action == MigrateEvent::ACTION {
+            MigrateEvent::parse(event).map(TerranamesEvent::Migrate)
+        } else {
+            Err(EventParseError::UnknownType { ty: event.ty.clone() })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::Attribute;
+
+    #[test]
+    fn name_event_round_trip() {
+        let event = NameEvent {
+            old_owner: Some(Addr::unchecked("bidder_1")),
+            new_owner: Some(Addr::unchecked("bidder_2")),
+            rate: Uint128::new(124),
+            begin_time: Timestamp::from_seconds(1234),
+            expire_time: Some(Timestamp::from_seconds(20_904_459)),
+            deposit: Uint128::new(30_000),
+            refund: Uint128::new(1_000),
+            collected: Uint128::new(26_100),
+            referrer: Some(Addr::unchecked("frontend")),
+            referral: Uint128::new(2_900),
+            ..NameEvent::new(NameAction::Bid, "example")
+        };
+
+        let mut emitted = event.to_event();
+        assert_eq!(emitted.ty, "terranames_bid");
+
+        // Chain prefixes the type and adds the contract address
+        emitted.ty = format!("wasm-{}", emitted.ty);
+        emitted.attributes.insert(0, Attribute {
+            key: "_contract_address".into(),
+            value: "auction".into(),
+        });
+
+        assert_eq!(NameEvent::parse(&emitted), Ok(event.clone()));
+        assert_eq!(TerranamesEvent::parse(&emitted), Ok(TerranamesEvent::Name(event)));
+    }
+
+    #[test]
+    fn value_event_round_trip() {
+        let event = ValueEvent {
+            name: "example".into(),
+            owner: Some(Addr::unchecked("owner")),
+            controller: Addr::unchecked("controller"),
+            value: Some("".into()),
+            expire_time: None,
+        };
+        assert_eq!(ValueEvent::parse(&event.to_event()), Ok(event));
+
+        let deleted = ValueEvent {
+            name: "example".into(),
+            owner: None,
+            controller: Addr::unchecked("controller"),
+            value: None,
+            expire_time: Some(Timestamp::from_seconds(5000)),
+        };
+        assert_eq!(
+            TerranamesEvent::parse(&deleted.to_event()),
+            Ok(TerranamesEvent::Value(deleted)),
+        );
+    }
+
+    #[test]
+    fn record_event_round_trip() {
+        let event = RecordEvent {
+            name: "example".into(),
+            owner: Some(Addr::unchecked("owner")),
+            controller: Addr::unchecked("controller"),
+            key: RecordKey::Address { chain: "terra".into() },
+            value: Some("terra1address".into()),
+            expire_time: Some(Timestamp::from_seconds(5000)),
+        };
+        assert_eq!(
+            event.to_event().attributes.iter().find(|attr| attr.key == "key").unwrap().value,
+            "address:terra",
+        );
+        assert_eq!(
+            TerranamesEvent::parse(&event.to_event()),
+            Ok(TerranamesEvent::Record(event)),
+        );
+
+        let deleted = RecordEvent {
+            name: "example".into(),
+            owner: None,
+            controller: Addr::unchecked("auction"),
+            key: RecordKey::ContentHash {},
+            value: None,
+            expire_time: None,
+        };
+        assert_eq!(RecordEvent::parse(&deleted.to_event()), Ok(deleted.clone()));
+
+        let mut invalid = deleted.to_event();
+        for attr in invalid.attributes.iter_mut().filter(|attr| attr.key == "key") {
+            attr.value = "other".into();
+        }
+        assert_eq!(
+            RecordEvent::parse(&invalid),
+            Err(EventParseError::InvalidAttribute { key: "key", value: "other".into() }),
+        );
+    }
+
+    #[test]
+    fn optional_attributes_are_omitted() {
+        let event = NameEvent::new(NameAction::Fund, "example").to_event();
+        assert!(event.attributes.iter().all(|attr| !attr.value.is_empty()));
+        assert!(!event.attributes.iter().any(|attr| attr.key == "old_owner"));
+
+        // Empty values emitted by older contracts are also decoded as missing
+        let event = event.add_attribute("old_owner", "").add_attribute("role", "");
+        assert_eq!(
+            NameEvent::parse(&event),
+            Ok(NameEvent::new(NameAction::Fund, "example")),
+        );
+    }
+
+    #[test]
+    fn collector_event_round_trip() {
+        let event = CollectorEvent {
+            action: CollectorAction::WithdrawDividends,
+            address: Addr::unchecked("staker"),
+            recipient: Some(Addr::unchecked("receiver")),
+            amount: Uint128::new(500),
+            staked_amount: Uint128::new(1_000),
+            unstaked_amount: Uint128::zero(),
+            multiplier: Decimal::percent(150),
+        };
+        assert_eq!(CollectorEvent::parse(&event.to_event()), Ok(event));
+    }
+
+    #[test]
+    fn referral_event_round_trip() {
+        let event = ReferralEvent {
+            referrer: Addr::unchecked("frontend"),
+            recipient: Addr::unchecked("treasury"),
+            amount: Uint128::new(2_900),
+        };
+        assert_eq!(
+            TerranamesEvent::parse(&event.to_event()),
+            Ok(TerranamesEvent::Referral(event)),
+        );
+    }
+
+    #[test]
+    fn admin_event_round_trip() {
+        let event = AdminEvent {
+            action: AdminAction::CreateTld,
+            admin: Addr::unchecked("admin"),
+            tld: Some("ust".into()),
+            resolver_contract: None,
+        };
+        assert_eq!(event.to_event().ty, "terranames_create_tld");
+        assert_eq!(
+            TerranamesEvent::parse(&event.to_event()),
+            Ok(TerranamesEvent::Admin(event)),
+        );
+
+        let event = AdminEvent {
+            action: AdminAction::SetResolverContract,
+            admin: Addr::unchecked("admin"),
+            tld: None,
+            resolver_contract: None,
+        };
+        assert_eq!(AdminEvent::parse(&event.to_event()), Ok(event));
+    }
+
+    #[test]
+    fn keeper_event_round_trip() {
+        let event = KeeperEvent {
+            action: KeeperAction::PruneExpired,
+            address: Addr::unchecked("keeper"),
+            amount: Uint128::new(100),
+            balance: Uint128::new(50),
+            pruned: 2,
+            last_scanned: Some("example".into()),
+        };
+        assert_eq!(event.to_event().ty, "terranames_prune_expired");
+        assert_eq!(
+            TerranamesEvent::parse(&event.to_event()),
+            Ok(TerranamesEvent::Keeper(event)),
+        );
+    }
+
+    #[test]
+    fn migrate_event_round_trip() {
+        let event = MigrateEvent {
+            migrated: 2,
+            complete: false,
+        };
+        assert_eq!(event.to_event().ty, "terranames_migrate");
+        assert_eq!(
+            TerranamesEvent::parse(&event.to_event()),
+            Ok(TerranamesEvent::Migrate(event)),
+        );
+    }
+
+    #[test]
+    fn parse_invalid_events() {
+        let event = Event::new("wasm-other_bid").add_attribute("name", "example");
+        assert!(matches!(
+            TerranamesEvent::parse(&event),
+            Err(EventParseError::UnknownType { .. }),
+        ));
+
+        let event = Event::new("wasm-terranames_bid").add_attribute("name", "example");
+        assert_eq!(
+            NameEvent::parse(&event),
+            Err(EventParseError::MissingAttribute { key: "rate" }),
+        );
+
+        let mut event = NameEvent::new(NameAction::Fund, "example").to_event();
+        event.attributes.retain(|attr| attr.key != "rate");
+        event = event.add_attribute("rate", "abc");
+        assert_eq!(
+            NameEvent::parse(&event),
+            Err(EventParseError::InvalidAttribute { key: "rate", value: "abc".into() }),
+        );
+    }
+}
@@ -0,0 +1,586 @@
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{
+    to_binary, Addr, Coin, CosmosMsg, QuerierWrapper, StdResult, Uint128,
+    WasmMsg, WasmQuery,
+};
+use cw20::Cw20ExecuteMsg;
+
+use crate::auction::{
+    AllNameStatesResponse, ConfigResponse as AuctionConfigResponse,
+    ExecuteMsg as AuctionExecuteMsg, KeeperPoolResponse, ListNamesResponse,
+    ListOrder, NameRolesResponse, NameStateResponse, NameStatus,
+    PendingTransferResponse, PermissionsResponse, QueryMsg as AuctionQueryMsg,
+    ReferralBalanceResponse, Role, StatsResponse as AuctionStatsResponse,
+    TldConfigMsg, TldsResponse,
+};
+use crate::resolver::{
+    ConfigResponse as ResolverConfigResponse, ExecuteMsg as ResolverExecuteMsg,
+    ListRecordsResponse, QueryMsg as ResolverQueryMsg, Record, RecordKey,
+    ResolveNameResponse, ResolveRecordResponse,
+};
+use crate::root_collector::{
+    ConfigResponse as RootCollectorConfigResponse,
+    ExecuteMsg as RootCollectorExecuteMsg, QueryMsg as RootCollectorQueryMsg,
+    ReceiveMsg as RootCollectorReceiveMsg, StakeStateResponse, StateResponse,
+    StatsResponse as RootCollectorStatsResponse,
+};
+use crate::utils::Timestamp;
+
+/// Return funds to attach for deposit
+///
+/// The bank module rejects coins with a zero amount so nothing is attached
+/// for an empty deposit.
+fn deposit_funds(deposit: Coin) -> Vec<Coin> {
+    if deposit.amount.is_zero() {
+        vec![]
+    } else {
+        vec![deposit]
+    }
+}
+
+fn execute_msg<T: Serialize>(
+    contract: &Addr,
+    msg: &T,
+    funds: Vec<Coin>,
+) -> StdResult<CosmosMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: contract.into(),
+        msg: to_binary(msg)?,
+        funds,
+    }.into())
+}
+
+fn query_msg<T, M>(
+    querier: &QuerierWrapper,
+    contract: &Addr,
+    msg: &M,
+) -> StdResult<T>
+where
+    T: DeserializeOwned,
+    M: Serialize,
+{
+    let query = WasmQuery::Smart {
+        contract_addr: contract.into(),
+        msg: to_binary(msg)?,
+    }.into();
+    querier.query(&query)
+}
+
+/// Filters of the auction `ListNames` query
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, JsonSchema)]
+pub struct ListNamesFilter {
+    /// Only return names in this status
+    pub status: Option<NameStatus>,
+    /// Only return names starting with this prefix
+    pub prefix: Option<String>,
+    /// Only return names with at least this rate
+    pub min_rate: Option<Uint128>,
+    /// Only return names owned by this address
+    pub owner: Option<String>,
+}
+
+/// Wrapper around the address of the auction contract
+///
+/// Builds messages for executing the contract and sends typed queries.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuctionContract(pub Addr);
+
+impl AuctionContract {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    /// Create message executing msg with funds attached
+    pub fn call(&self, msg: AuctionExecuteMsg, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        execute_msg(&self.0, &msg, funds)
+    }
+
+    /// Bid on name with the deposit attached
+    pub fn bid_name(
+        &self,
+        name: impl Into<String>,
+        rate: Uint128,
+        referrer: Option<String>,
+        deposit: Coin,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            AuctionExecuteMsg::BidName { name: name.into(), rate, referrer },
+            deposit_funds(deposit),
+        )
+    }
+
+    /// Add the deposit to the funds of name held by owner
+    pub fn fund_name(
+        &self,
+        name: impl Into<String>,
+        owner: impl Into<String>,
+        referrer: Option<String>,
+        deposit: Coin,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            AuctionExecuteMsg::FundName { name: name.into(), owner: owner.into(), referrer },
+            deposit_funds(deposit),
+        )
+    }
+
+    pub fn set_name_rate(&self, name: impl Into<String>, rate: Uint128) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::SetNameRate { name: name.into(), rate }, vec![])
+    }
+
+    pub fn transfer_name_owner(
+        &self,
+        name: impl Into<String>,
+        to: impl Into<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::TransferNameOwner { name: name.into(), to: to.into() }, vec![])
+    }
+
+    pub fn set_name_controller(
+        &self,
+        name: impl Into<String>,
+        controller: Option<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::SetNameController { name: name.into(), controller }, vec![])
+    }
+
+    pub fn grant_role(
+        &self,
+        name: impl Into<String>,
+        role: Role,
+        address: impl Into<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            AuctionExecuteMsg::GrantRole { name: name.into(), role, address: address.into() },
+            vec![],
+        )
+    }
+
+    pub fn revoke_role(
+        &self,
+        name: impl Into<String>,
+        role: Role,
+        address: impl Into<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            AuctionExecuteMsg::RevokeRole { name: name.into(), role, address: address.into() },
+            vec![],
+        )
+    }
+
+    pub fn propose_transfer(
+        &self,
+        name: impl Into<String>,
+        to: impl Into<String>,
+        expires: Option<Timestamp>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            AuctionExecuteMsg::ProposeTransfer { name: name.into(), to: to.into(), expires },
+            vec![],
+        )
+    }
+
+    pub fn accept_transfer(&self, name: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::AcceptTransfer { name: name.into() }, vec![])
+    }
+
+    pub fn cancel_transfer(&self, name: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::CancelTransfer { name: name.into() }, vec![])
+    }
+
+    pub fn withdraw_referral_balance(&self, to: Option<String>) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::WithdrawReferralBalance { to }, vec![])
+    }
+
+    pub fn create_tld(&self, tld: impl Into<String>, config: TldConfigMsg) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::CreateTld { tld: tld.into(), config }, vec![])
+    }
+
+    pub fn set_resolver_contract(&self, resolver_contract: Option<String>) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::SetResolverContract { resolver_contract }, vec![])
+    }
+
+    /// Add the deposit to the pool paying keepers for pruning names
+    pub fn fund_keeper_pool(&self, deposit: Coin) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::FundKeeperPool {}, deposit_funds(deposit))
+    }
+
+    pub fn prune_expired(&self, names: Vec<String>) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::PruneExpired { names }, vec![])
+    }
+
+    pub fn prune_expired_batch(
+        &self,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(AuctionExecuteMsg::PruneExpiredBatch { start_after, limit }, vec![])
+    }
+
+    /// Send query to the contract
+    pub fn query<T: DeserializeOwned>(
+        &self,
+        querier: &QuerierWrapper,
+        msg: &AuctionQueryMsg,
+    ) -> StdResult<T> {
+        query_msg(querier, &self.0, msg)
+    }
+
+    /// Query config of the default namespace
+    pub fn config(&self, querier: &QuerierWrapper) -> StdResult<AuctionConfigResponse> {
+        self.query(querier, &AuctionQueryMsg::Config {})
+    }
+
+    pub fn name_state(
+        &self,
+        querier: &QuerierWrapper,
+        name: impl Into<String>,
+    ) -> StdResult<NameStateResponse> {
+        self.query(querier, &AuctionQueryMsg::GetNameState { name: name.into() })
+    }
+
+    pub fn all_name_states(
+        &self,
+        querier: &QuerierWrapper,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<AllNameStatesResponse> {
+        self.query(querier, &AuctionQueryMsg::GetAllNameStates { start_after, limit })
+    }
+
+    pub fn name_roles(
+        &self,
+        querier: &QuerierWrapper,
+        name: impl Into<String>,
+    ) -> StdResult<NameRolesResponse> {
+        self.query(querier, &AuctionQueryMsg::GetNameRoles { name: name.into() })
+    }
+
+    pub fn pending_transfer(
+        &self,
+        querier: &QuerierWrapper,
+        name: impl Into<String>,
+    ) -> StdResult<PendingTransferResponse> {
+        self.query(querier, &AuctionQueryMsg::GetPendingTransfer { name: name.into() })
+    }
+
+    pub fn list_names(
+        &self,
+        querier: &QuerierWrapper,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        order: Option<ListOrder>,
+        filter: ListNamesFilter,
+    ) -> StdResult<ListNamesResponse> {
+        self.query(querier, &AuctionQueryMsg::ListNames {
+            start_after,
+            limit,
+            order,
+            status: filter.status,
+            prefix: filter.prefix,
+            min_rate: filter.min_rate,
+            owner: filter.owner,
+        })
+    }
+
+    pub fn stats(&self, querier: &QuerierWrapper) -> StdResult<AuctionStatsResponse> {
+        self.query(querier, &AuctionQueryMsg::Stats {})
+    }
+
+    pub fn referral_balance(
+        &self,
+        querier: &QuerierWrapper,
+        address: impl Into<String>,
+    ) -> StdResult<ReferralBalanceResponse> {
+        self.query(querier, &AuctionQueryMsg::GetReferralBalance { address: address.into() })
+    }
+
+    pub fn tld_config(
+        &self,
+        querier: &QuerierWrapper,
+        tld: impl Into<String>,
+    ) -> StdResult<AuctionConfigResponse> {
+        self.query(querier, &AuctionQueryMsg::GetTldConfig { tld: tld.into() })
+    }
+
+    pub fn list_tlds(
+        &self,
+        querier: &QuerierWrapper,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<TldsResponse> {
+        self.query(querier, &AuctionQueryMsg::ListTlds { start_after, limit })
+    }
+
+    pub fn keeper_pool(&self, querier: &QuerierWrapper) -> StdResult<KeeperPoolResponse> {
+        self.query(querier, &AuctionQueryMsg::GetKeeperPool {})
+    }
+
+    pub fn permissions(
+        &self,
+        querier: &QuerierWrapper,
+        name: impl Into<String>,
+        address: impl Into<String>,
+    ) -> StdResult<PermissionsResponse> {
+        self.query(querier, &AuctionQueryMsg::GetPermissions {
+            name: name.into(),
+            address: address.into(),
+        })
+    }
+}
+
+/// Wrapper around the address of the resolver contract
+///
+/// Builds messages for executing the contract and sends typed queries.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ResolverContract(pub Addr);
+
+impl ResolverContract {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    /// Create message executing msg
+    pub fn call(&self, msg: ResolverExecuteMsg) -> StdResult<CosmosMsg> {
+        execute_msg(&self.0, &msg, vec![])
+    }
+
+    /// Set value of name (None to clear)
+    pub fn set_name_value(
+        &self,
+        name: impl Into<String>,
+        value: Option<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ResolverExecuteMsg::SetNameValue { name: name.into(), value })
+    }
+
+    /// Drop values of names (only callable by the auction contract)
+    pub fn drop_name_values(&self, names: Vec<String>) -> StdResult<CosmosMsg> {
+        self.call(ResolverExecuteMsg::DropNameValues { names })
+    }
+
+    pub fn set_record(
+        &self,
+        name: impl Into<String>,
+        key: RecordKey,
+        value: impl Into<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ResolverExecuteMsg::SetRecord { name: name.into(), key, value: value.into() })
+    }
+
+    pub fn set_records(
+        &self,
+        name: impl Into<String>,
+        records: Vec<Record>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ResolverExecuteMsg::SetRecords { name: name.into(), records })
+    }
+
+    pub fn delete_record(&self, name: impl Into<String>, key: RecordKey) -> StdResult<CosmosMsg> {
+        self.call(ResolverExecuteMsg::DeleteRecord { name: name.into(), key })
+    }
+
+    /// Send query to the contract
+    pub fn query<T: DeserializeOwned>(
+        &self,
+        querier: &QuerierWrapper,
+        msg: &ResolverQueryMsg,
+    ) -> StdResult<T> {
+        query_msg(querier, &self.0, msg)
+    }
+
+    pub fn config(&self, querier: &QuerierWrapper) -> StdResult<ResolverConfigResponse> {
+        self.query(querier, &ResolverQueryMsg::Config {})
+    }
+
+    pub fn resolve_name(
+        &self,
+        querier: &QuerierWrapper,
+        name: impl Into<String>,
+    ) -> StdResult<ResolveNameResponse> {
+        self.query(querier, &ResolverQueryMsg::ResolveName { name: name.into() })
+    }
+
+    pub fn resolve_record(
+        &self,
+        querier: &QuerierWrapper,
+        name: impl Into<String>,
+        key: RecordKey,
+    ) -> StdResult<ResolveRecordResponse> {
+        self.query(querier, &ResolverQueryMsg::ResolveRecord { name: name.into(), key })
+    }
+
+    pub fn list_records(
+        &self,
+        querier: &QuerierWrapper,
+        name: impl Into<String>,
+    ) -> StdResult<ListRecordsResponse> {
+        self.query(querier, &ResolverQueryMsg::ListRecords { name: name.into() })
+    }
+}
+
+/// Wrapper around the address of the root collector contract
+///
+/// Builds messages for executing the contract and sends typed queries.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RootCollectorContract(pub Addr);
+
+impl RootCollectorContract {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    /// Create message executing msg with funds attached
+    pub fn call(&self, msg: RootCollectorExecuteMsg, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        execute_msg(&self.0, &msg, funds)
+    }
+
+    /// Deposit stablecoins to be paid out as dividends
+    pub fn deposit(&self, deposit: Coin) -> StdResult<CosmosMsg> {
+        self.call(RootCollectorExecuteMsg::Deposit {}, deposit_funds(deposit))
+    }
+
+    /// Stake tokens by sending them from the base token contract
+    ///
+    /// The message is executed on the base token contract which calls the
+    /// root collector with `Receive`.
+    pub fn stake_tokens(&self, base_token: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+        execute_msg(
+            base_token,
+            &Cw20ExecuteMsg::Send {
+                contract: self.0.to_string(),
+                amount,
+                msg: to_binary(&RootCollectorReceiveMsg::Stake {})?,
+            },
+            vec![],
+        )
+    }
+
+    pub fn unstake_tokens(&self, amount: Uint128) -> StdResult<CosmosMsg> {
+        self.call(RootCollectorExecuteMsg::UnstakeTokens { amount }, vec![])
+    }
+
+    pub fn withdraw_tokens(&self, amount: Uint128, to: Option<String>) -> StdResult<CosmosMsg> {
+        self.call(RootCollectorExecuteMsg::WithdrawTokens { amount, to }, vec![])
+    }
+
+    pub fn withdraw_dividends(&self, to: Option<String>) -> StdResult<CosmosMsg> {
+        self.call(RootCollectorExecuteMsg::WithdrawDividends { to }, vec![])
+    }
+
+    /// Send query to the contract
+    pub fn query<T: DeserializeOwned>(
+        &self,
+        querier: &QuerierWrapper,
+        msg: &RootCollectorQueryMsg,
+    ) -> StdResult<T> {
+        query_msg(querier, &self.0, msg)
+    }
+
+    pub fn config(&self, querier: &QuerierWrapper) -> StdResult<RootCollectorConfigResponse> {
+        self.query(querier, &RootCollectorQueryMsg::Config {})
+    }
+
+    pub fn state(&self, querier: &QuerierWrapper) -> StdResult<StateResponse> {
+        self.query(querier, &RootCollectorQueryMsg::State {})
+    }
+
+    pub fn stake_state(
+        &self,
+        querier: &QuerierWrapper,
+        address: impl Into<String>,
+    ) -> StdResult<StakeStateResponse> {
+        self.query(querier, &RootCollectorQueryMsg::StakeState { address: address.into() })
+    }
+
+    pub fn stats(&self, querier: &QuerierWrapper) -> StdResult<RootCollectorStatsResponse> {
+        self.query(querier, &RootCollectorQueryMsg::Stats {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::{coin, from_binary, Decimal};
+
+    use crate::testing::auction::{AuctionQuerier, MockNameState};
+    use crate::testing::MockQuerierBuilder;
+
+    #[test]
+    fn attach_deposit_funds() {
+        let auction = AuctionContract(Addr::unchecked("auction"));
+
+        let msg = auction.bid_name("example", Uint128::new(100), None, coin(1_000, "uusd")).unwrap();
+        match msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }) => {
+                assert_eq!(contract_addr, "auction");
+                assert_eq!(funds, vec![coin(1_000, "uusd")]);
+                assert_eq!(from_binary::<AuctionExecuteMsg>(&msg).unwrap(), AuctionExecuteMsg::BidName {
+                    name: "example".into(),
+                    rate: Uint128::new(100),
+                    referrer: None,
+                });
+            },
+            _ => panic!("Unexpected message: {:?}", msg),
+        }
+
+        let msg = auction.fund_keeper_pool(coin(0, "uusd")).unwrap();
+        assert_eq!(msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "auction".into(),
+            msg: to_binary(&AuctionExecuteMsg::FundKeeperPool {}).unwrap(),
+            funds: vec![],
+        }));
+
+        let root_collector = RootCollectorContract(Addr::unchecked("root_collector"));
+        let msg = root_collector.deposit(coin(500, "uusd")).unwrap();
+        assert_eq!(msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "root_collector".into(),
+            msg: to_binary(&RootCollectorExecuteMsg::Deposit {}).unwrap(),
+            funds: vec![coin(500, "uusd")],
+        }));
+    }
+
+    #[test]
+    fn stake_tokens_through_base_token() {
+        let root_collector = RootCollectorContract(Addr::unchecked("root_collector"));
+        let msg = root_collector.stake_tokens(&Addr::unchecked("token"), Uint128::new(1_000)).unwrap();
+        assert_eq!(msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token".into(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: "root_collector".into(),
+                amount: Uint128::new(1_000),
+                msg: to_binary(&RootCollectorReceiveMsg::Stake {}).unwrap(),
+            }).unwrap(),
+            funds: vec![],
+        }));
+    }
+
+    #[test]
+    fn query_auction_contract() {
+        let state = MockNameState::new("owner", 100, 1_000, 30_000);
+        let querier = MockQuerierBuilder::new()
+            .with_contract("auction", AuctionQuerier::new()
+                .with_name_state("example", &state, Timestamp::from_seconds(700_000))
+            )
+            .build();
+        let querier = QuerierWrapper::new(&querier);
+        let auction = AuctionContract(Addr::unchecked("auction"));
+
+        let config = auction.config(&querier).unwrap();
+        assert_eq!(config.stable_denom, "uabc");
+        assert_eq!(config.referral_share, Decimal::zero());
+
+        let res = auction.name_state(&querier, "example").unwrap();
+        assert_eq!(res.name_owner, Some(Addr::unchecked("owner")));
+        assert!(auction.name_state(&querier, "other").is_err());
+
+        let res = auction.all_name_states(&querier, None, None).unwrap();
+        assert_eq!(res.names.len(), 1);
+
+        let res = auction.name_roles(&querier, "example").unwrap();
+        assert_eq!(res.name_owner, Some(Addr::unchecked("owner")));
+    }
+}
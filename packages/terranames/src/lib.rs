@@ -1,7 +1,14 @@
 pub mod auction;
+pub mod events;
+pub mod helpers;
 pub mod querier;
 pub mod resolver;
 pub mod root_collector;
+pub mod storage;
+pub mod tax;
+#[cfg(feature = "terra")]
 pub mod terra;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod testing;
 pub mod utils;
+pub mod validation;
@@ -1,18 +1,39 @@
-use cosmwasm_std::{to_binary, Addr, QuerierWrapper, StdResult, WasmQuery};
+use cosmwasm_std::{Addr, QuerierWrapper, StdResult};
 
-use crate::auction::{QueryMsg as AuctionQueryMsg, NameStateResponse};
+use crate::auction::{split_name, ConfigResponse, NameStateResponse};
+use crate::helpers::AuctionContract;
 
 pub fn query_name_state(
     querier: &QuerierWrapper,
     auction_contract: &Addr,
     name: &str,
 ) -> StdResult<NameStateResponse> {
-    let msg = AuctionQueryMsg::GetNameState {
-        name: name.into(),
-    };
-    let query = WasmQuery::Smart {
-        contract_addr: auction_contract.into(),
-        msg: to_binary(&msg)?,
-    }.into();
-    querier.query(&query)
+    AuctionContract(auction_contract.clone()).name_state(querier, name)
+}
+
+/// Query config of the TLD or the default namespace if None
+pub fn query_tld_config(
+    querier: &QuerierWrapper,
+    auction_contract: &Addr,
+    tld: Option<&str>,
+) -> StdResult<ConfigResponse> {
+    let auction = AuctionContract(auction_contract.clone());
+    match tld {
+        Some(tld) => auction.tld_config(querier, tld),
+        None => auction.config(querier),
+    }
+}
+
+/// Query config that applies to name based on its TLD
+///
+/// Names stored before TLDs existed are in the default namespace even if
+/// they contain the separator. The TLD of a held name is reported in its
+/// name state.
+pub fn query_name_config(
+    querier: &QuerierWrapper,
+    auction_contract: &Addr,
+    name: &str,
+) -> StdResult<ConfigResponse> {
+    let (_, tld) = split_name(name);
+    query_tld_config(querier, auction_contract, tld)
 }
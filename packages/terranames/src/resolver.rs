@@ -1,13 +1,151 @@
-use cosmwasm_std::Addr;
+use std::fmt;
+use std::str::FromStr;
+
+use cosmwasm_std::{Addr, Api, StdError, StdResult};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::utils::Timestamp;
+use crate::validation::{
+    is_valid_bech32, is_valid_content_hash, is_valid_evm_address, is_valid_url,
+};
+
+/// Maximum length of the chain of address records and the key of text records
+pub const MAX_RECORD_KEY_LENGTH: usize = 64;
+
+/// Key of a record stored for a name
+///
+/// The string form used in events is `default`, `address:<chain>`,
+/// `text:<key>` or `content_hash`.
+#[derive(
+    Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordKey {
+    /// Record set by `SetNameValue` and resolved by `ResolveName`
+    Default {},
+    /// Address on a chain, identified by chain name or SLIP-44 coin type
+    /// (e.g. `terra` or `60`)
+    Address { chain: String },
+    /// Text record such as `email`, `url` or `avatar`
+    Text { key: String },
+    /// Content hash of a website or other content
+    ContentHash {},
+}
+
+impl RecordKey {
+    /// Return whether the chain or text key is non-empty and not too long
+    pub fn is_valid(&self) -> bool {
+        match self {
+            RecordKey::Address { chain: key } | RecordKey::Text { key } => {
+                !key.is_empty() && key.len() <= MAX_RECORD_KEY_LENGTH
+            },
+            RecordKey::Default {} | RecordKey::ContentHash {} => true,
+        }
+    }
+}
+
+impl fmt::Display for RecordKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordKey::Default {} => write!(f, "default"),
+            RecordKey::Address { chain } => write!(f, "address:{}", chain),
+            RecordKey::Text { key } => write!(f, "text:{}", key),
+            RecordKey::ContentHash {} => write!(f, "content_hash"),
+        }
+    }
+}
+
+impl FromStr for RecordKey {
+    type Err = StdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("address", chain)) => Ok(RecordKey::Address { chain: chain.into() }),
+            Some(("text", key)) => Ok(RecordKey::Text { key: key.into() }),
+            None if s == "default" => Ok(RecordKey::Default {}),
+            None if s == "content_hash" => Ok(RecordKey::ContentHash {}),
+            _ => Err(StdError::parse_err("RecordKey", format!("Unknown record key: {}", s))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Record {
+    /// Key of the record
+    pub key: RecordKey,
+    /// Value of the record
+    pub value: String,
+}
+
+/// Format that the values of a record are validated against
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordFormat {
+    /// Address on the chain that the resolver runs on
+    NativeAddress {},
+    /// Bech32 address with the human-readable prefix (e.g. `cosmos`)
+    Bech32Address { prefix: String },
+    /// Hex EVM address with EIP-55 checksum
+    EvmAddress {},
+    /// Absolute URL with scheme and host
+    Url {},
+    /// Hex encoded content hash as defined by EIP-1577
+    ContentHash {},
+}
+
+impl RecordFormat {
+    /// Return whether the format is usable for validation
+    pub fn is_valid(&self) -> bool {
+        match self {
+            RecordFormat::Bech32Address { prefix } => {
+                !prefix.is_empty() && prefix.bytes().all(|c| (33..=126).contains(&c))
+            },
+            _ => true,
+        }
+    }
+
+    /// Validate value of record (native addresses are validated by the API)
+    pub fn validate(&self, api: &dyn Api, value: &str) -> StdResult<()> {
+        let reason = match self {
+            RecordFormat::NativeAddress {} => {
+                return api.addr_validate(value).map(|_| ());
+            },
+            RecordFormat::Bech32Address { prefix } if !is_valid_bech32(value, prefix) => {
+                format!("Invalid bech32 address with prefix {}", prefix)
+            },
+            RecordFormat::EvmAddress {} if !is_valid_evm_address(value) => {
+                "Invalid EVM address or EIP-55 checksum".into()
+            },
+            RecordFormat::Url {} if !is_valid_url(value) => {
+                "Invalid URL".into()
+            },
+            RecordFormat::ContentHash {} if !is_valid_content_hash(value) => {
+                "Invalid content hash".into()
+            },
+            _ => return Ok(()),
+        };
+        Err(StdError::generic_err(reason))
+    }
+}
+
+/// Validation of the values of a record
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RecordValidation {
+    /// Key of the record
+    pub key: RecordKey,
+    /// Format that values must have
+    pub format: RecordFormat,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     // Auction contract handling ownership
     pub auction_contract: String,
+    /// Records that are validated when set (other records are not checked)
+    #[serde(default)]
+    pub record_validations: Vec<RecordValidation>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -19,6 +157,30 @@ pub enum ExecuteMsg {
         /// Value to set
         value: Option<String>,
     },
+    DropNameValues {
+        /// Names to drop values of (only callable by the auction contract)
+        names: Vec<String>,
+    },
+    SetRecord {
+        /// Name to set record for
+        name: String,
+        /// Key of the record
+        key: RecordKey,
+        /// Value to set
+        value: String,
+    },
+    SetRecords {
+        /// Name to set records for
+        name: String,
+        /// Records to set (other records are kept)
+        records: Vec<Record>,
+    },
+    DeleteRecord {
+        /// Name to delete record of
+        name: String,
+        /// Key of the record
+        key: RecordKey,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -29,15 +191,39 @@ pub enum QueryMsg {
         /// Name to resolve value for
         name: String,
     },
+    ResolveRecord {
+        /// Name to resolve record for
+        name: String,
+        /// Key of the record
+        key: RecordKey,
+    },
+    ListRecords {
+        /// Name to list records of
+        name: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct MigrateMsg {}
+pub struct MigrateMsg {
+    /// Replace the record validations if set
+    #[serde(default)]
+    pub record_validations: Option<Vec<RecordValidation>>,
+    /// Maximum number of values to stamp with the epoch of the current owner
+    /// when migrating from a version without epochs (default 100)
+    ///
+    /// Migrate again to stamp the remaining values until the migrate event
+    /// reports that the migration is complete. Records cannot be set until
+    /// all values are stamped.
+    #[serde(default)]
+    pub epoch_limit: Option<u32>,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
     // Auction contract handling ownership
     pub auction_contract: Addr,
+    /// Records that are validated when set
+    pub record_validations: Vec<RecordValidation>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -48,4 +234,99 @@ pub struct ResolveNameResponse {
     pub owner: Addr,
     /// Timestamp when value expires
     pub expire_time: Option<Timestamp>,
+    /// Whether the name has expired and is only held in the grace period
+    pub lapsed: bool,
+    /// TLD of the name (None for names in the default namespace)
+    pub tld: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ResolveRecordResponse {
+    /// Value of the record (None if not set)
+    pub value: Option<String>,
+    /// Current owner (for verifying if owner has changed)
+    pub owner: Addr,
+    /// Timestamp when value expires
+    pub expire_time: Option<Timestamp>,
+    /// Whether the name has expired and is only held in the grace period
+    pub lapsed: bool,
+    /// TLD of the name (None for names in the default namespace)
+    pub tld: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListRecordsResponse {
+    /// Records of the name, starting with the default record
+    pub records: Vec<Record>,
+    /// Current owner (for verifying if owner has changed)
+    pub owner: Addr,
+    /// Timestamp when values expire
+    pub expire_time: Option<Timestamp>,
+    /// Whether the name has expired and is only held in the grace period
+    pub lapsed: bool,
+    /// TLD of the name (None for names in the default namespace)
+    pub tld: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockApi;
+
+    #[test]
+    fn record_key_string_round_trip() {
+        let keys = [
+            RecordKey::Default {},
+            RecordKey::Address { chain: "terra".into() },
+            RecordKey::Address { chain: "cosmos:hub".into() },
+            RecordKey::Text { key: "email".into() },
+            RecordKey::Text { key: "".into() },
+            RecordKey::ContentHash {},
+        ];
+        for key in keys {
+            assert_eq!(key.to_string().parse::<RecordKey>().unwrap(), key);
+        }
+
+        assert_eq!(RecordKey::Text { key: "url".into() }.to_string(), "text:url");
+        assert!("other".parse::<RecordKey>().is_err());
+        assert!("default:".parse::<RecordKey>().is_err());
+    }
+
+    #[test]
+    fn record_key_validation() {
+        assert!(RecordKey::Default {}.is_valid());
+        assert!(RecordKey::ContentHash {}.is_valid());
+        assert!(RecordKey::Address { chain: "60".into() }.is_valid());
+        assert!(!RecordKey::Address { chain: "".into() }.is_valid());
+        assert!(RecordKey::Text { key: "a".repeat(MAX_RECORD_KEY_LENGTH) }.is_valid());
+        assert!(!RecordKey::Text { key: "a".repeat(MAX_RECORD_KEY_LENGTH + 1) }.is_valid());
+    }
+
+    #[test]
+    fn record_format_validation() {
+        let api = MockApi::default();
+
+        let format = RecordFormat::NativeAddress {};
+        assert!(format.validate(&api, "terra1x46rqay4d3cssq8gxxvqz8xt6nwlz4td20k38v").is_ok());
+        assert!(format.validate(&api, "").is_err());
+
+        let format = RecordFormat::Bech32Address { prefix: "cosmos".into() };
+        assert!(format.is_valid());
+        assert!(format.validate(&api, "cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu").is_ok());
+        let err = format.validate(&api, "cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xv").unwrap_err();
+        assert_eq!(err.to_string(), "Generic error: Invalid bech32 address with prefix cosmos");
+        assert!(!RecordFormat::Bech32Address { prefix: "".into() }.is_valid());
+
+        let format = RecordFormat::EvmAddress {};
+        assert!(format.validate(&api, "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_ok());
+        assert!(format.validate(&api, "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_err());
+
+        let format = RecordFormat::Url {};
+        assert!(format.validate(&api, "https://example.com").is_ok());
+        assert!(format.validate(&api, "example.com").is_err());
+
+        let format = RecordFormat::ContentHash {};
+        assert!(format.validate(&api, "0xbc037a716b746c7769756176767671717434").is_ok());
+        assert!(format.validate(&api, "0xbc03").is_err());
+    }
 }
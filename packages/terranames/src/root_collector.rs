@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use cw20::Cw20ReceiveMsg;
 
+use crate::tax::TaxPolicy;
 use crate::utils::{Timedelta, Timestamp};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -15,6 +16,9 @@ pub struct InstantiateMsg {
     pub stable_denom: String,
     /// Unstake delay
     pub unstake_delay: Timedelta,
+    /// Tax deducted from dividends sent by the contract
+    #[serde(default)]
+    pub tax_policy: TaxPolicy,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -26,6 +30,7 @@ pub enum QueryMsg {
         /// Address to query stake state for
         address: String,
     },
+    Stats {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -50,7 +55,19 @@ pub enum ExecuteMsg {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct MigrateMsg {}
+pub struct MigrateMsg {
+    /// Tax policy (unchanged if None)
+    #[serde(default)]
+    pub tax_policy: Option<TaxPolicy>,
+    /// Maximum number of stakes to count when migrating from a version
+    /// without stats (default 300)
+    ///
+    /// Migrate again to count the remaining stakes until the migrate event
+    /// reports that the migration is complete. Execute messages are
+    /// rejected until all stakes are counted.
+    #[serde(default)]
+    pub stake_limit: Option<u32>,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -61,6 +78,8 @@ pub struct ConfigResponse {
     pub stable_denom: String,
     /// Unstake delay
     pub unstake_delay: Timedelta,
+    /// Tax deducted from dividends sent by the contract
+    pub tax_policy: TaxPolicy,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -96,3 +115,16 @@ pub struct StakeStateResponse {
 pub enum ReceiveMsg {
     Stake {},
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct StatsResponse {
+    /// Total stablecoin deposits received
+    pub total_deposits: Uint128,
+    /// Total dividends withdrawn by stakers
+    pub total_dividends_withdrawn: Uint128,
+    /// Tokens unstaked that have not been withdrawn yet
+    ///
+    /// This includes tokens where the unstake delay has passed.
+    pub tokens_unstaking: Uint128,
+}
@@ -0,0 +1,58 @@
+//! Storage helpers shared by the contracts
+//!
+//! The contracts store maps with the same layout as the cosmwasm_storage
+//! buckets they replace. Items do not share the layout of singletons so the
+//! singletons are moved by the migration.
+
+use cosmwasm_std::{StdResult, Storage};
+use cw_storage_plus::{Item, Path};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Move a value stored by a cosmwasm_storage singleton into an item
+///
+/// Singletons stored the value under the length-prefixed key while items use
+/// the key as is. Returns true if a value was moved.
+pub fn migrate_singleton<T>(
+    storage: &mut dyn Storage,
+    key: &str,
+    item: &Item<T>,
+) -> StdResult<bool>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let singleton: Path<T> = Path::new(key.as_bytes(), &[b""]);
+    match singleton.may_load(storage)? {
+        Some(value) => {
+            item.save(storage, &value)?;
+            singleton.remove(storage);
+            Ok(true)
+        },
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::MockStorage;
+
+    const ITEM: Item<u64> = Item::new("config");
+
+    #[test]
+    fn migrate_singleton_moves_value() {
+        let mut storage = MockStorage::new();
+        let singleton: Path<u64> = Path::new(b"config", &[b""]);
+        singleton.save(&mut storage, &1234).unwrap();
+        assert_eq!(ITEM.may_load(&storage).unwrap(), None);
+
+        assert!(migrate_singleton(&mut storage, "config", &ITEM).unwrap());
+        assert_eq!(ITEM.load(&storage).unwrap(), 1234);
+        assert_eq!(singleton.may_load(&storage).unwrap(), None);
+
+        // Nothing is moved the second time
+        assert!(!migrate_singleton(&mut storage, "config", &ITEM).unwrap());
+        assert_eq!(ITEM.load(&storage).unwrap(), 1234);
+    }
+}
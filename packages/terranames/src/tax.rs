@@ -0,0 +1,204 @@
+use cosmwasm_std::{Coin, Decimal, QuerierWrapper, StdResult, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+const DECIMAL_FRACTION: Uint128 = Uint128::new(1_000_000_000_000_000_000u128);
+
+/// Tax charged by the chain on transfers sent by the contracts
+///
+/// The contracts deduct the tax from the amount they send so that the amount
+/// plus tax sums to the amount that the contract intended to pay out.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxPolicy {
+    /// No tax is charged
+    None {},
+    /// Fixed rate is charged on the amount received, up to the cap
+    Fixed {
+        rate: Decimal,
+        cap: Option<Uint128>,
+    },
+    /// Tax is charged according to the Terra treasury
+    ///
+    /// Only supported when built with the `terra` feature.
+    Terra {},
+}
+
+impl Default for TaxPolicy {
+    fn default() -> Self {
+        if cfg!(feature = "terra") {
+            TaxPolicy::Terra {}
+        } else {
+            TaxPolicy::None {}
+        }
+    }
+}
+
+impl TaxPolicy {
+    /// Return whether the policy is supported by this build
+    pub fn is_supported(&self) -> bool {
+        cfg!(feature = "terra") || !matches!(self, TaxPolicy::Terra {})
+    }
+
+    /// Calculate tax that is subtracted from the sent amount
+    pub fn calculate_tax(
+        &self,
+        querier: &QuerierWrapper,
+        denom: &str,
+        amount: Uint128,
+    ) -> StdResult<Uint128> {
+        match self {
+            TaxPolicy::None {} => Ok(Uint128::zero()),
+            TaxPolicy::Fixed { rate, cap } => calculate_rate_tax(amount, *rate, *cap),
+            TaxPolicy::Terra {} => calculate_terra_tax(querier, denom, amount),
+        }
+    }
+
+    /// Return amount after deducting tax
+    pub fn deduct_tax(
+        &self,
+        querier: &QuerierWrapper,
+        denom: &str,
+        amount: Uint128,
+    ) -> StdResult<Uint128> {
+        let tax = self.calculate_tax(querier, denom, amount)?;
+        Ok(amount.checked_sub(tax)?)
+    }
+
+    /// Return Coin after deducting tax
+    pub fn deduct_coin_tax(
+        &self,
+        querier: &QuerierWrapper,
+        coin: Coin,
+    ) -> StdResult<Coin> {
+        if let TaxPolicy::Terra {} = self {
+            return deduct_terra_coin_tax(querier, coin);
+        }
+
+        let amount = self.deduct_tax(querier, &coin.denom, coin.amount)?;
+        Ok(Coin {
+            denom: coin.denom,
+            amount,
+        })
+    }
+}
+
+/// Calculate tax that is subtracted from an amount sent with tax at the rate
+///
+/// The tax is limited to the cap if any. Also used for the Terra treasury tax.
+pub fn calculate_rate_tax(
+    amount: Uint128,
+    rate: Decimal,
+    cap: Option<Uint128>,
+) -> StdResult<Uint128> {
+    // a * (1 - (1 / (t + 1)))
+    let tax = amount.checked_sub(
+        amount.multiply_ratio(
+            DECIMAL_FRACTION,
+            DECIMAL_FRACTION * rate + DECIMAL_FRACTION,
+        ),
+    )?;
+    Ok(match cap {
+        Some(cap) => std::cmp::min(tax, cap),
+        None => tax,
+    })
+}
+
+#[cfg(feature = "terra")]
+fn calculate_terra_tax(
+    querier: &QuerierWrapper,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    crate::terra::calculate_tax(querier, denom, amount)
+}
+
+#[cfg(not(feature = "terra"))]
+fn calculate_terra_tax(
+    _querier: &QuerierWrapper,
+    _denom: &str,
+    _amount: Uint128,
+) -> StdResult<Uint128> {
+    Err(cosmwasm_std::StdError::generic_err("Terra tax policy is not supported"))
+}
+
+#[cfg(feature = "terra")]
+fn deduct_terra_coin_tax(
+    querier: &QuerierWrapper,
+    coin: Coin,
+) -> StdResult<Coin> {
+    crate::terra::deduct_coin_tax(querier, coin)
+}
+
+#[cfg(not(feature = "terra"))]
+fn deduct_terra_coin_tax(
+    _querier: &QuerierWrapper,
+    _coin: Coin,
+) -> StdResult<Coin> {
+    Err(cosmwasm_std::StdError::generic_err("Terra tax policy is not supported"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::coin;
+
+    use crate::testing::MockQuerier;
+
+    #[test]
+    fn deduct_fixed_tax() {
+        let querier = MockQuerier::default();
+        let querier = QuerierWrapper::new(&querier);
+
+        let policy = TaxPolicy::Fixed { rate: Decimal::percent(1), cap: None };
+        assert_eq!(
+            policy.deduct_coin_tax(&querier, coin(10_100, "uusd")).unwrap(),
+            coin(10_000, "uusd"),
+        );
+
+        let policy = TaxPolicy::Fixed { rate: Decimal::percent(1), cap: Some(Uint128::new(50)) };
+        assert_eq!(
+            policy.deduct_coin_tax(&querier, coin(10_100, "uusd")).unwrap(),
+            coin(10_050, "uusd"),
+        );
+
+        let policy = TaxPolicy::None {};
+        assert_eq!(
+            policy.deduct_coin_tax(&querier, coin(10_100, "uusd")).unwrap(),
+            coin(10_100, "uusd"),
+        );
+    }
+
+    #[cfg(feature = "terra")]
+    #[test]
+    fn deduct_terra_tax() {
+        use crate::testing::MockQuerierBuilder;
+        use crate::testing::terra::TaxQuerier;
+
+        let querier = MockQuerierBuilder::new()
+            .with_tax_querier(TaxQuerier::new(Decimal::percent(1), &[("uusd", &Uint128::new(50))]))
+            .build();
+        let querier = QuerierWrapper::new(&querier);
+
+        let policy = TaxPolicy::Terra {};
+        assert_eq!(
+            policy.deduct_coin_tax(&querier, coin(1_010, "uusd")).unwrap(),
+            coin(1_000, "uusd"),
+        );
+        assert_eq!(
+            policy.deduct_coin_tax(&querier, coin(10_100, "uusd")).unwrap(),
+            coin(10_050, "uusd"),
+        );
+        assert_eq!(
+            policy.deduct_coin_tax(&querier, coin(10_100, "uluna")).unwrap(),
+            coin(10_100, "uluna"),
+        );
+    }
+
+    #[test]
+    fn default_policy_is_supported() {
+        assert!(TaxPolicy::default().is_supported());
+        assert_eq!(TaxPolicy::Terra {}.is_supported(), cfg!(feature = "terra"));
+    }
+}
@@ -1,7 +1,7 @@
 use cosmwasm_std::{Coin, QuerierWrapper, StdResult, Uint128};
 use terra_cosmwasm::TerraQuerier;
 
-const DECIMAL_FRACTION: Uint128 = Uint128::new(1_000_000_000_000_000_000u128);
+use crate::tax::calculate_rate_tax;
 
 /// Calculate tax that is subtracted from the sent amount
 ///
@@ -14,16 +14,7 @@ pub fn calculate_tax(
     let terra_querier = TerraQuerier::new(querier);
     let tax_rate = terra_querier.query_tax_rate()?.rate;
     let tax_cap = terra_querier.query_tax_cap(denom)?.cap;
-    Ok(std::cmp::min(
-        // a * (1 - (1 / (t + 1)))
-        amount.checked_sub(
-            amount.multiply_ratio(
-                DECIMAL_FRACTION,
-                DECIMAL_FRACTION * tax_rate + DECIMAL_FRACTION,
-            ),
-        )?,
-        tax_cap,
-    ))
+    calculate_rate_tax(amount, tax_rate, Some(tax_cap))
 }
 
 /// Calculate tax to be sent in addition in order for recipient to receive amount
@@ -1,43 +1,401 @@
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
 use cosmwasm_std::{
-    from_binary, to_binary, CustomQuery, QuerierResult, QueryRequest, SystemError, WasmQuery,
+    from_binary, to_binary, Addr, Binary, ContractResult, Decimal, QuerierResult,
+    StdError, SystemError, SystemResult, Uint128,
+};
+
+use crate::auction::{
+    checked_deposit_from_seconds_ceil, checked_seconds_from_deposit, split_name,
+    AllNameStatesResponse, ConfigResponse, NameRolesResponse, NameStateItem,
+    NameStateResponse, QueryMsg, Recipient, RecipientKind, Role, RoleGrant,
 };
+use crate::tax::TaxPolicy;
+use crate::utils::{Timedelta, Timestamp};
+use super::querier::ContractQuerier;
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+/// Type name reported by the auction contract for missing name states
+const NAME_STATE_KIND: &str = "terranames_auction::state::NameState";
 
-use crate::auction::{NameStateResponse, QueryMsg};
+/// Config matching the defaults used in the contract tests
+pub fn mock_config() -> ConfigResponse {
+    ConfigResponse {
+        admin: None,
+        resolver_contract: None,
+        recipients: vec![
+            Recipient {
+                addr: Addr::unchecked("collector"),
+                share: Decimal::one(),
+                kind: RecipientKind::Deposit,
+            },
+        ],
+        stable_denom: "uabc".into(),
+        min_lease_secs: Timedelta::from_seconds(15_778_476), // 6 months
+        max_lease_secs: Timedelta::from_seconds(157_784_760), // 5 years
+        counter_delay_secs: Timedelta::from_seconds(604_800), // 1 week
+        transition_delay_secs: Timedelta::from_seconds(1_814_400), // 3 weeks
+        bid_delay_secs: Timedelta::from_seconds(15_778_476), // 6 months
+        grace_period_secs: Timedelta::zero(),
+        grace_fee_secs: Timedelta::zero(),
+        referral_share: Decimal::zero(),
+        min_rate: Uint128::zero(),
+        retention_secs: Timedelta::zero(),
+        keeper_reward: Uint128::zero(),
+        tax_policy: TaxPolicy::default(),
+    }
+}
+
+/// Description of the stored state of a name in the auction
+///
+/// Mirrors the state kept by the auction contract so that responses can be
+/// computed for any point in time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockNameState {
+    pub owner: Addr,
+    pub roles: Vec<RoleGrant>,
+    /// Timestamp from where transition delay is calculated from (zero for a
+    /// new bid on an unowned name)
+    pub transition_reference_time: Timestamp,
+    pub rate: Uint128,
+    pub begin_time: Timestamp,
+    pub begin_deposit: Uint128,
+    pub previous_owner: Option<Addr>,
+    pub previous_roles: Vec<RoleGrant>,
+    /// Transition reference timestamp of the previous owner
+    pub previous_transition_reference_time: Timestamp,
+}
+
+impl MockNameState {
+    /// Create state of new bid on an unowned name
+    pub fn new(
+        owner: impl Into<String>,
+        rate: u128,
+        begin_time: u64,
+        begin_deposit: u128,
+    ) -> Self {
+        MockNameState {
+            owner: Addr::unchecked(owner),
+            roles: vec![],
+            transition_reference_time: Timestamp::zero(),
+            rate: Uint128::new(rate),
+            begin_time: Timestamp::from_seconds(begin_time),
+            begin_deposit: Uint128::new(begin_deposit),
+            previous_owner: None,
+            previous_roles: vec![],
+            previous_transition_reference_time: Timestamp::zero(),
+        }
+    }
+
+    /// Grant role to address
+    pub fn with_role(mut self, role: Role, address: impl Into<String>) -> Self {
+        self.roles.push(RoleGrant { role, address: Addr::unchecked(address) });
+        self
+    }
+
+    /// Set controller of the name
+    pub fn with_controller(self, controller: impl Into<String>) -> Self {
+        self.with_role(Role::Controller, controller)
+    }
+
+    /// Set owner that held the name before the current bid
+    ///
+    /// The previous owner keeps the name and its roles during the counter
+    /// delay. The transition delay is counted from the begin time.
+    pub fn with_previous_owner(
+        mut self,
+        previous_owner: impl Into<String>,
+        previous_roles: Vec<RoleGrant>,
+    ) -> Self {
+        self.previous_owner = Some(Addr::unchecked(previous_owner));
+        self.previous_roles = previous_roles;
+        self.transition_reference_time = self.begin_time;
+        self
+    }
+
+    /// Compute response of the auction contract at current time
+    ///
+    /// Panics if any of the timestamps overflow.
+    pub fn response(&self, config: &ConfigResponse, current_time: Timestamp) -> NameStateResponse {
+        let overflow = "mock name state overflow";
+        let counter_delay_end = self.begin_time.checked_add(config.counter_delay_secs)
+            .expect(overflow);
+        let transition_delay_end = if self.transition_reference_time.is_zero() {
+            self.begin_time
+        } else {
+            self.transition_reference_time
+                .checked_add(config.counter_delay_secs)
+                .and_then(|time| time.checked_add(config.transition_delay_secs))
+                .expect(overflow)
+        };
+        let bid_delay_end = if self.rate.is_zero() {
+            self.begin_time
+        } else {
+            counter_delay_end.checked_add(config.bid_delay_secs).expect(overflow)
+        };
+        let expire_time = checked_seconds_from_deposit(self.begin_deposit, self.rate)
+            .expect(overflow)
+            .map(|seconds| self.begin_time.checked_add(seconds).expect(overflow));
+        let grace_period_end = expire_time
+            .map(|time| time.checked_add(config.grace_period_secs).expect(overflow));
+
+        let current_deposit = match current_time.checked_sub(self.begin_time) {
+            Ok(seconds_spent) => {
+                let spent = checked_deposit_from_seconds_ceil(seconds_spent, self.rate)
+                    .expect(overflow);
+                self.begin_deposit.saturating_sub(spent)
+            },
+            Err(_) => Uint128::zero(),
+        };
+
+        let expired = current_time < self.begin_time ||
+            matches!(grace_period_end, Some(end) if current_time >= end);
+        let (name_owner, bid_owner, roles, transition_reference_time) = if expired {
+            (None, None, vec![], self.transition_reference_time)
+        } else if current_time < counter_delay_end {
+            let roles = match self.previous_owner {
+                Some(_) => self.previous_roles.clone(),
+                None => vec![],
+            };
+            (
+                self.previous_owner.clone(),
+                Some(self.owner.clone()),
+                roles,
+                self.previous_transition_reference_time,
+            )
+        } else {
+            (
+                Some(self.owner.clone()),
+                Some(self.owner.clone()),
+                self.roles.clone(),
+                self.transition_reference_time,
+            )
+        };
+
+        let controller = roles.iter()
+            .find(|grant| grant.role == Role::Controller)
+            .map(|grant| grant.address.clone());
+
+        NameStateResponse {
+            name_owner,
+            bid_owner,
+            controller,
+            roles,
+            tld: None,
+            rate: self.rate,
+            begin_time: self.begin_time,
+            begin_deposit: self.begin_deposit,
+            current_deposit,
+            counter_delay_end,
+            transition_delay_end,
+            bid_delay_end,
+            expire_time,
+            grace_period_end,
+            transition_reference_time,
+        }
+    }
+}
 
 /// Mock querier for the auction contract
+///
+/// Answers queries from a map of name states. Names that are not present are
+/// reported as not found like the auction contract does.
 #[derive(Clone)]
 pub struct AuctionQuerier {
-    pub response: Option<NameStateResponse>,
+    pub config: ConfigResponse,
+    pub names: BTreeMap<String, NameStateResponse>,
+}
+
+impl Default for AuctionQuerier {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AuctionQuerier {
     pub fn new() -> Self {
         Self {
-            response: None,
+            config: mock_config(),
+            names: BTreeMap::new(),
         }
     }
+
+    pub fn with_config(mut self, config: ConfigResponse) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Add name with the given response
+    pub fn with_name(mut self, name: impl Into<String>, response: NameStateResponse) -> Self {
+        self.set_name(name, response);
+        self
+    }
+
+    /// Add name with response computed from state at current time
+    pub fn with_name_state(
+        mut self,
+        name: impl Into<String>,
+        state: &MockNameState,
+        current_time: Timestamp,
+    ) -> Self {
+        self.set_name_state(name, state, current_time);
+        self
+    }
+
+    /// Set response for name
+    pub fn set_name(&mut self, name: impl Into<String>, response: NameStateResponse) {
+        self.names.insert(name.into(), response);
+    }
+
+    /// Set response for name computed from state at current time
+    pub fn set_name_state(
+        &mut self,
+        name: impl Into<String>,
+        state: &MockNameState,
+        current_time: Timestamp,
+    ) {
+        let name = name.into();
+        let mut response = state.response(&self.config, current_time);
+        response.tld = split_name(&name).1.map(String::from);
+        self.set_name(name, response);
+    }
+
+    /// Remove name, e.g. to simulate pruning
+    pub fn remove_name(&mut self, name: &str) -> Option<NameStateResponse> {
+        self.names.remove(name)
+    }
+
+    fn name_state(&self, name: &str) -> Result<&NameStateResponse, StdError> {
+        self.names.get(name).ok_or_else(|| StdError::not_found(NAME_STATE_KIND))
+    }
+
+    fn all_name_states(
+        &self,
+        start_after: Option<&str>,
+        limit: Option<u32>,
+    ) -> AllNameStatesResponse {
+        let start = match start_after {
+            Some(start_after) => Bound::Excluded(start_after),
+            None => Bound::Unbounded,
+        };
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let names = self.names.range::<str, _>((start, Bound::Unbounded))
+            .take(limit)
+            .map(|(name, state)| NameStateItem {
+                name: name.clone(),
+                state: state.clone(),
+            })
+            .collect();
+
+        AllNameStatesResponse { names }
+    }
 }
 
-impl AuctionQuerier {
-    pub fn handle_query<T: CustomQuery>(&self, request: &QueryRequest<T>) -> Option<QuerierResult> {
-        let res = match &request {
-            QueryRequest::Wasm(WasmQuery::Smart { msg, .. }) => {
-                match from_binary(&msg).unwrap() {
-                    QueryMsg::GetNameState { .. } => {
-                        match &self.response {
-                            Some(response) => Ok(to_binary(response).into()),
-                            None => Err(SystemError::InvalidRequest {
-                                error: "Mock auction querier does not contain a response".to_string(),
-                                request: msg.as_slice().into(),
-                            }),
-                        }
-
-                    },
-                    _ => unimplemented!(),
-                }
+impl ContractQuerier for AuctionQuerier {
+    fn query(&self, msg: &Binary) -> QuerierResult {
+        let res = match from_binary(msg).unwrap() {
+            QueryMsg::Config {} => to_binary(&self.config),
+            QueryMsg::GetNameState { name } => {
+                self.name_state(&name).and_then(to_binary)
+            },
+            QueryMsg::GetNameRoles { name } => {
+                self.name_state(&name).and_then(|state| to_binary(&NameRolesResponse {
+                    name_owner: state.name_owner.clone(),
+                    roles: state.roles.clone(),
+                }))
+            },
+            QueryMsg::GetAllNameStates { start_after, limit } => {
+                to_binary(&self.all_name_states(start_after.as_deref(), limit))
             },
-            _ => return None,
+            _ => return SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "mock auction query".into(),
+            }),
         };
-        Some(res.into())
+        SystemResult::Ok(ContractResult::from(res.map_err(|err| err.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::{Querier, QuerierWrapper};
+
+    use crate::querier::query_name_state;
+    use crate::testing::MockQuerierBuilder;
+
+    #[test]
+    fn compute_response_from_state() {
+        let config = mock_config();
+        let state = MockNameState::new("bidder", 100, 1_000_000, 5_000)
+            .with_controller("controller");
+
+        // Counter delay with no previous owner
+        let response = state.response(&config, Timestamp::from_seconds(1_000_000));
+        assert_eq!(response.name_owner, None);
+        assert_eq!(response.bid_owner, Some(Addr::unchecked("bidder")));
+        assert!(response.roles.is_empty());
+        assert_eq!(response.counter_delay_end, Timestamp::from_seconds(1_604_800));
+        assert_eq!(response.transition_delay_end, Timestamp::from_seconds(1_000_000));
+        assert_eq!(response.expire_time, Some(Timestamp::from_seconds(5_320_000)));
+
+        // Owned after counter delay
+        let response = state.response(&config, Timestamp::from_seconds(1_604_800));
+        assert_eq!(response.name_owner, Some(Addr::unchecked("bidder")));
+        assert!(response.has_role(Role::Controller, &Addr::unchecked("controller")));
+        assert_eq!(response.current_deposit, Uint128::new(4_300));
+
+        // Expired
+        let response = state.response(&config, Timestamp::from_seconds(5_320_000));
+        assert_eq!(response.name_owner, None);
+        assert_eq!(response.current_deposit, Uint128::zero());
+
+        // Previous owner holds the name during counter delay
+        let state = MockNameState::new("bidder", 100, 1_000_000, 5_000)
+            .with_previous_owner("owner", vec![RoleGrant {
+                role: Role::Controller,
+                address: Addr::unchecked("owner_controller"),
+            }]);
+        let response = state.response(&config, Timestamp::from_seconds(1_000_000));
+        assert_eq!(response.name_owner, Some(Addr::unchecked("owner")));
+        assert!(response.has_role(Role::Controller, &Addr::unchecked("owner_controller")));
+        assert_eq!(response.transition_delay_end, Timestamp::from_seconds(3_419_200));
+        assert_eq!(response.transition_reference_time, Timestamp::zero());
+
+        // New owner takes over with a new transition reference time
+        let response = state.response(&config, Timestamp::from_seconds(1_604_800));
+        assert_eq!(response.name_owner, Some(Addr::unchecked("bidder")));
+        assert_eq!(response.transition_reference_time, Timestamp::from_seconds(1_000_000));
+    }
+
+    #[test]
+    fn query_names() {
+        let time = Timestamp::from_seconds(2_000_000);
+        let mut auction = AuctionQuerier::new();
+        for (index, name) in ["a", "b", "c"].iter().enumerate() {
+            let state = MockNameState::new(format!("owner_{}", index), 100, 1_000_000, 5_000);
+            auction.set_name_state(*name, &state, time);
+        }
+        let querier = MockQuerierBuilder::new().with_contract("auction", auction).build();
+        let wrapper = QuerierWrapper::new(&querier as &dyn Querier);
+
+        let state = query_name_state(&wrapper, &Addr::unchecked("auction"), "b").unwrap();
+        assert_eq!(state.name_owner, Some(Addr::unchecked("owner_1")));
+
+        let err = query_name_state(&wrapper, &Addr::unchecked("auction"), "d").unwrap_err();
+        assert!(err.to_string().contains("NameState not found"));
+
+        let res: AllNameStatesResponse = wrapper.query_wasm_smart("auction", &QueryMsg::GetAllNameStates {
+            start_after: Some("a".into()),
+            limit: Some(1),
+        }).unwrap();
+        assert_eq!(res.names.len(), 1);
+        assert_eq!(res.names[0].name, "b");
+
+        let config: ConfigResponse = wrapper.query_wasm_smart("auction", &QueryMsg::Config {}).unwrap();
+        assert_eq!(config, mock_config());
     }
 }
@@ -1,43 +1,120 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
 
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, CustomQuery, QuerierResult, QueryRequest, Uint128,
-    WasmQuery,
+    from_binary, to_binary, Addr, Binary, QuerierResult, SystemError,
+    SystemResult, Uint128,
 };
-use cw20::{Cw20QueryMsg, BalanceResponse};
+use cw20::{
+    AllAccountsResponse, AllowanceResponse, BalanceResponse, Cw20QueryMsg,
+    Expiration, TokenInfoResponse,
+};
+
+use super::querier::ContractQuerier;
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
 
 /// Mock querier for cw20 contracts
 #[derive(Clone)]
 pub struct Cw20Querier {
-    pub token_addr: Addr,
-    pub balances: HashMap<Addr, Uint128>,
+    pub token_info: TokenInfoResponse,
+    pub balances: BTreeMap<Addr, Uint128>,
+    /// Allowances by owner and spender
+    pub allowances: HashMap<(Addr, Addr), AllowanceResponse>,
+}
+
+impl Default for Cw20Querier {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cw20Querier {
-    pub fn new(token_addr: Addr) -> Self {
+    pub fn new() -> Self {
         Self {
-            token_addr,
-            balances: HashMap::new(),
+            token_info: TokenInfoResponse {
+                name: "Terranames".into(),
+                symbol: "TNS".into(),
+                decimals: 6,
+                total_supply: Uint128::zero(),
+            },
+            balances: BTreeMap::new(),
+            allowances: HashMap::new(),
         }
     }
 
-    pub fn handle_query<T: CustomQuery>(&self, request: &QueryRequest<T>) -> Option<QuerierResult> {
-        let res = match &request {
-            QueryRequest::Wasm(WasmQuery::Smart { msg, contract_addr }) => {
-                if contract_addr == &self.token_addr {
-                    match from_binary(msg).unwrap() {
-                        Cw20QueryMsg::Balance { address } => {
-                            let balance = self.balances.get(&Addr::unchecked(address)).cloned().unwrap_or_default();
-                            Ok(to_binary(&BalanceResponse { balance }).into())
-                        },
-                        _ => unimplemented!(),
-                    }
-                } else {
-                    return None;
-                }
+    pub fn with_token_info(mut self, token_info: TokenInfoResponse) -> Self {
+        self.token_info = token_info;
+        self
+    }
+
+    /// Set balance of address and update the total supply
+    pub fn with_balance(mut self, address: impl Into<String>, balance: u128) -> Self {
+        self.set_balance(address, balance);
+        self
+    }
+
+    /// Set allowance of spender on tokens of owner
+    pub fn with_allowance(
+        mut self,
+        owner: impl Into<String>,
+        spender: impl Into<String>,
+        allowance: u128,
+        expires: Expiration,
+    ) -> Self {
+        self.allowances.insert(
+            (Addr::unchecked(owner), Addr::unchecked(spender)),
+            AllowanceResponse { allowance: Uint128::new(allowance), expires },
+        );
+        self
+    }
+
+    /// Set balance of address and update the total supply
+    pub fn set_balance(&mut self, address: impl Into<String>, balance: u128) {
+        let balance = Uint128::new(balance);
+        let previous = self.balances.insert(Addr::unchecked(address), balance)
+            .unwrap_or_default();
+        self.token_info.total_supply = self.token_info.total_supply - previous + balance;
+    }
+
+    fn all_accounts(&self, start_after: Option<String>, limit: Option<u32>) -> AllAccountsResponse {
+        let start = match start_after {
+            Some(start_after) => Bound::Excluded(Addr::unchecked(start_after)),
+            None => Bound::Unbounded,
+        };
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let accounts = self.balances.range((start, Bound::Unbounded))
+            .take(limit)
+            .map(|(address, _)| address.to_string())
+            .collect();
+
+        AllAccountsResponse { accounts }
+    }
+}
+
+impl ContractQuerier for Cw20Querier {
+    fn query(&self, msg: &Binary) -> QuerierResult {
+        let res = match from_binary(msg).unwrap() {
+            Cw20QueryMsg::Balance { address } => {
+                let balance = self.balances.get(&Addr::unchecked(address)).cloned().unwrap_or_default();
+                to_binary(&BalanceResponse { balance })
+            },
+            Cw20QueryMsg::TokenInfo {} => to_binary(&self.token_info),
+            Cw20QueryMsg::Allowance { owner, spender } => {
+                let allowance = self.allowances
+                    .get(&(Addr::unchecked(owner), Addr::unchecked(spender)))
+                    .cloned()
+                    .unwrap_or_default();
+                to_binary(&allowance)
+            },
+            Cw20QueryMsg::AllAccounts { start_after, limit } => {
+                to_binary(&self.all_accounts(start_after, limit))
             },
-            _ => return None,
+            _ => return SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "mock cw20 query".into(),
+            }),
         };
-        Some(res.into())
+        SystemResult::Ok(res.into())
     }
 }
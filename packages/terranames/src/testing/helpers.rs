@@ -8,7 +8,7 @@ pub trait EnvBuilder {
 impl EnvBuilder for Env {
     /// Set block time for Env
     fn at_time(mut self, timestamp: u64) -> Self {
-        self.block.time = Timestamp::from_seconds(timestamp).into();
+        self.block.time = Timestamp::from_seconds(timestamp);
         self
     }
 }
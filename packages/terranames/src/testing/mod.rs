@@ -1,4 +1,12 @@
 pub mod auction;
 pub mod cw20;
 pub mod helpers;
+pub mod querier;
+#[cfg(feature = "terra")]
 pub mod terra;
+
+pub use querier::{
+    contract_querier, contract_queries, custom_queries, mock_dependencies,
+    ContractQuerier, MockCustomQuery, MockQuerier, MockQuerierBuilder,
+    QueryFilter,
+};
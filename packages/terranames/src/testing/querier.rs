@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+
+use cosmwasm_std::{
+    from_slice, to_binary, Binary, Coin, ContractResult, Deps, Empty, Env,
+    OwnedDeps, Querier, QuerierResult, QuerierWrapper, QueryRequest,
+    QueryResponse, SystemError, SystemResult, WasmQuery,
+};
+use cosmwasm_std::testing::{
+    MockApi, MockQuerier as CosmMockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+};
+use serde::de::DeserializeOwned;
+#[cfg(feature = "terra")]
+use terra_cosmwasm::TerraQueryWrapper;
+
+#[cfg(feature = "terra")]
+use super::terra::{OracleQuerier, TaxQuerier};
+
+/// Custom query type of the chain
+#[cfg(feature = "terra")]
+pub type MockCustomQuery = TerraQueryWrapper;
+/// Custom query type of the chain
+#[cfg(not(feature = "terra"))]
+pub type MockCustomQuery = Empty;
+
+/// Handler of smart queries sent to a mock contract
+pub trait ContractQuerier {
+    fn query(&self, msg: &Binary) -> QuerierResult;
+}
+
+impl<F> ContractQuerier for F
+where
+    F: Fn(&Binary) -> QuerierResult,
+{
+    fn query(&self, msg: &Binary) -> QuerierResult {
+        self(msg)
+    }
+}
+
+/// Predicate selecting queries to fail
+pub type QueryFilter = Box<dyn Fn(&QueryRequest<MockCustomQuery>) -> bool>;
+
+/// Return filter selecting smart and raw queries to contract
+pub fn contract_queries(contract_addr: impl Into<String>) -> QueryFilter {
+    let contract_addr = contract_addr.into();
+    Box::new(move |request| match request {
+        QueryRequest::Wasm(WasmQuery::Smart { contract_addr: addr, .. }) |
+        QueryRequest::Wasm(WasmQuery::Raw { contract_addr: addr, .. }) => addr == &contract_addr,
+        _ => false,
+    })
+}
+
+/// Return filter selecting custom queries (e.g. Terra tax queries)
+pub fn custom_queries() -> QueryFilter {
+    Box::new(|request| matches!(request, QueryRequest::Custom(_)))
+}
+
+/// Contract query entry point
+pub type QueryFn<M, E> = fn(Deps, Env, M) -> Result<QueryResponse, E>;
+
+/// Answer smart queries with the query entry point of a real contract
+///
+/// The contract state is read from the given storage which can be prepared by
+/// running the contract against separate mock dependencies. Queries are made
+/// at the block time of env.
+pub fn contract_querier<M, E>(
+    storage: MockStorage,
+    env: Env,
+    query: QueryFn<M, E>,
+) -> impl ContractQuerier
+where
+    M: DeserializeOwned,
+    E: ToString,
+{
+    move |msg: &Binary| -> QuerierResult {
+        let msg: M = match from_slice(msg) {
+            Ok(msg) => msg,
+            Err(err) => return SystemResult::Err(SystemError::InvalidRequest {
+                error: format!("Parsing query message: {}", err),
+                request: msg.clone(),
+            }),
+        };
+        let api = MockApi::default();
+        let querier = MockQuerier::default();
+        let deps = Deps {
+            storage: &storage,
+            api: &api,
+            querier: QuerierWrapper::new(&querier),
+        };
+        let res = query(deps, env.clone(), msg).map_err(|err| err.to_string());
+        SystemResult::Ok(ContractResult::from(res))
+    }
+}
+
+/// Mock querier that routes queries to the registered mock contracts
+///
+/// Smart and raw queries are routed by contract address, Terra custom queries
+/// go to the tax and oracle queriers (with the `terra` feature) and bank
+/// queries to the base querier.
+/// Queries matching any of the failure filters fail with a system error.
+pub struct MockQuerier {
+    #[cfg(feature = "terra")]
+    pub tax_querier: TaxQuerier,
+    #[cfg(feature = "terra")]
+    pub oracle_querier: OracleQuerier,
+    pub base_querier: CosmMockQuerier<Empty>,
+    contracts: HashMap<String, Box<dyn ContractQuerier>>,
+    raw: HashMap<String, HashMap<Binary, Binary>>,
+    failures: Vec<QueryFilter>,
+}
+
+impl Default for MockQuerier {
+    fn default() -> Self {
+        MockQuerierBuilder::new().build()
+    }
+}
+
+impl MockQuerier {
+    /// Register handler of smart queries to contract
+    ///
+    /// Replaces any handler previously registered for the address.
+    pub fn set_contract(
+        &mut self,
+        contract_addr: impl Into<String>,
+        querier: impl ContractQuerier + 'static,
+    ) {
+        self.contracts.insert(contract_addr.into(), Box::new(querier));
+    }
+
+    /// Set value returned for raw query of key in contract
+    pub fn set_raw(
+        &mut self,
+        contract_addr: impl Into<String>,
+        key: impl Into<Binary>,
+        value: impl Into<Binary>,
+    ) {
+        self.raw.entry(contract_addr.into())
+            .or_default()
+            .insert(key.into(), value.into());
+    }
+
+    /// Make queries matching filter fail
+    pub fn fail_queries(&mut self, filter: QueryFilter) {
+        self.failures.push(filter);
+    }
+
+    /// Remove all failure filters
+    pub fn clear_failures(&mut self) {
+        self.failures.clear();
+    }
+
+    fn no_such_contract(contract_addr: &str) -> QuerierResult {
+        SystemResult::Err(SystemError::NoSuchContract {
+            addr: contract_addr.into(),
+        })
+    }
+
+    fn handle_wasm_query(&self, request: &WasmQuery) -> QuerierResult {
+        match request {
+            WasmQuery::Smart { contract_addr, msg } => {
+                match self.contracts.get(contract_addr) {
+                    Some(querier) => querier.query(msg),
+                    None => Self::no_such_contract(contract_addr),
+                }
+            },
+            WasmQuery::Raw { contract_addr, key } => {
+                let contract_raw = self.raw.get(contract_addr);
+                if contract_raw.is_none() && !self.contracts.contains_key(contract_addr) {
+                    return Self::no_such_contract(contract_addr);
+                }
+
+                // Missing keys are returned as empty values like the chain does
+                let value = contract_raw
+                    .and_then(|values| values.get(key))
+                    .cloned()
+                    .unwrap_or_default();
+                SystemResult::Ok(ContractResult::Ok(value))
+            },
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "wasm".into(),
+            }),
+        }
+    }
+
+    pub fn handle_query(&self, request: &QueryRequest<MockCustomQuery>) -> QuerierResult {
+        if self.failures.iter().any(|filter| filter(request)) {
+            return SystemResult::Err(SystemError::Unknown {});
+        }
+
+        #[cfg(feature = "terra")]
+        if let Some(res) = self.tax_querier.handle_query(request) {
+            return res;
+        } else if let Some(res) = self.oracle_querier.handle_query(request) {
+            return res;
+        }
+
+        match request {
+            QueryRequest::Wasm(wasm_query) => self.handle_wasm_query(wasm_query),
+            QueryRequest::Bank(bank_query) => {
+                self.base_querier.handle_query(&QueryRequest::Bank(bank_query.clone()))
+            },
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "unknown".into(),
+            }),
+        }
+    }
+}
+
+impl Querier for MockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<MockCustomQuery> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                });
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+/// Builder of mock querier
+pub struct MockQuerierBuilder {
+    balances: Vec<(String, Vec<Coin>)>,
+    #[cfg(feature = "terra")]
+    tax_querier: TaxQuerier,
+    #[cfg(feature = "terra")]
+    oracle_querier: OracleQuerier,
+    contracts: HashMap<String, Box<dyn ContractQuerier>>,
+    raw: HashMap<String, HashMap<Binary, Binary>>,
+    failures: Vec<QueryFilter>,
+}
+
+impl Default for MockQuerierBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockQuerierBuilder {
+    pub fn new() -> Self {
+        Self {
+            balances: vec![],
+            #[cfg(feature = "terra")]
+            tax_querier: TaxQuerier::default(),
+            #[cfg(feature = "terra")]
+            oracle_querier: OracleQuerier::default(),
+            contracts: HashMap::new(),
+            raw: HashMap::new(),
+            failures: vec![],
+        }
+    }
+
+    /// Set bank balance of address
+    pub fn with_balance(mut self, addr: impl Into<String>, balance: &[Coin]) -> Self {
+        self.balances.push((addr.into(), balance.to_vec()));
+        self
+    }
+
+    #[cfg(feature = "terra")]
+    pub fn with_tax_querier(mut self, tax_querier: TaxQuerier) -> Self {
+        self.tax_querier = tax_querier;
+        self
+    }
+
+    #[cfg(feature = "terra")]
+    pub fn with_oracle_querier(mut self, oracle_querier: OracleQuerier) -> Self {
+        self.oracle_querier = oracle_querier;
+        self
+    }
+
+    /// Make queries matching filter fail
+    pub fn with_failure(mut self, filter: QueryFilter) -> Self {
+        self.failures.push(filter);
+        self
+    }
+
+    /// Register handler of smart queries to contract
+    pub fn with_contract(
+        mut self,
+        contract_addr: impl Into<String>,
+        querier: impl ContractQuerier + 'static,
+    ) -> Self {
+        self.contracts.insert(contract_addr.into(), Box::new(querier));
+        self
+    }
+
+    /// Register handler that answers smart queries to contract with a fixed
+    /// response regardless of the query message
+    pub fn with_response<T: serde::Serialize>(
+        self,
+        contract_addr: impl Into<String>,
+        response: &T,
+    ) -> Self {
+        let response = to_binary(response).unwrap();
+        self.with_contract(contract_addr, move |_: &Binary| -> QuerierResult {
+            SystemResult::Ok(ContractResult::Ok(response.clone()))
+        })
+    }
+
+    /// Set value returned for raw query of key in contract
+    pub fn with_raw(
+        mut self,
+        contract_addr: impl Into<String>,
+        key: impl Into<Binary>,
+        value: impl Into<Binary>,
+    ) -> Self {
+        self.raw.entry(contract_addr.into())
+            .or_default()
+            .insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> MockQuerier {
+        let balances: Vec<(&str, &[Coin])> = self.balances.iter()
+            .map(|(addr, balance)| (addr.as_str(), balance.as_slice()))
+            .collect();
+
+        MockQuerier {
+            #[cfg(feature = "terra")]
+            tax_querier: self.tax_querier,
+            #[cfg(feature = "terra")]
+            oracle_querier: self.oracle_querier,
+            base_querier: CosmMockQuerier::new(&balances),
+            contracts: self.contracts,
+            raw: self.raw,
+            failures: self.failures,
+        }
+    }
+
+    /// Build mock dependencies using the querier
+    pub fn build_deps(self) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: self.build(),
+        }
+    }
+}
+
+/// Create mock dependencies with the given balance of the mock contract
+pub fn mock_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    MockQuerierBuilder::new()
+        .with_balance(MOCK_CONTRACT_ADDR, contract_balance)
+        .build_deps()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::{
+        coins, Addr, StdError, StdResult, Storage, Uint128,
+    };
+    use cosmwasm_std::testing::mock_env;
+    use cw20::{AllAccountsResponse, Cw20Contract, Cw20QueryMsg, Expiration};
+    #[cfg(feature = "terra")]
+    use cosmwasm_std::{coin, Decimal};
+    #[cfg(feature = "terra")]
+    use terra_cosmwasm::TerraQuerier;
+
+    use crate::auction::{QueryMsg, NameStateResponse};
+    use crate::testing::cw20::Cw20Querier;
+
+    fn query_stored_value(deps: Deps, _env: Env, msg: String) -> StdResult<QueryResponse> {
+        match deps.storage.get(msg.as_bytes()) {
+            Some(value) => Ok(value.into()),
+            None => Err(StdError::not_found(msg)),
+        }
+    }
+
+    #[test]
+    fn route_queries_by_contract_address() {
+        let mut storage = MockStorage::new();
+        storage.set(b"key", b"\"value\"");
+
+        let querier = MockQuerierBuilder::new()
+            .with_balance("holder", &coins(100, "uusd"))
+            .with_contract("store", contract_querier(storage, mock_env(), query_stored_value))
+            .with_response("fixed", &"response")
+            .with_raw("raw", b"key", b"raw_value")
+            .build();
+        let wrapper = QuerierWrapper::new(&querier);
+
+        let value: String = wrapper.query_wasm_smart("store", &"key").unwrap();
+        assert_eq!(value, "value");
+        assert!(wrapper.query_wasm_smart::<String>("store", &"other").is_err());
+
+        let value: String = wrapper.query_wasm_smart("fixed", &"anything").unwrap();
+        assert_eq!(value, "response");
+        assert!(wrapper.query_wasm_smart::<String>("unknown", &"key").is_err());
+
+        let raw = querier.handle_query(&QueryRequest::Wasm(WasmQuery::Raw {
+            contract_addr: "raw".into(),
+            key: b"key".into(),
+        }));
+        assert_eq!(raw, SystemResult::Ok(ContractResult::Ok(Binary::from(b"raw_value"))));
+        let raw = querier.handle_query(&QueryRequest::Wasm(WasmQuery::Raw {
+            contract_addr: "raw".into(),
+            key: b"other".into(),
+        }));
+        assert_eq!(raw, SystemResult::Ok(ContractResult::Ok(Binary::default())));
+
+        let balance = wrapper.query_balance("holder", "uusd").unwrap();
+        assert_eq!(balance.amount, Uint128::new(100));
+    }
+
+    #[cfg(feature = "terra")]
+    #[test]
+    fn query_tax_rate() {
+        use crate::testing::terra::TaxQuerier;
+
+        let querier = MockQuerierBuilder::new()
+            .with_tax_querier(TaxQuerier::new(Decimal::percent(1), &[]))
+            .build();
+        let wrapper = QuerierWrapper::new(&querier);
+
+        let rate = TerraQuerier::new(&wrapper).query_tax_rate().unwrap().rate;
+        assert_eq!(rate, Decimal::percent(1));
+    }
+
+    #[test]
+    fn replace_contract_querier() {
+        let mut deps = mock_dependencies(&[]);
+        let msg = QueryMsg::GetNameState { name: "example".into() };
+        assert!(deps.as_ref().querier.query_wasm_smart::<NameStateResponse>("auction", &msg).is_err());
+
+        deps.querier.set_contract("auction", |_: &Binary| -> QuerierResult {
+            SystemResult::Ok(ContractResult::Err("not found".into()))
+        });
+        let err = deps.as_ref().querier
+            .query_wasm_smart::<NameStateResponse>("auction", &msg)
+            .unwrap_err();
+        assert!(err.to_string().contains("not found"));
+    }
+
+    #[test]
+    fn query_cw20_token() {
+        let token = Cw20Querier::new()
+            .with_balance("holder_1", 1_000)
+            .with_balance("holder_2", 500)
+            .with_balance("holder_3", 0)
+            .with_allowance("holder_1", "spender", 200, Expiration::AtHeight(100));
+        let querier = MockQuerierBuilder::new().with_contract("token", token).build();
+        let contract = Cw20Contract(Addr::unchecked("token"));
+
+        assert_eq!(contract.balance(&querier, "holder_2").unwrap(), Uint128::new(500));
+        assert_eq!(contract.balance(&querier, "other").unwrap(), Uint128::zero());
+        assert_eq!(contract.meta(&querier).unwrap().total_supply, Uint128::new(1_500));
+
+        let allowance = contract.allowance(&querier, "holder_1", "spender").unwrap();
+        assert_eq!(allowance.allowance, Uint128::new(200));
+        assert_eq!(allowance.expires, Expiration::AtHeight(100));
+        let allowance = contract.allowance(&querier, "holder_2", "spender").unwrap();
+        assert_eq!(allowance.allowance, Uint128::zero());
+
+        let res: AllAccountsResponse = QuerierWrapper::new(&querier).query_wasm_smart(
+            "token",
+            &Cw20QueryMsg::AllAccounts { start_after: Some("holder_1".into()), limit: Some(1) },
+        ).unwrap();
+        assert_eq!(res.accounts, vec!["holder_2".to_string()]);
+    }
+
+    #[cfg(feature = "terra")]
+    #[test]
+    fn query_oracle_and_market() {
+        use crate::testing::terra::OracleQuerier;
+
+        let querier = MockQuerierBuilder::new()
+            .with_oracle_querier(OracleQuerier::new(
+                &[("uusd", Decimal::percent(5_000)), ("ukrw", Decimal::percent(6_000_000))],
+                Decimal::percent(2),
+            ))
+            .build();
+        let wrapper = QuerierWrapper::new(&querier);
+        let terra = TerraQuerier::new(&wrapper);
+
+        let res = terra.query_exchange_rates("uusd", vec!["uluna", "ukrw"]).unwrap();
+        assert_eq!(res.exchange_rates[0].exchange_rate, Decimal::permille(20));
+        assert_eq!(res.exchange_rates[1].exchange_rate, Decimal::percent(120_000));
+        assert!(terra.query_exchange_rates("uusd", vec!["uabc"]).is_err());
+
+        let res = terra.query_swap(coin(1_000, "uluna"), "uusd").unwrap();
+        assert_eq!(res.receive, coin(49_000, "uusd"));
+        assert!(terra.query_swap(coin(1_000, "uabc"), "uusd").is_err());
+    }
+
+    #[test]
+    fn inject_query_failures() {
+        let mut deps = MockQuerierBuilder::new()
+            .with_contract("token", Cw20Querier::new())
+            .with_failure(Box::new(|request| matches!(request, QueryRequest::Bank(_))))
+            .build_deps();
+        let contract = Cw20Contract(Addr::unchecked("token"));
+
+        assert!(deps.as_ref().querier.query_balance("holder", "uusd").is_err());
+        assert!(contract.balance(&deps.querier, "holder").is_ok());
+
+        deps.querier.fail_queries(contract_queries("token"));
+        assert!(contract.balance(&deps.querier, "holder").is_err());
+
+        deps.querier.clear_failures();
+        assert!(deps.as_ref().querier.query_balance("holder", "uusd").is_ok());
+        assert!(contract.balance(&deps.querier, "holder").is_ok());
+    }
+}
@@ -2,10 +2,12 @@ use std::collections::HashMap;
 use std::iter::FromIterator;
 
 use cosmwasm_std::{
-    to_binary, Decimal, QuerierResult, QueryRequest, Uint128,
+    to_binary, Coin, ContractResult, Decimal, QuerierResult, QueryRequest,
+    SystemResult, Uint128,
 };
 use terra_cosmwasm::{
-    TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute,
+    ExchangeRateItem, ExchangeRatesResponse, SwapResponse, TaxCapResponse,
+    TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute,
 };
 
 // Mock querier for queries to Terra tax state
@@ -37,28 +39,26 @@ impl TaxQuerier {
 
 pub(crate) fn caps_to_map(caps: &[(&str, &Uint128)]) -> HashMap<String, Uint128> {
     HashMap::from_iter(
-        caps.into_iter().map(|(denom, &value)| (denom.to_string(), value)),
+        caps.iter().map(|(denom, &value)| (denom.to_string(), value)),
     )
 }
 
 impl TaxQuerier {
     pub fn handle_query(&self, request: &QueryRequest<TerraQueryWrapper>) -> Option<QuerierResult> {
         let res = match &request {
-            QueryRequest::Custom(TerraQueryWrapper { route, query_data }) => {
-                match route {
-                    TerraRoute::Treasury => {
-                        match query_data {
-                            TerraQuery::TaxRate {} => {
-                                Ok(to_binary(&TaxRateResponse {
-                                    rate: self.rate,
-                                }).into())
-                            },
-                            TerraQuery::TaxCap { denom } => {
-                                let cap = self.caps.get(denom).copied().unwrap_or_default();
-                                Ok(to_binary(&TaxCapResponse { cap }).into())
-                            },
-                            _ => return None,
-                        }
+            QueryRequest::Custom(TerraQueryWrapper {
+                route: TerraRoute::Treasury,
+                query_data,
+            }) => {
+                match query_data {
+                    TerraQuery::TaxRate {} => {
+                        Ok(to_binary(&TaxRateResponse {
+                            rate: self.rate,
+                        }).into())
+                    },
+                    TerraQuery::TaxCap { denom } => {
+                        let cap = self.caps.get(denom).copied().unwrap_or_default();
+                        Ok(to_binary(&TaxCapResponse { cap }).into())
                     },
                     _ => return None,
                 }
@@ -68,3 +68,112 @@ impl TaxQuerier {
         Some(res.into())
     }
 }
+
+/// Denomination that oracle exchange rates are quoted against
+pub const LUNA_DENOM: &str = "uluna";
+
+/// Number of atomic units in one Decimal
+const DECIMAL_FRACTIONAL: u128 = 1_000_000_000_000_000_000;
+
+// Mock querier for queries to Terra oracle exchange rates
+//
+// Also simulates market swaps at the oracle rates minus the spread.
+#[derive(Clone)]
+pub struct OracleQuerier {
+    /// Price of one Luna in each denomination
+    pub exchange_rates: HashMap<String, Decimal>,
+    /// Share of the swapped amount that is kept as spread
+    pub swap_spread: Decimal,
+}
+
+impl Default for OracleQuerier {
+    fn default() -> Self {
+        Self::new(&[], Decimal::zero())
+    }
+}
+
+impl OracleQuerier {
+    pub fn new(exchange_rates: &[(&str, Decimal)], swap_spread: Decimal) -> Self {
+        OracleQuerier {
+            exchange_rates: HashMap::from_iter(
+                exchange_rates.iter().map(|(denom, rate)| (denom.to_string(), *rate)),
+            ),
+            swap_spread,
+        }
+    }
+
+    /// Return price of one Luna in denom
+    fn luna_rate(&self, denom: &str) -> Option<Decimal> {
+        if denom == LUNA_DENOM {
+            Some(Decimal::one())
+        } else {
+            self.exchange_rates.get(denom).copied()
+        }
+    }
+
+    /// Return price of one unit of base denom in quote denom
+    pub fn exchange_rate(&self, base_denom: &str, quote_denom: &str) -> Option<Decimal> {
+        let base_rate = Uint128::new(DECIMAL_FRACTIONAL) * self.luna_rate(base_denom)?;
+        let quote_rate = Uint128::new(DECIMAL_FRACTIONAL) * self.luna_rate(quote_denom)?;
+        if base_rate.is_zero() {
+            return None;
+        }
+        // Decimal::from_ratio overflows for large rates so the atomics are
+        // computed directly
+        let atomics = quote_rate.multiply_ratio(DECIMAL_FRACTIONAL, base_rate).u128();
+        let rate = format!("{}.{:018}", atomics / DECIMAL_FRACTIONAL, atomics % DECIMAL_FRACTIONAL);
+        Some(rate.parse().unwrap())
+    }
+
+    /// Return amount received when swapping offer coin to ask denom
+    pub fn swap(&self, offer_coin: &Coin, ask_denom: &str) -> Option<Coin> {
+        let rate = self.exchange_rate(&offer_coin.denom, ask_denom)?;
+        let amount = offer_coin.amount * rate;
+        Some(Coin {
+            denom: ask_denom.into(),
+            amount: amount - amount * self.swap_spread,
+        })
+    }
+
+    fn unknown_denom() -> QuerierResult {
+        SystemResult::Ok(ContractResult::Err("unknown denom".into()))
+    }
+
+    pub fn handle_query(&self, request: &QueryRequest<TerraQueryWrapper>) -> Option<QuerierResult> {
+        let res = match &request {
+            QueryRequest::Custom(TerraQueryWrapper {
+                route: TerraRoute::Oracle,
+                query_data: TerraQuery::ExchangeRates { base_denom, quote_denoms },
+            }) => {
+                let exchange_rates: Option<Vec<_>> = quote_denoms.iter()
+                    .map(|quote_denom| {
+                        self.exchange_rate(base_denom, quote_denom).map(|exchange_rate| {
+                            ExchangeRateItem {
+                                quote_denom: quote_denom.clone(),
+                                exchange_rate,
+                            }
+                        })
+                    })
+                    .collect();
+                match exchange_rates {
+                    Some(exchange_rates) => to_binary(&ExchangeRatesResponse {
+                        base_denom: base_denom.clone(),
+                        exchange_rates,
+                    }),
+                    None => return Some(Self::unknown_denom()),
+                }
+            },
+            QueryRequest::Custom(TerraQueryWrapper {
+                route: TerraRoute::Market,
+                query_data: TerraQuery::Swap { offer_coin, ask_denom },
+            }) => {
+                match self.swap(offer_coin, ask_denom) {
+                    Some(receive) => to_binary(&SwapResponse { receive }),
+                    None => return Some(Self::unknown_denom()),
+                }
+            },
+            _ => return None,
+        };
+        Some(SystemResult::Ok(res.into()))
+    }
+}
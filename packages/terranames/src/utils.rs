@@ -1,13 +1,24 @@
-use std::ops::Add;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
 
-use cosmwasm_std::{Timestamp as CWTimestamp, OverflowError, Uint64};
+use cosmwasm_std::{
+    OverflowError, StdError, Timestamp as CWTimestamp, Uint64,
+};
 use schemars::JsonSchema;
+use serde::de::{self, Deserializer, Visitor};
 use serde::{Deserialize, Serialize};
 
+const SECONDS_PER_DAY: u64 = 86_400;
+
 /// Timestamp (in seconds)
+///
+/// Serialized as a string of seconds. Deserializes from a string of seconds
+/// or an RFC 3339 timestamp such as "2021-08-01T00:00:00Z".
 #[derive(
-    Serialize, Deserialize, Copy, Clone, Default, Debug, PartialEq, Eq,
-    PartialOrd, Ord, JsonSchema,
+    Serialize, Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord,
+    JsonSchema,
 )]
 pub struct Timestamp(Uint64);
 
@@ -35,13 +46,77 @@ impl Timestamp {
     pub fn checked_sub(self, other: Self) -> Result<Timedelta, OverflowError> {
         self.0.checked_sub(other.0).map(Timedelta)
     }
+
+    pub fn checked_sub_delta(self, other: Timedelta) -> Result<Timestamp, OverflowError> {
+        self.0.checked_sub(other.0).map(Timestamp)
+    }
 }
 
+// Operators return a Result instead of panicking on overflow so that they
+// can be used with `?` in contract handlers.
+
 impl Add<Timedelta> for Timestamp {
-    type Output = Timestamp;
+    type Output = Result<Timestamp, OverflowError>;
+
+    fn add(self, other: Timedelta) -> Self::Output {
+        self.checked_add(other)
+    }
+}
+
+impl Sub for Timestamp {
+    type Output = Result<Timedelta, OverflowError>;
+
+    fn sub(self, other: Self) -> Self::Output {
+        self.checked_sub(other)
+    }
+}
+
+impl Sub<Timedelta> for Timestamp {
+    type Output = Result<Timestamp, OverflowError>;
+
+    fn sub(self, other: Timedelta) -> Self::Output {
+        self.checked_sub_delta(other)
+    }
+}
+
+/// Format as RFC 3339 in UTC, e.g. "2021-08-01T00:00:00Z"
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.value();
+        let (year, month, day) = civil_from_days(seconds / SECONDS_PER_DAY);
+        let seconds_of_day = seconds % SECONDS_PER_DAY;
+        write!(
+            f, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year, month, day,
+            seconds_of_day / 3600, seconds_of_day / 60 % 60, seconds_of_day % 60,
+        )
+    }
+}
+
+/// Parse a string of seconds or an RFC 3339 timestamp
+///
+/// Fractional seconds are truncated and the offset is applied so the result
+/// is always in UTC. Times before the unix epoch are rejected.
+impl FromStr for Timestamp {
+    type Err = StdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if is_digits(s) {
+            return s.parse().map(Timestamp::from_seconds)
+                .map_err(|_| StdError::parse_err("Timestamp", "Value out of range"));
+        }
+        parse_rfc3339(s)
+            .map(Timestamp::from_seconds)
+            .ok_or_else(|| StdError::parse_err("Timestamp", format!("Invalid RFC 3339 timestamp: {}", s)))
+    }
+}
 
-    fn add(self, rhs: Timedelta) -> Self::Output {
-        Timestamp(self.0 + rhs.0)
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(SecondsVisitor::<Timestamp>::new("a timestamp"))
     }
 }
 
@@ -58,13 +133,24 @@ impl From<Timestamp> for CWTimestamp {
 }
 
 /// Timedelta (in seconds)
+///
+/// Serialized as a string of seconds. Deserializes from a string of seconds
+/// or a duration such as "30d", "12h" or "1y6h".
 #[derive(
-    Serialize, Deserialize, Copy, Clone, Default, Debug, PartialEq, Eq,
-    PartialOrd, Ord, JsonSchema,
+    Serialize, Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord,
+    JsonSchema,
 )]
 pub struct Timedelta(Uint64);
 
 impl Timedelta {
+    pub const SECOND: Timedelta = Timedelta::from_seconds(1);
+    pub const MINUTE: Timedelta = Timedelta::from_seconds(60);
+    pub const HOUR: Timedelta = Timedelta::from_seconds(3600);
+    pub const DAY: Timedelta = Timedelta::from_seconds(SECONDS_PER_DAY);
+    pub const WEEK: Timedelta = Timedelta::from_seconds(7 * SECONDS_PER_DAY);
+    /// Average length of a gregorian year (365.2425 days)
+    pub const YEAR: Timedelta = Timedelta::from_seconds(31_556_952);
+
     pub const fn from_seconds(seconds: u64) -> Timedelta {
         Timedelta(Uint64::new(seconds))
     }
@@ -80,13 +166,126 @@ impl Timedelta {
     pub fn is_zero(&self) -> bool {
         self.0.is_zero()
     }
+
+    pub fn checked_add(self, other: Self) -> Result<Timedelta, OverflowError> {
+        self.0.checked_add(other.0).map(Timedelta)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Timedelta, OverflowError> {
+        self.0.checked_sub(other.0).map(Timedelta)
+    }
+
+    pub fn checked_mul(self, factor: u64) -> Result<Timedelta, OverflowError> {
+        self.0.checked_mul(Uint64::new(factor)).map(Timedelta)
+    }
 }
 
 impl Add for Timedelta {
-    type Output = Self;
+    type Output = Result<Timedelta, OverflowError>;
+
+    fn add(self, other: Self) -> Self::Output {
+        self.checked_add(other)
+    }
+}
+
+impl Sub for Timedelta {
+    type Output = Result<Timedelta, OverflowError>;
+
+    fn sub(self, other: Self) -> Self::Output {
+        self.checked_sub(other)
+    }
+}
+
+impl Mul<u64> for Timedelta {
+    type Output = Result<Timedelta, OverflowError>;
+
+    fn mul(self, factor: u64) -> Self::Output {
+        self.checked_mul(factor)
+    }
+}
+
+/// Units accepted by the duration format in order of decreasing length
+const DURATION_UNITS: [(&str, Timedelta); 6] = [
+    ("y", Timedelta::YEAR),
+    ("w", Timedelta::WEEK),
+    ("d", Timedelta::DAY),
+    ("h", Timedelta::HOUR),
+    ("m", Timedelta::MINUTE),
+    ("s", Timedelta::SECOND),
+];
+
+/// Format as a duration, e.g. "1y", "30d" or "1d12h"
+///
+/// Weeks are never used when formatting. The output parses back to the same
+/// value.
+impl fmt::Display for Timedelta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0s");
+        }
+        let mut remainder = self.value();
+        for (unit, length) in DURATION_UNITS.iter().filter(|(unit, _)| *unit != "w") {
+            let count = remainder / length.value();
+            if count > 0 {
+                write!(f, "{}{}", count, unit)?;
+                remainder %= length.value();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a string of seconds or a duration such as "30d", "12h" or "1y6h"
+///
+/// Supported units are y (365.2425 days), w, d, h, m and s. Components may be
+/// separated by whitespace and are summed.
+impl FromStr for Timedelta {
+    type Err = StdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_err = |msg: &str| StdError::parse_err("Timedelta", format!("{}: {}", msg, s));
+        if is_digits(s) {
+            return s.parse().map(Timedelta::from_seconds)
+                .map_err(|_| parse_err("Value out of range"));
+        }
 
-    fn add(self, rhs: Self) -> Self::Output {
-        Timedelta(self.0 + rhs.0)
+        let mut rest = s.trim_start();
+        if rest.is_empty() {
+            return Err(parse_err("Empty duration"));
+        }
+
+        let mut total = Timedelta::zero();
+        while !rest.is_empty() {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            if digits == 0 {
+                return Err(parse_err("Expected a number"));
+            }
+            let count: u64 = rest[..digits].parse()
+                .map_err(|_| parse_err("Value out of range"))?;
+            rest = &rest[digits..];
+
+            let unit_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+            let length = DURATION_UNITS.iter()
+                .find(|(unit, _)| *unit == &rest[..unit_len])
+                .map(|(_, length)| *length)
+                .ok_or_else(|| parse_err("Invalid unit"))?;
+            rest = rest[unit_len..].trim_start();
+
+            total = length.checked_mul(count)
+                .and_then(|delta| total.checked_add(delta))
+                .map_err(|_| parse_err("Value out of range"))?;
+        }
+
+        Ok(total)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timedelta {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(SecondsVisitor::<Timedelta>::new("a duration"))
     }
 }
 
@@ -108,3 +307,356 @@ impl From<CWTimestamp> for Timestamp {
         Timestamp::from_seconds(other.nanos() / 1_000_000_000)
     }
 }
+
+/// Visitor accepting any string format of T or an integer of seconds
+///
+/// Timestamp and Timedelta request a string since deserialize_any is not
+/// implemented by the JSON deserializer used by contracts (serde-json-wasm
+/// 0.3 panics). Deserializers that forward to deserialize_any, such as the
+/// serde value deserializers, can also hand over plain integers.
+struct SecondsVisitor<T> {
+    expecting: &'static str,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T> SecondsVisitor<T> {
+    fn new(expecting: &'static str) -> Self {
+        SecondsVisitor { expecting, marker: std::marker::PhantomData }
+    }
+}
+
+impl<'de, T> Visitor<'de> for SecondsVisitor<T>
+where
+    T: FromStr<Err = StdError>,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "integer or string with {}", self.expecting)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        value.parse().map_err(|err: StdError| E::custom(err.to_string()))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<T, E> {
+        self.visit_str(&value.to_string())
+    }
+}
+
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_leap_year(year: u64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Return days since the unix epoch (negative before 1970-01-01)
+///
+/// Based on the days_from_civil algorithm by Howard Hinnant. Year must be at
+/// least 1.
+fn days_from_civil(year: u64, month: u64, day: u64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    (era * 146_097 + day_of_era) as i64 - 719_468
+}
+
+/// Return (year, month, day) of the given number of days since the unix epoch
+///
+/// Inverse of days_from_civil.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (
+        day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096
+    ) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Parse RFC 3339 timestamp into seconds since the unix epoch
+fn parse_rfc3339(s: &str) -> Option<u64> {
+    fn number(s: &str, range: std::ops::Range<usize>) -> Option<u64> {
+        let digits = s.get(range)?;
+        if is_digits(digits) { digits.parse().ok() } else { None }
+    }
+
+    let bytes = s.as_bytes();
+    if bytes.len() < 20
+        || bytes[4] != b'-' || bytes[7] != b'-'
+        || !matches!(bytes[10], b'T' | b't' | b' ')
+        || bytes[13] != b':' || bytes[16] != b':'
+    {
+        return None;
+    }
+
+    let year = number(s, 0..4)?;
+    let month = number(s, 5..7)?;
+    let day = number(s, 8..10)?;
+    let hour = number(s, 11..13)?;
+    let minute = number(s, 14..16)?;
+    let second = number(s, 17..19)?;
+    if year == 0 || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month)
+        || hour > 23 || minute > 59 || second > 59
+    {
+        return None;
+    }
+
+    // Fractional seconds are truncated
+    let mut rest = &s[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.find(|c: char| !c.is_ascii_digit()).unwrap_or(fraction.len());
+        if digits == 0 {
+            return None;
+        }
+        rest = &fraction[digits..];
+    }
+
+    let local_seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY as i64
+        + (hour * 3600 + minute * 60 + second) as i64;
+    let offset = match rest.as_bytes() {
+        b"Z" | b"z" => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let offset_hour = number(rest, 1..3)?;
+            let offset_minute = number(rest, 4..6)?;
+            if offset_hour > 23 || offset_minute > 59 {
+                return None;
+            }
+            let offset = (offset_hour * 3600 + offset_minute * 60) as i64;
+            if *sign == b'+' { offset } else { -offset }
+        },
+        _ => return None,
+    };
+    u64::try_from(local_seconds - offset).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::{from_slice, to_vec};
+    use proptest::prelude::*;
+
+    #[test]
+    fn parse_timedelta() {
+        assert_eq!("30d".parse::<Timedelta>().unwrap(), Timedelta::from_seconds(2_592_000));
+        assert_eq!("1y".parse::<Timedelta>().unwrap(), Timedelta::from_seconds(31_556_952));
+        assert_eq!("12h".parse::<Timedelta>().unwrap(), Timedelta::from_seconds(43_200));
+        assert_eq!("2w".parse::<Timedelta>().unwrap(), Timedelta::from_seconds(14 * 86_400));
+        assert_eq!("1d 12h".parse::<Timedelta>().unwrap(), Timedelta::from_seconds(129_600));
+        assert_eq!("90m30s".parse::<Timedelta>().unwrap(), Timedelta::from_seconds(5_430));
+        assert_eq!("31536000".parse::<Timedelta>().unwrap(), Timedelta::from_seconds(31_536_000));
+        assert_eq!("0s".parse::<Timedelta>().unwrap(), Timedelta::zero());
+
+        for invalid in &["", " ", "d", "30 days", "1.5h", "-1d", "12x", "h12"] {
+            assert!(invalid.parse::<Timedelta>().is_err(), "{:?} should not parse", invalid);
+        }
+        assert!("584554049254y".parse::<Timedelta>().is_err());
+        assert!("18446744073709551616".parse::<Timedelta>().is_err());
+    }
+
+    #[test]
+    fn format_timedelta() {
+        assert_eq!(Timedelta::zero().to_string(), "0s");
+        assert_eq!(Timedelta::from_seconds(2_592_000).to_string(), "30d");
+        assert_eq!(Timedelta::YEAR.to_string(), "1y");
+        assert_eq!(Timedelta::from_seconds(129_600).to_string(), "1d12h");
+        assert_eq!(Timedelta::from_seconds(15_778_476).to_string(), "182d14h54m36s");
+    }
+
+    #[test]
+    fn parse_and_format_timestamp() {
+        let timestamp: Timestamp = "2021-08-01T00:00:00Z".parse().unwrap();
+        assert_eq!(timestamp, Timestamp::from_seconds(1_627_776_000));
+        assert_eq!(timestamp.to_string(), "2021-08-01T00:00:00Z");
+
+        assert_eq!(Timestamp::zero().to_string(), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            "2024-02-29T12:34:56.789+02:00".parse::<Timestamp>().unwrap(),
+            Timestamp::from_seconds(1_709_202_896),
+        );
+        assert_eq!(
+            "1969-12-31T23:00:00-01:00".parse::<Timestamp>().unwrap(),
+            Timestamp::zero(),
+        );
+        assert_eq!("1627776000".parse::<Timestamp>().unwrap(), timestamp);
+
+        for invalid in &[
+            "2021-08-01", "2021-08-01T00:00:00", "2021-13-01T00:00:00Z",
+            "2023-02-29T00:00:00Z", "2021-08-01T24:00:00Z", "2021-08-01T00:00:00.Z",
+            "1969-12-31T23:59:59Z", "1970-01-01T00:00:00+00:01", "2021-08-01T00:00:00+0200",
+        ] {
+            assert!(invalid.parse::<Timestamp>().is_err(), "{:?} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn checked_operations() {
+        let timestamp = Timestamp::from_seconds(1000);
+        assert_eq!(timestamp.checked_sub(Timestamp::from_seconds(400)).unwrap(), Timedelta::from_seconds(600));
+        assert_eq!(timestamp.checked_sub_delta(Timedelta::from_seconds(400)).unwrap(), Timestamp::from_seconds(600));
+        assert_eq!(timestamp.checked_add(Timedelta::from_seconds(400)).unwrap(), Timestamp::from_seconds(1400));
+        assert_eq!(Timedelta::DAY.checked_sub(Timedelta::HOUR).unwrap(), Timedelta::from_seconds(82_800));
+        assert_eq!(Timedelta::WEEK.checked_mul(2).unwrap(), Timedelta::from_seconds(1_209_600));
+
+        assert!(Timestamp::from_seconds(u64::MAX).checked_add(Timedelta::from_seconds(1)).is_err());
+
+        assert!(timestamp.checked_sub_delta(Timedelta::from_seconds(1001)).is_err());
+        assert!(Timedelta::YEAR.checked_mul(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn operators() {
+        let timestamp = Timestamp::from_seconds(1000);
+        assert_eq!(timestamp - Timestamp::from_seconds(400), Ok(Timedelta::from_seconds(600)));
+        assert_eq!(timestamp - Timedelta::from_seconds(400), Ok(Timestamp::from_seconds(600)));
+        assert_eq!(timestamp + Timedelta::from_seconds(400), Ok(Timestamp::from_seconds(1400)));
+        assert_eq!(Timedelta::DAY - Timedelta::HOUR, Ok(Timedelta::from_seconds(82_800)));
+        assert_eq!(Timedelta::DAY + Timedelta::HOUR, Ok(Timedelta::from_seconds(90_000)));
+        assert_eq!(Timedelta::WEEK * 2, Ok(Timedelta::from_seconds(1_209_600)));
+
+        assert!((timestamp - Timestamp::from_seconds(1001)).is_err());
+        assert!((timestamp - Timedelta::from_seconds(1001)).is_err());
+        assert!((Timestamp::from_seconds(u64::MAX) + Timedelta::SECOND).is_err());
+        assert!((Timedelta::HOUR - Timedelta::DAY).is_err());
+        assert!((Timedelta::YEAR * u64::MAX).is_err());
+    }
+
+    #[test]
+    fn deserialize_from_string() {
+        #[derive(Deserialize, Serialize, Debug, PartialEq)]
+        struct Msg {
+            delta: Timedelta,
+            time: Timestamp,
+        }
+
+        let expected = Msg {
+            delta: Timedelta::from_seconds(31_536_000),
+            time: Timestamp::from_seconds(1_627_776_000),
+        };
+        let msg: Msg = from_slice(br#"{"delta":"31536000","time":"1627776000"}"#).unwrap();
+        assert_eq!(msg, expected);
+        let msg: Msg = from_slice(br#"{"delta":"365d","time":"2021-08-01T00:00:00Z"}"#).unwrap();
+        assert_eq!(msg, expected);
+
+        // Serialized as before for backwards compatibility
+        assert_eq!(
+            to_vec(&expected).unwrap(),
+            br#"{"delta":"31536000","time":"1627776000"}"#.to_vec(),
+        );
+
+        assert!(from_slice::<Msg>(br#"{"delta":"365 days","time":"0"}"#).is_err());
+        assert!(from_slice::<Msg>(br#"{"delta":"-1","time":"0"}"#).is_err());
+    }
+
+    #[test]
+    fn deserialize_from_integer_or_string() {
+        use serde::de::value::{Error, StrDeserializer, U64Deserializer};
+
+        let from_integer = Timedelta::deserialize(U64Deserializer::<Error>::new(3600)).unwrap();
+        let from_string = Timedelta::deserialize(StrDeserializer::<Error>::new("3600")).unwrap();
+        assert_eq!(from_integer, Timedelta::HOUR);
+        assert_eq!(from_string, Timedelta::HOUR);
+        assert_eq!(Timedelta::deserialize(StrDeserializer::<Error>::new("1h")).unwrap(), Timedelta::HOUR);
+
+        let from_integer = Timestamp::deserialize(U64Deserializer::<Error>::new(1_627_776_000)).unwrap();
+        let from_string = Timestamp::deserialize(StrDeserializer::<Error>::new("1627776000")).unwrap();
+        assert_eq!(from_integer, Timestamp::from_seconds(1_627_776_000));
+        assert_eq!(from_string, Timestamp::from_seconds(1_627_776_000));
+
+        // Contract messages still require a string but must not panic
+        assert!(from_slice::<Timedelta>(b"3600").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn timestamp_add_near_max_is_error(
+            timestamp in (u64::MAX - 1_000_000)..=u64::MAX,
+            delta in 1_000_001u64..=u64::MAX,
+        ) {
+            let timestamp = Timestamp::from_seconds(timestamp);
+            let delta = Timedelta::from_seconds(delta);
+            prop_assert!(timestamp.checked_add(delta).is_err());
+        }
+
+        #[test]
+        fn timedelta_add_near_max_is_error(
+            a in (u64::MAX - 1_000_000)..=u64::MAX,
+            b in 1_000_001u64..=u64::MAX,
+        ) {
+            let a = Timedelta::from_seconds(a);
+            let b = Timedelta::from_seconds(b);
+            prop_assert!(a.checked_add(b).is_err());
+            prop_assert!(b.checked_add(a).is_err());
+        }
+
+        #[test]
+        fn checked_add_matches_u64(a in any::<u64>(), b in any::<u64>()) {
+            let expected = a.checked_add(b);
+            let timestamp = Timestamp::from_seconds(a).checked_add(Timedelta::from_seconds(b));
+            prop_assert_eq!(timestamp.ok().map(u64::from), expected);
+            let delta = Timedelta::from_seconds(a).checked_add(Timedelta::from_seconds(b));
+            prop_assert_eq!(delta.ok().map(u64::from), expected);
+        }
+
+        #[test]
+        fn timedelta_format_round_trip(seconds in any::<u64>()) {
+            let delta = Timedelta::from_seconds(seconds);
+            prop_assert_eq!(delta.to_string().parse::<Timedelta>().unwrap(), delta);
+        }
+
+        #[test]
+        fn timestamp_format_round_trip(seconds in 0u64..253_402_300_800) {
+            let timestamp = Timestamp::from_seconds(seconds);
+            prop_assert_eq!(timestamp.to_string().parse::<Timestamp>().unwrap(), timestamp);
+        }
+
+        #[test]
+        fn timedelta_parse_never_panics(s in "[0-9ywdhms ]{0,40}") {
+            let _ = s.parse::<Timedelta>();
+        }
+
+        #[test]
+        fn timestamp_parse_never_panics(s in "[0-9T:Z.+-]{0,40}") {
+            let _ = s.parse::<Timestamp>();
+        }
+
+        #[test]
+        fn checked_sub_matches_u64(a in any::<u64>(), b in any::<u64>()) {
+            let expected = a.checked_sub(b);
+            let delta = Timestamp::from_seconds(a).checked_sub(Timestamp::from_seconds(b));
+            prop_assert_eq!(delta.ok().map(u64::from), expected);
+            let delta = Timedelta::from_seconds(a).checked_sub(Timedelta::from_seconds(b));
+            prop_assert_eq!(delta.ok().map(u64::from), expected);
+        }
+
+        #[test]
+        fn operators_match_u64(a in any::<u64>(), b in any::<u64>()) {
+            let delta = Timestamp::from_seconds(a) - Timestamp::from_seconds(b);
+            prop_assert_eq!(delta.ok().map(u64::from), a.checked_sub(b));
+            let timestamp = Timestamp::from_seconds(a) - Timedelta::from_seconds(b);
+            prop_assert_eq!(timestamp.ok().map(u64::from), a.checked_sub(b));
+            let delta = Timedelta::from_seconds(a) * b;
+            prop_assert_eq!(delta.ok().map(u64::from), a.checked_mul(b));
+        }
+    }
+}
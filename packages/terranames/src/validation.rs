@@ -0,0 +1,277 @@
+//! Validation of record values that does not depend on the chain
+//!
+//! Addresses of the chain that the contracts run on are validated through
+//! the API instead.
+
+use sha3::{Digest, Keccak256};
+
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+const BECH32_CHECKSUM_LENGTH: usize = 6;
+
+fn bech32_polymod(values: impl Iterator<Item = u8>) -> u32 {
+    let mut checksum: u32 = 1;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ffffff) << 5 ^ value as u32;
+        for (i, generator) in BECH32_GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Return whether value is a bech32 string with the given prefix
+///
+/// The prefix is the human-readable part (e.g. `cosmos`). Mixed case strings
+/// are not valid.
+pub fn is_valid_bech32(value: &str, prefix: &str) -> bool {
+    if value.bytes().any(|c| c.is_ascii_uppercase()) &&
+            value.bytes().any(|c| c.is_ascii_lowercase()) {
+        return false;
+    }
+    let value = value.to_ascii_lowercase();
+
+    let (hrp, data) = match value.rsplit_once('1') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    if hrp.is_empty() || hrp != prefix.to_ascii_lowercase() ||
+            !hrp.bytes().all(|c| (33..=126).contains(&c)) {
+        return false;
+    }
+
+    let data: Option<Vec<u8>> = data.bytes()
+        .map(|c| BECH32_CHARSET.iter().position(|&d| d == c).map(|d| d as u8))
+        .collect();
+    let data = match data {
+        Some(data) if data.len() > BECH32_CHECKSUM_LENGTH => data,
+        _ => return false,
+    };
+
+    let expanded = hrp.bytes().map(|c| c >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|c| c & 0x1f))
+        .chain(data);
+    bech32_polymod(expanded) == 1
+}
+
+/// Return EVM address with EIP-55 checksum (None if not a hex address)
+pub fn to_checksum_address(value: &str) -> Option<String> {
+    let hex = value.strip_prefix("0x")?;
+    if hex.len() != 40 || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let hex = hex.to_ascii_lowercase();
+    let hash = Keccak256::digest(hex.as_bytes());
+    let checksummed = hex.chars().enumerate().map(|(i, c)| {
+        let nibble = (hash[i / 2] >> (4 * (1 - i % 2))) & 0x0f;
+        if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+    });
+    Some(std::iter::once('0').chain(std::iter::once('x')).chain(checksummed).collect())
+}
+
+/// Return whether value is a hex EVM address with a valid EIP-55 checksum
+///
+/// Addresses without checksum (all lowercase or all uppercase) are not
+/// valid since mistyped addresses cannot be detected.
+pub fn is_valid_evm_address(value: &str) -> bool {
+    to_checksum_address(value).as_deref() == Some(value)
+}
+
+/// Return whether value is an absolute URL with scheme and host
+pub fn is_valid_url(value: &str) -> bool {
+    let (scheme, rest) = match value.split_once("://") {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    let mut scheme_chars = scheme.chars();
+    let scheme_is_valid = matches!(scheme_chars.next(), Some(c) if c.is_ascii_alphabetic()) &&
+        scheme_chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !scheme_is_valid {
+        return false;
+    }
+
+    if !value.chars().all(|c| c.is_ascii_graphic() || !c.is_ascii()) {
+        return false;
+    }
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    !host.is_empty() && !host.starts_with(':')
+}
+
+/// Multicodec codes of content hash namespaces (see EIP-1577)
+const IPFS_NS: u64 = 0xe3;
+const SWARM_NS: u64 = 0xe4;
+const IPNS_NS: u64 = 0xe5;
+const ONION: u64 = 0x01bc;
+const ONION3: u64 = 0x01bd;
+const SKYNET_NS: u64 = 0xb19910;
+const ARWEAVE_NS: u64 = 0xb29910;
+
+/// Read an unsigned varint from the beginning of data
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value: u64 = 0;
+    for i in 0..9 {
+        let (&byte, rest) = data.split_first()?;
+        *data = rest;
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Return whether data is a version 1 CID with a complete multihash
+fn is_valid_cid(mut data: &[u8]) -> bool {
+    let version = read_varint(&mut data);
+    let content_codec = read_varint(&mut data);
+    let hash_function = read_varint(&mut data);
+    let digest_length = read_varint(&mut data);
+    match (version, content_codec, hash_function, digest_length) {
+        (Some(1), Some(_), Some(_), Some(length)) => {
+            length > 0 && data.len() as u64 == length
+        },
+        _ => false,
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| value.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+/// Return whether value is a hex encoded content hash (see EIP-1577)
+///
+/// The value is prefixed by `0x` and starts with the multicodec code of the
+/// namespace. IPFS, IPNS and Swarm hashes must contain a version 1 CID.
+pub fn is_valid_content_hash(value: &str) -> bool {
+    let data = match value.strip_prefix("0x").and_then(decode_hex) {
+        Some(data) => data,
+        None => return false,
+    };
+    let mut content = data.as_slice();
+    let codec = match read_varint(&mut content) {
+        Some(codec) => codec,
+        None => return false,
+    };
+
+    match codec {
+        IPFS_NS | IPNS_NS | SWARM_NS => is_valid_cid(content),
+        ONION => content.len() == 16,
+        ONION3 => content.len() == 56,
+        SKYNET_NS | ARWEAVE_NS => !content.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bech32_checksum() {
+        assert!(is_valid_bech32("cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu", "cosmos"));
+        assert!(is_valid_bech32("ABCDEF1QPZRY9X8GF2TVDW0S3JN54KHCE6MUA7LMQQQXW", "abcdef"));
+        assert!(is_valid_bech32("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw", "abcdef"));
+        assert!(is_valid_bech32("terra1x46rqay4d3cssq8gxxvqz8xt6nwlz4td20k38v", "terra"));
+
+        // Wrong prefix, mistyped character and mixed case
+        assert!(!is_valid_bech32("cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu", "osmo"));
+        assert!(!is_valid_bech32("cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xv", "cosmos"));
+        assert!(!is_valid_bech32("Cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu", "cosmos"));
+        assert!(!is_valid_bech32("cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xb", "cosmos"));
+        assert!(!is_valid_bech32("cosmos1", "cosmos"));
+        assert!(!is_valid_bech32("cosmosqypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu", "cosmos"));
+    }
+
+    #[test]
+    fn keccak256_hash() {
+        // Ethereum uses Keccak-256, not the standardized SHA3-256
+        let vectors: [(&[u8], &str); 3] = [
+            (b"", "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+            (
+                b"The quick brown fox jumps over the lazy dog",
+                "4d741b6f1eb29cb2a9b9911c82f56fa8d73b04959d3d9d222895df6c0b28aa15",
+            ),
+            // Input longer than one block
+            (&[b'a'; 200], "96ea54061def936c4be90b518992fdc6f12f535068a256229aca54267b4d084d"),
+        ];
+        for (data, hash) in vectors {
+            assert_eq!(Keccak256::digest(data).to_vec(), decode_hex(hash).unwrap());
+        }
+    }
+
+    #[test]
+    fn evm_address_checksum() {
+        let addresses = [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+            "0x52908400098527886E0F7030069857D2E4169EE7",
+            "0xde709f2102306220921060314715629080e2fb77",
+        ];
+        for address in addresses {
+            assert!(is_valid_evm_address(address), "{}", address);
+            assert_eq!(to_checksum_address(&address.to_ascii_lowercase()).as_deref(), Some(address));
+        }
+
+        assert!(!is_valid_evm_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"));
+        assert!(!is_valid_evm_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"));
+        assert!(!is_valid_evm_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA"));
+        assert!(!is_valid_evm_address("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+        assert!(!is_valid_evm_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg"));
+    }
+
+    #[test]
+    fn url_format() {
+        assert!(is_valid_url("https://example.com"));
+        assert!(is_valid_url("https://user@example.com:8080/path?query#fragment"));
+        assert!(is_valid_url("ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"));
+
+        assert!(!is_valid_url("example.com"));
+        assert!(!is_valid_url("https://"));
+        assert!(!is_valid_url("https:///path"));
+        assert!(!is_valid_url("https://:8080"));
+        assert!(!is_valid_url("https://exa mple.com"));
+        assert!(!is_valid_url("1https://example.com"));
+    }
+
+    #[test]
+    fn content_hash_format() {
+        // IPFS, Swarm, IPNS and onion examples from EIP-1577 and ENS
+        assert!(is_valid_content_hash(
+            "0xe3010170122029f2d17be6139079dc48696d1f582a8530eb9805b561eda517e22a892c7e3f1f"
+        ));
+        assert!(is_valid_content_hash(
+            "0xe40101fa011b20d1de9994b4d039f6548d191eb26786769f580809256b4685ef316805265ea162"
+        ));
+        assert!(is_valid_content_hash(
+            "0xe5010172002408011220a7448dcfc00e746c22e238de5c1e3b6fb97bae0949e47741b4e0ae8e929abd4f"
+        ));
+        assert!(is_valid_content_hash("0xbc037a716b746c7769756176767671717434"));
+
+        // Truncated digest, unknown codec, missing prefix and invalid hex
+        assert!(!is_valid_content_hash(
+            "0xe3010170122029f2d17be6139079dc48696d1f582a8530eb9805b561eda517e22a892c7e3f"
+        ));
+        assert!(!is_valid_content_hash("0xe6010170122029f2"));
+        assert!(!is_valid_content_hash(
+            "e3010170122029f2d17be6139079dc48696d1f582a8530eb9805b561eda517e22a892c7e3f1f"
+        ));
+        assert!(!is_valid_content_hash("0xe3zz"));
+        assert!(!is_valid_content_hash("0x"));
+    }
+}
@@ -0,0 +1,452 @@
+//! Integration test harness for the terranames contracts
+//!
+//! The auction, resolver and root collector contracts are wired together
+//! with a CW20 base token in an in-process chain simulated by cw-multi-test.
+//! The simulator does not know about Terra custom queries so each contract
+//! entry point is wrapped to answer tax queries from a TaxQuerier stand-in.
+//! Note that the simulated bank does not charge the tax, so the tax deducted
+//! by a contract stays in the balance of that contract.
+
+use anyhow::Result as AnyResult;
+use cosmwasm_std::testing::{mock_env, MockApi, MockStorage};
+use cosmwasm_std::{
+    coins, from_slice, to_binary, Addr, Binary, Decimal, Deps, DepsMut, Empty,
+    Env, MessageInfo, Querier, QuerierResult, QuerierWrapper, QueryRequest,
+    Response, StdResult, Uint128,
+};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw_multi_test::{App, AppResponse, BankKeeper, Contract, ContractWrapper, Executor};
+use terra_cosmwasm::TerraQueryWrapper;
+
+use terranames::auction::{
+    ExecuteMsg as AuctionExecuteMsg, InstantiateMsg as AuctionInstantiateMsg,
+    NameStateResponse, QueryMsg as AuctionQueryMsg, RecipientKind, RecipientMsg,
+};
+use terranames::resolver::{
+    ExecuteMsg as ResolverExecuteMsg, InstantiateMsg as ResolverInstantiateMsg,
+    QueryMsg as ResolverQueryMsg, ResolveNameResponse,
+};
+use terranames::root_collector::{
+    ExecuteMsg as RootCollectorExecuteMsg,
+    InstantiateMsg as RootCollectorInstantiateMsg,
+    QueryMsg as RootCollectorQueryMsg, ReceiveMsg as RootCollectorReceiveMsg,
+    StakeStateResponse,
+};
+use terranames::tax::TaxPolicy;
+use terranames::terra::deduct_tax;
+use terranames::testing::terra::TaxQuerier;
+use terranames::utils::Timedelta;
+
+pub const STABLE_DENOM: &str = "uusd";
+pub const ADMIN: &str = "admin";
+
+/// Initial token balance of each account in TOKEN_HOLDERS
+pub const INITIAL_TOKENS: u128 = 1_000_000_000;
+pub const TOKEN_HOLDERS: [&str; 2] = ["staker1", "staker2"];
+
+pub const UNSTAKE_DELAY: Timedelta = Timedelta::from_seconds(1_209_600); // 2 weeks
+
+/// Tax applied by the stand-in for the Terra treasury module
+pub fn tax_querier() -> TaxQuerier {
+    TaxQuerier::new(
+        Decimal::permille(4),
+        &[(STABLE_DENOM, &Uint128::new(1_000_000))],
+    )
+}
+
+/// Querier answering Terra tax queries and forwarding everything else
+struct TerraQuerier<'a> {
+    base: QuerierWrapper<'a>,
+    tax_querier: TaxQuerier,
+}
+
+impl Querier for TerraQuerier<'_> {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        if let Ok(request) = from_slice::<QueryRequest<TerraQueryWrapper>>(bin_request) {
+            if let Some(res) = self.tax_querier.handle_query(&request) {
+                return res;
+            }
+        }
+        self.base.raw_query(bin_request)
+    }
+}
+
+fn with_terra_querier<R>(
+    base: QuerierWrapper,
+    f: impl FnOnce(QuerierWrapper) -> R,
+) -> R {
+    let querier = TerraQuerier { base, tax_querier: tax_querier() };
+    f(QuerierWrapper::new(&querier))
+}
+
+macro_rules! terra_contract {
+    ($contract:ident, $name:ident) => {
+        fn $name() -> Box<dyn Contract<Empty>> {
+            fn execute(
+                deps: DepsMut, env: Env, info: MessageInfo,
+                msg: $contract::msg::ExecuteMsg,
+            ) -> Result<Response, String> {
+                let DepsMut { storage, api, querier } = deps;
+                with_terra_querier(querier, |querier| {
+                    let deps = DepsMut { storage, api, querier };
+                    $contract::contract::execute(deps, env, info, msg)
+                        .map_err(|err| err.to_string())
+                })
+            }
+
+            fn instantiate(
+                deps: DepsMut, env: Env, info: MessageInfo,
+                msg: $contract::msg::InstantiateMsg,
+            ) -> Result<Response, String> {
+                let DepsMut { storage, api, querier } = deps;
+                with_terra_querier(querier, |querier| {
+                    let deps = DepsMut { storage, api, querier };
+                    $contract::contract::instantiate(deps, env, info, msg)
+                        .map_err(|err| err.to_string())
+                })
+            }
+
+            fn query(
+                deps: Deps, env: Env, msg: $contract::msg::QueryMsg,
+            ) -> Result<Binary, String> {
+                with_terra_querier(deps.querier, |querier| {
+                    let deps = Deps { querier, ..deps };
+                    $contract::contract::query(deps, env, msg)
+                        .map_err(|err| err.to_string())
+                })
+            }
+
+            Box::new(ContractWrapper::new(execute, instantiate, query))
+        }
+    };
+}
+
+mod auction {
+    pub use terranames::auction as msg;
+    pub use terranames_auction::contract;
+}
+
+mod resolver {
+    pub use terranames::resolver as msg;
+    pub use terranames_resolver::contract;
+}
+
+mod root_collector {
+    pub use terranames::root_collector as msg;
+    pub use terranames_root_collector::contract;
+}
+
+terra_contract!(auction, auction_contract);
+terra_contract!(resolver, resolver_contract);
+terra_contract!(root_collector, root_collector_contract);
+
+fn token_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    ))
+}
+
+/// Default auction config where all funds are deposited in the root collector
+pub fn auction_instantiate_msg(root_collector: &Addr) -> AuctionInstantiateMsg {
+    AuctionInstantiateMsg {
+        recipients: vec![
+            RecipientMsg {
+                addr: root_collector.to_string(),
+                share: Decimal::one(),
+                kind: RecipientKind::Deposit,
+            },
+        ],
+        stable_denom: STABLE_DENOM.into(),
+        min_lease_secs: Timedelta::from_seconds(15_778_476), // 6 months
+        max_lease_secs: Timedelta::from_seconds(157_784_760), // 5 years
+        counter_delay_secs: Timedelta::from_seconds(604_800), // 1 week
+        transition_delay_secs: Timedelta::from_seconds(1_814_400), // 3 weeks
+        bid_delay_secs: Timedelta::from_seconds(15_778_476), // 6 months
+        grace_period_secs: Timedelta::from_seconds(1_209_600), // 2 weeks
+        grace_fee_secs: Timedelta::from_seconds(604_800), // 1 week
+        referral_share: Decimal::zero(),
+        min_rate: Uint128::zero(),
+        retention_secs: Timedelta::zero(),
+        keeper_reward: Uint128::zero(),
+        tax_policy: TaxPolicy::Terra {},
+    }
+}
+
+/// Simulated chain with all contracts deployed
+pub struct Suite {
+    pub app: App,
+    pub token: Addr,
+    pub auction: Addr,
+    pub resolver: Addr,
+    pub root_collector: Addr,
+}
+
+impl Suite {
+    /// Deploy contracts and give each account the stablecoin balance
+    pub fn new(accounts: &[(&str, u128)]) -> Suite {
+        let mut app = App::new(
+            MockApi::default(),
+            mock_env().block,
+            BankKeeper::new(),
+            MockStorage::new(),
+        );
+        for (account, amount) in accounts {
+            app.init_bank_balance(&Addr::unchecked(*account), coins(*amount, STABLE_DENOM))
+                .unwrap();
+        }
+
+        let admin = Addr::unchecked(ADMIN);
+        let token_code_id = app.store_code(token_contract());
+        let auction_code_id = app.store_code(auction_contract());
+        let resolver_code_id = app.store_code(resolver_contract());
+        let root_collector_code_id = app.store_code(root_collector_contract());
+
+        let token = app.instantiate_contract(
+            token_code_id,
+            admin.clone(),
+            &cw20_base::msg::InstantiateMsg {
+                name: "Terranames".into(),
+                symbol: "TNS".into(),
+                decimals: 6,
+                initial_balances: TOKEN_HOLDERS.iter()
+                    .map(|address| Cw20Coin {
+                        address: address.to_string(),
+                        amount: Uint128::new(INITIAL_TOKENS),
+                    })
+                    .collect(),
+                mint: None,
+                marketing: None,
+            },
+            &[],
+            "token",
+            None,
+        ).unwrap();
+
+        let root_collector = app.instantiate_contract(
+            root_collector_code_id,
+            admin.clone(),
+            &RootCollectorInstantiateMsg {
+                base_token: token.to_string(),
+                stable_denom: STABLE_DENOM.into(),
+                unstake_delay: UNSTAKE_DELAY,
+                tax_policy: TaxPolicy::Terra {},
+            },
+            &[],
+            "root_collector",
+            None,
+        ).unwrap();
+
+        let auction = app.instantiate_contract(
+            auction_code_id,
+            admin.clone(),
+            &auction_instantiate_msg(&root_collector),
+            &[],
+            "auction",
+            None,
+        ).unwrap();
+
+        let resolver = app.instantiate_contract(
+            resolver_code_id,
+            admin.clone(),
+            &ResolverInstantiateMsg {
+                auction_contract: auction.to_string(),
+                record_validations: vec![],
+            },
+            &[],
+            "resolver",
+            None,
+        ).unwrap();
+
+        app.execute_contract(
+            admin,
+            auction.clone(),
+            &AuctionExecuteMsg::SetResolverContract {
+                resolver_contract: Some(resolver.to_string()),
+            },
+            &[],
+        ).unwrap();
+
+        Suite { app, token, auction, resolver, root_collector }
+    }
+
+    /// Move block time forward
+    pub fn advance_time(&mut self, seconds: u64) {
+        self.app.update_block(|block| {
+            block.time = block.time.plus_seconds(seconds);
+            block.height += seconds / 5;
+        });
+    }
+
+    /// Return amount left after tax is deducted by a contract
+    pub fn deduct_tax(&self, amount: u128) -> Uint128 {
+        with_terra_querier(self.app.wrap(), |querier| {
+            deduct_tax(&querier, STABLE_DENOM, Uint128::new(amount))
+        }).unwrap()
+    }
+
+    /// Return stablecoin balance of address
+    pub fn balance(&self, address: &str) -> Uint128 {
+        self.app.wrap().query_balance(address, STABLE_DENOM).unwrap().amount
+    }
+
+    /// Return token balance of address
+    pub fn token_balance(&self, address: &str) -> Uint128 {
+        let response: BalanceResponse = self.app.wrap().query_wasm_smart(
+            &self.token,
+            &Cw20QueryMsg::Balance { address: address.into() },
+        ).unwrap();
+        response.balance
+    }
+
+    pub fn bid(
+        &mut self,
+        sender: &str,
+        name: &str,
+        rate: u128,
+        deposit: u128,
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.auction.clone(),
+            &AuctionExecuteMsg::BidName {
+                name: name.into(),
+                rate: Uint128::new(rate),
+                referrer: None,
+            },
+            &coins(deposit, STABLE_DENOM),
+        )
+    }
+
+    pub fn fund(
+        &mut self,
+        sender: &str,
+        name: &str,
+        owner: &str,
+        deposit: u128,
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.auction.clone(),
+            &AuctionExecuteMsg::FundName {
+                name: name.into(),
+                owner: owner.into(),
+                referrer: None,
+            },
+            &coins(deposit, STABLE_DENOM),
+        )
+    }
+
+    pub fn set_controller(
+        &mut self,
+        sender: &str,
+        name: &str,
+        controller: Option<&str>,
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.auction.clone(),
+            &AuctionExecuteMsg::SetNameController {
+                name: name.into(),
+                controller: controller.map(String::from),
+            },
+            &[],
+        )
+    }
+
+    pub fn prune(&mut self, sender: &str, names: &[&str]) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.auction.clone(),
+            &AuctionExecuteMsg::PruneExpired {
+                names: names.iter().map(|name| name.to_string()).collect(),
+            },
+            &[],
+        )
+    }
+
+    pub fn name_state(&self, name: &str) -> StdResult<NameStateResponse> {
+        self.app.wrap().query_wasm_smart(
+            &self.auction,
+            &AuctionQueryMsg::GetNameState { name: name.into() },
+        )
+    }
+
+    pub fn set_value(
+        &mut self,
+        sender: &str,
+        name: &str,
+        value: Option<&str>,
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.resolver.clone(),
+            &ResolverExecuteMsg::SetNameValue {
+                name: name.into(),
+                value: value.map(String::from),
+            },
+            &[],
+        )
+    }
+
+    pub fn resolve(&self, name: &str) -> StdResult<ResolveNameResponse> {
+        self.app.wrap().query_wasm_smart(
+            &self.resolver,
+            &ResolverQueryMsg::ResolveName { name: name.into() },
+        )
+    }
+
+    /// Stake tokens in the root collector
+    pub fn stake(&mut self, sender: &str, amount: u128) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.token.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: self.root_collector.to_string(),
+                amount: Uint128::new(amount),
+                msg: to_binary(&RootCollectorReceiveMsg::Stake {}).unwrap(),
+            },
+            &[],
+        )
+    }
+
+    pub fn unstake(&mut self, sender: &str, amount: u128) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.root_collector.clone(),
+            &RootCollectorExecuteMsg::UnstakeTokens {
+                amount: Uint128::new(amount),
+            },
+            &[],
+        )
+    }
+
+    pub fn withdraw_tokens(&mut self, sender: &str, amount: u128) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.root_collector.clone(),
+            &RootCollectorExecuteMsg::WithdrawTokens {
+                amount: Uint128::new(amount),
+                to: None,
+            },
+            &[],
+        )
+    }
+
+    pub fn withdraw_dividends(&mut self, sender: &str) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.root_collector.clone(),
+            &RootCollectorExecuteMsg::WithdrawDividends { to: None },
+            &[],
+        )
+    }
+
+    pub fn stake_state(&self, address: &str) -> StdResult<StakeStateResponse> {
+        self.app.wrap().query_wasm_smart(
+            &self.root_collector,
+            &RootCollectorQueryMsg::StakeState { address: address.into() },
+        )
+    }
+}
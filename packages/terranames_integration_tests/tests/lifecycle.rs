@@ -0,0 +1,169 @@
+use cosmwasm_std::Uint128;
+
+use terranames_integration_tests::{Suite, INITIAL_TOKENS, UNSTAKE_DELAY};
+
+const INITIAL_BALANCE: u128 = 10_000_000;
+
+fn suite() -> Suite {
+    Suite::new(&[
+        ("alice", INITIAL_BALANCE),
+        ("bob", INITIAL_BALANCE),
+    ])
+}
+
+/// Assert that no stablecoins were created or destroyed
+fn assert_balances_conserved(suite: &Suite) {
+    let accounts = [
+        "alice",
+        "bob",
+        "staker1",
+        "staker2",
+        suite.auction.as_str(),
+        suite.root_collector.as_str(),
+    ];
+    let total: Uint128 = accounts.iter().map(|account| suite.balance(account)).sum();
+    assert_eq!(total, Uint128::new(2 * INITIAL_BALANCE));
+}
+
+#[test]
+fn bid_deposit_is_paid_out_to_stakers() {
+    let mut suite = suite();
+
+    suite.stake("staker1", 1_000_000).unwrap();
+    suite.stake("staker2", 3_000_000).unwrap();
+    assert_eq!(suite.token_balance("staker1"), Uint128::new(INITIAL_TOKENS - 1_000_000));
+    assert_eq!(suite.token_balance(suite.root_collector.as_str()), Uint128::new(4_000_000));
+
+    suite.bid("alice", "example", 1_000, 200_000).unwrap();
+    assert_eq!(suite.balance("alice"), Uint128::new(INITIAL_BALANCE - 200_000));
+
+    // Deposit minus tax is forwarded to the root collector
+    let collected = suite.deduct_tax(200_000);
+    assert_eq!(suite.balance(suite.root_collector.as_str()), collected);
+    assert_eq!(
+        suite.balance(suite.auction.as_str()),
+        Uint128::new(200_000) - collected,
+    );
+
+    // Dividends are split by stake
+    let dividend1 = suite.stake_state("staker1").unwrap().dividend;
+    let dividend2 = suite.stake_state("staker2").unwrap().dividend;
+    assert_eq!(dividend1, collected.multiply_ratio(1u128, 4u128));
+    assert_eq!(dividend2, collected.multiply_ratio(3u128, 4u128));
+
+    suite.withdraw_dividends("staker1").unwrap();
+    suite.withdraw_dividends("staker2").unwrap();
+    assert_eq!(suite.balance("staker1"), suite.deduct_tax(dividend1.u128()));
+    assert_eq!(suite.balance("staker2"), suite.deduct_tax(dividend2.u128()));
+    assert_eq!(suite.stake_state("staker1").unwrap().dividend, Uint128::zero());
+    assert!(suite.withdraw_dividends("staker1").is_err());
+
+    assert_balances_conserved(&suite);
+}
+
+#[test]
+fn counter_bid_refunds_previous_bidder() {
+    let mut suite = suite();
+
+    suite.bid("alice", "example", 1_000, 200_000).unwrap();
+    suite.advance_time(86_400);
+
+    // Counter-bid with lower deposit than what is left fails
+    assert!(suite.bid("bob", "example", 2_000, 199_000).is_err());
+    assert_eq!(suite.balance("bob"), Uint128::new(INITIAL_BALANCE));
+
+    suite.bid("bob", "example", 2_000, 400_000).unwrap();
+
+    // Alice spent one day at rate 1000 and gets the rest back
+    let refund = 200_000 - 1_000;
+    assert_eq!(
+        suite.balance("alice"),
+        Uint128::new(INITIAL_BALANCE - 200_000) + suite.deduct_tax(refund),
+    );
+    assert_eq!(suite.balance("bob"), Uint128::new(INITIAL_BALANCE - 400_000));
+    assert_eq!(
+        suite.balance(suite.root_collector.as_str()),
+        suite.deduct_tax(200_000) + suite.deduct_tax(400_000 - refund),
+    );
+
+    let name_state = suite.name_state("example").unwrap();
+    assert_eq!(name_state.bid_owner.as_ref().map(|addr| addr.as_str()), Some("bob"));
+    assert_eq!(name_state.rate, Uint128::new(2_000));
+
+    assert_balances_conserved(&suite);
+}
+
+#[test]
+fn resolver_follows_auction_ownership() {
+    let mut suite = suite();
+
+    suite.bid("alice", "example", 1_000, 200_000).unwrap();
+
+    // Ownership is not assigned until the counter delay ends
+    assert!(suite.set_controller("alice", "example", Some("alice")).is_err());
+    suite.advance_time(604_800);
+
+    // Owner has to assign a controller before values can be set
+    assert!(suite.set_value("alice", "example", Some("alice-value")).is_err());
+    suite.set_controller("alice", "example", Some("alice")).unwrap();
+    suite.set_value("alice", "example", Some("alice-value")).unwrap();
+    assert!(suite.set_value("bob", "example", Some("bob-value")).is_err());
+
+    let response = suite.resolve("example").unwrap();
+    assert_eq!(response.value.as_deref(), Some("alice-value"));
+    assert_eq!(response.owner.as_str(), "alice");
+    assert!(!response.lapsed);
+
+    // Owner can renew during the grace period
+    let expire_time = suite.name_state("example").unwrap().expire_time.unwrap();
+    let now = suite.app.block_info().time.seconds();
+    suite.advance_time(expire_time.value() - now);
+    let response = suite.resolve("example").unwrap();
+    assert!(response.lapsed);
+    assert!(suite.bid("bob", "example", 2_000, 400_000).is_err());
+    suite.fund("alice", "example", "alice", 20_000).unwrap();
+    assert!(!suite.resolve("example").unwrap().lapsed);
+
+    // After the grace period the name is pruned along with the value
+    let expire_time = suite.name_state("example").unwrap().expire_time.unwrap();
+    let now = suite.app.block_info().time.seconds();
+    suite.advance_time(expire_time.value() - now + 1_209_600);
+    suite.prune("keeper", &["example"]).unwrap();
+    assert!(suite.name_state("example").is_err());
+    assert!(suite.resolve("example").is_err());
+
+    // Name is open for new bids
+    suite.bid("bob", "example", 2_000, 400_000).unwrap();
+    suite.advance_time(604_800);
+    suite.set_controller("bob", "example", Some("bob")).unwrap();
+    suite.set_value("bob", "example", Some("bob-value")).unwrap();
+    let response = suite.resolve("example").unwrap();
+    assert_eq!(response.value.as_deref(), Some("bob-value"));
+    assert_eq!(response.owner.as_str(), "bob");
+
+    assert_balances_conserved(&suite);
+}
+
+#[test]
+fn unstake_and_withdraw_tokens() {
+    let mut suite = suite();
+
+    suite.stake("staker1", 1_000_000).unwrap();
+    suite.unstake("staker1", 400_000).unwrap();
+
+    // Tokens are locked until the unstake delay has passed
+    assert!(suite.withdraw_tokens("staker1", 400_000).is_err());
+    suite.advance_time(UNSTAKE_DELAY.value());
+    assert!(suite.withdraw_tokens("staker1", 400_001).is_err());
+    suite.withdraw_tokens("staker1", 400_000).unwrap();
+
+    assert_eq!(suite.token_balance("staker1"), Uint128::new(INITIAL_TOKENS - 600_000));
+    assert_eq!(suite.token_balance(suite.root_collector.as_str()), Uint128::new(600_000));
+
+    // Remaining stake still earns dividends
+    suite.bid("alice", "example", 1_000, 200_000).unwrap();
+    assert_eq!(
+        suite.stake_state("staker1").unwrap().dividend,
+        suite.deduct_tax(200_000),
+    );
+}